@@ -0,0 +1,171 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Size the archive at `path` takes once unpacked, read from its own
+/// headers without unpacking anything: the central directory of a zip, the
+/// trailer of a gzip (mod 4 GiB, as gzip keeps it). `None` for anything
+/// else, or an archive which can't be read.
+pub fn uncompressed_size(path: &Path) -> Option<u64> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "zip" | "jar" | "war" | "apk" => self::zip(path),
+        "gz" | "tgz" => self::gzip(path),
+        _ => None,
+    }
+}
+
+/// Sum of the uncompressed sizes in the central directory. Zip64 archives
+/// aren't read.
+fn zip(path: &Path) -> Option<u64> {
+    const END: u32 = 0x0605_4b50;
+    const ENTRY: u32 = 0x0201_4b50;
+    // the end record and the longest comment it can have
+    const TAIL: u64 = 22 + 0xffff;
+
+    let mut file = File::open(path).ok()?;
+    let length = file.metadata().ok()?.len();
+    let start = length.saturating_sub(TAIL);
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+
+    let end = (0..tail.len().checked_sub(21)?)
+        .rev()
+        .find(|&i| self::u32_at(&tail, i) == Some(END))?;
+    let entries = self::u16_at(&tail, end + 10)?;
+    let size = self::u32_at(&tail, end + 12)?;
+    let offset = self::u32_at(&tail, end + 16)?;
+    if offset == u32::MAX || size == u32::MAX {
+        return None;
+    }
+    // the directory comes before the end record, whatever the header says
+    if u64::from(offset) + u64::from(size) > start + end as u64 {
+        return None;
+    }
+
+    let mut directory = Vec::new();
+    file.seek(SeekFrom::Start(u64::from(offset))).ok()?;
+    (&mut file)
+        .take(u64::from(size))
+        .read_to_end(&mut directory)
+        .ok()?;
+    let mut total = 0;
+    let mut at = 0;
+    for _ in 0..entries {
+        if self::u32_at(&directory, at)? != ENTRY {
+            return None;
+        }
+        let uncompressed = self::u32_at(&directory, at + 24)?;
+        if uncompressed == u32::MAX {
+            return None;
+        }
+        total += u64::from(uncompressed);
+        let name = self::u16_at(&directory, at + 28)? as usize;
+        let extra = self::u16_at(&directory, at + 30)? as usize;
+        let comment = self::u16_at(&directory, at + 32)? as usize;
+        at += 46 + name + extra + comment;
+    }
+    Some(total)
+}
+
+/// The size kept in the last 4 bytes of a gzip stream.
+fn gzip(path: &Path) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    let mut magic = [0; 2];
+    file.read_exact(&mut magic).ok()?;
+    if magic != [0x1f, 0x8b] {
+        return None;
+    }
+    file.seek(SeekFrom::End(-4)).ok()?;
+    let mut size = [0; 4];
+    file.read_exact(&mut size).ok()?;
+    Some(u64::from(u32::from_le_bytes(size)))
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A zip of `files` stored as is, CRCs left out as nothing here reads
+    /// them.
+    fn zip_of(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let (mut bytes, mut directory) = (Vec::new(), Vec::new());
+        for (name, content) in files {
+            let offset = bytes.len() as u32;
+            let size = content.len() as u32;
+            bytes.extend(0x0403_4b50u32.to_le_bytes());
+            bytes.extend([20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            bytes.extend(size.to_le_bytes());
+            bytes.extend(size.to_le_bytes());
+            bytes.extend((name.len() as u16).to_le_bytes());
+            bytes.extend([0, 0]);
+            bytes.extend(name.as_bytes());
+            bytes.extend(*content);
+
+            directory.extend(0x0201_4b50u32.to_le_bytes());
+            directory.extend([20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            directory.extend(size.to_le_bytes());
+            directory.extend(size.to_le_bytes());
+            directory.extend((name.len() as u16).to_le_bytes());
+            directory.extend([0; 12]);
+            directory.extend(offset.to_le_bytes());
+            directory.extend(name.as_bytes());
+        }
+        let offset = bytes.len() as u32;
+        bytes.extend(&directory);
+        bytes.extend(0x0605_4b50u32.to_le_bytes());
+        bytes.extend([0, 0, 0, 0]);
+        bytes.extend((files.len() as u16).to_le_bytes());
+        bytes.extend((files.len() as u16).to_le_bytes());
+        bytes.extend((directory.len() as u32).to_le_bytes());
+        bytes.extend(offset.to_le_bytes());
+        bytes.extend([0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn archive_sizes() {
+        let root = tempfile::tempdir().unwrap();
+
+        let zip = root.path().join("artifacts.zip");
+        fs::write(
+            &zip,
+            zip_of(&[("a.txt", b"hello"), ("b/c.bin", &[7; 1000])]),
+        )
+        .unwrap();
+        assert_eq!(uncompressed_size(&zip), Some(1005));
+
+        // header, no data to speak of, then CRC and size
+        let gz = root.path().join("logs.tar.gz");
+        let mut bytes = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3, 3, 0];
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend(10_240u32.to_le_bytes());
+        fs::write(&gz, bytes).unwrap();
+        assert_eq!(uncompressed_size(&gz), Some(10_240));
+
+        // not an archive after all, or not one read here
+        let fake = root.path().join("fake.zip");
+        fs::write(&fake, "not a zip").unwrap();
+        assert_eq!(uncompressed_size(&fake), None);
+        assert_eq!(uncompressed_size(&root.path().join("notes.txt")), None);
+
+        // a directory said to be far larger than the archive itself
+        let mut bytes = zip_of(&[("a.txt", b"hello")]);
+        let size = bytes.len() - 10;
+        bytes[size..size + 4].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+        fs::write(&zip, bytes).unwrap();
+        assert_eq!(uncompressed_size(&zip), None);
+    }
+}
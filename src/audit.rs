@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// One line of the audit log, which every run appends to and none
+/// truncates.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Record {
+    /// seconds since the Unix epoch.
+    pub time: u64,
+    pub user: String,
+    /// `config_hash` of the config which did it alone, none when the run
+    /// ran a plan or a manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    pub path: PathBuf,
+    pub action: Action,
+    /// `ok`, or the error it failed with.
+    pub result: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Remove,
+    Quarantine,
+    /// listed by a dry-run, nothing done.
+    WouldRemove,
+}
+
+impl Record {
+    pub fn new(
+        config_hash: Option<&str>,
+        path: &Path,
+        action: Action,
+        result: Result<(), String>,
+    ) -> Self {
+        Self {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            user: self::user(),
+            config_hash: config_hash.map(String::from),
+            path: path.to_path_buf(),
+            action,
+            result: result.err().unwrap_or_else(|| String::from("ok")),
+        }
+    }
+}
+
+/// Append `record` to the log at `log` as a JSON line. The file is locked
+/// meanwhile, for runs at the same time not to mix their lines.
+pub fn append(log: &Path, record: &Record) -> crate::Result<()> {
+    if let Some(parent) = log.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(log)?;
+    file.lock()?;
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    file.write_all(line.as_bytes())?;
+    // closing the file releases the lock
+    Ok(())
+}
+
+/// Name of the user running, as the system knows it where it can tell.
+fn user() -> String {
+    #[cfg(unix)]
+    if let Ok(Some(user)) = nix::unistd::User::from_uid(nix::unistd::Uid::current()) {
+        return user.name;
+    }
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
@@ -1,27 +1,477 @@
+use crate::error::{AppError, AppErrorKind};
 use clap::ValueEnum;
-use serde::Deserialize;
-use std::path::PathBuf;
+use regex::Regex;
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{self, SeqAccess, Visitor},
+};
+use serde_json::Value;
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::Read,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
 
-#[derive(Deserialize, Debug, PartialEq, Clone, ValueEnum)]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone, ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum Kind {
+    #[default]
+    #[serde(alias = "Folder")]
     Folder,
+    #[serde(alias = "File")]
     File,
 }
 
-impl Default for Kind {
-    fn default() -> Self {
-        Self::Folder
+/// A pattern of a config, for items of its own `kind` when it has one and
+/// of the config's otherwise. Written as a plain string in the latter case,
+/// as `{ "pattern": "tmp", "kind": "file" }` in the former.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(from = "RawPattern", into = "RawPattern")]
+pub struct Pattern {
+    pub pattern: String,
+    pub kind: Option<Kind>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawPattern {
+    Plain(String),
+    Typed { pattern: String, kind: Kind },
+}
+
+impl From<RawPattern> for Pattern {
+    fn from(raw: RawPattern) -> Self {
+        match raw {
+            RawPattern::Plain(pattern) => Self {
+                pattern,
+                kind: None,
+            },
+            RawPattern::Typed { pattern, kind } => Self {
+                pattern,
+                kind: Some(kind),
+            },
+        }
+    }
+}
+
+impl From<Pattern> for RawPattern {
+    fn from(pattern: Pattern) -> Self {
+        match pattern.kind {
+            Some(kind) => Self::Typed {
+                pattern: pattern.pattern,
+                kind,
+            },
+            None => Self::Plain(pattern.pattern),
+        }
+    }
+}
+
+impl From<String> for Pattern {
+    fn from(pattern: String) -> Self {
+        Self {
+            pattern,
+            kind: None,
+        }
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(pattern: &str) -> Self {
+        Self::from(pattern.to_string())
+    }
+}
+
+impl PartialEq<&str> for Pattern {
+    fn eq(&self, other: &&str) -> bool {
+        self.kind.is_none() && self.pattern == *other
+    }
+}
+
+impl Pattern {
+    /// The kind of item the pattern is for, under a config of `kind`.
+    pub fn kind_or<'a>(&'a self, kind: &'a Kind) -> &'a Kind {
+        self.kind.as_ref().unwrap_or(kind)
+    }
+
+    /// A folder pattern written as a path to the folder (`build/`, `./build`)
+    /// turned into the bare name it matches.
+    fn normalize(&mut self, kind: &Kind) {
+        if *self.kind_or(kind) != Kind::Folder {
+            return;
+        }
+        let mut name = self.pattern.trim_end_matches(['/', '\\']);
+        while let Some(rest) = name.strip_prefix("./").or(name.strip_prefix(".\\")) {
+            name = rest;
+        }
+        if !name.is_empty() {
+            self.pattern = name.to_string();
+        }
+    }
+}
+
+/// What to do when a destination doesn't exist.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingPolicy {
+    /// fail the whole run.
+    #[default]
+    Error,
+    /// leave the destination out silently.
+    Skip,
+    /// leave the destination out with a warning.
+    Warn,
+}
+
+/// What to do when a config removes fewer items than its `min_matches`.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum ShortfallPolicy {
+    /// fail the run once it's over.
+    #[default]
+    Error,
+    /// only print a warning.
+    Warn,
+}
+
+/// What to do with a file of a config directory which can't be parsed.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum InvalidPolicy {
+    /// fail the whole run.
+    #[default]
+    Error,
+    /// leave the file out with a warning.
+    Skip,
+}
+
+/// What to do when a destination is a symlink to a folder.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkedPolicy {
+    /// walk the folder it points at.
+    #[default]
+    Follow,
+    /// fail the whole run.
+    Refuse,
+}
+
+/// What a failed removal does to the rest of the run.
+#[derive(Deserialize, Default, Debug, PartialEq, Clone, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorPolicy {
+    /// stop at the first failure and report it.
+    FailFast,
+    /// remove everything else, then report every failure.
+    #[default]
+    KeepGoing,
+}
+
+/// What removing a folder does about an entry it isn't allowed to remove.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionPolicy {
+    /// give up on the whole folder at once.
+    #[default]
+    Abort,
+    /// leave the entry (and so its folders) in place, remove the rest.
+    Skip,
+    /// make the entry writable and try again, then as `skip`.
+    Fix,
+}
+
+/// What a walk does with the symlinks it comes across.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum SymlinkPolicy {
+    /// never touch a symlink, nor look behind it.
+    #[default]
+    Skip,
+    /// remove a matching link (never its target), don't look behind it.
+    DeleteLink,
+    /// as `delete-link`, and walk into linked folders like real ones.
+    FollowAndDelete,
+}
+
+impl SymlinkPolicy {
+    fn is_skip(&self) -> bool {
+        *self == Self::Skip
+    }
+}
+
+/// Which timestamp of an item `older_than` is about.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeBasis {
+    /// last modification.
+    #[default]
+    Mtime,
+    /// last access, only as good as the mount records it (`noatime`,
+    /// `relatime`); the modification time where it isn't available.
+    Atime,
+    /// last status change on unix, the modification time elsewhere.
+    Ctime,
+}
+
+/// Characters a whole name has to be made of, for `name_charset`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum Charset {
+    /// `0-9` and `a-f`, in either case.
+    Hex,
+    /// ASCII letters and digits.
+    Alnum,
+    /// ASCII letters.
+    Alpha,
+    /// ASCII digits.
+    Digit,
+}
+
+impl Charset {
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            Self::Hex => c.is_ascii_hexdigit(),
+            Self::Alnum => c.is_ascii_alphanumeric(),
+            Self::Alpha => c.is_ascii_alphabetic(),
+            Self::Digit => c.is_ascii_digit(),
+        }
+    }
+}
+
+/// Which of byte-identical files `dedup_content` keeps.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum KeepCopy {
+    /// the least recently modified.
+    Oldest,
+    /// the most recently modified.
+    Newest,
+}
+
+/// Well-known build and cache folders of an ecosystem, for `--presets`.
+#[derive(Debug, PartialEq, Clone, ValueEnum)]
+pub enum Preset {
+    /// `target` next to a `Cargo.toml`.
+    Rust,
+    /// `node_modules` next to a `package.json`.
+    Node,
+    /// `__pycache__` next to Python sources, and `.pytest_cache`.
+    Python,
+}
+
+impl Preset {
+    /// The configs the preset stands for at `destination`.
+    pub fn configs(&self, destination: &Path) -> Vec<Config> {
+        let folders = |patterns: &[&str], next_to: Option<&str>| {
+            let mut config = Config::new(destination, Kind::Folder, patterns.to_vec(), None);
+            config.next_to = next_to.map(String::from);
+            config
+        };
+        match self {
+            Self::Rust => vec![folders(&["target"], Some("Cargo.toml"))],
+            Self::Node => vec![folders(&["node_modules"], Some("package.json"))],
+            // pytest's cache is named after it alone
+            Self::Python => vec![
+                folders(&["__pycache__"], Some("*.py")),
+                folders(&[".pytest_cache"], None),
+            ],
+        }
+    }
+}
+
+impl TimeBasis {
+    fn is_mtime(&self) -> bool {
+        *self == Self::Mtime
     }
 }
 
 // TODO: try to replace `String` with `&str` (if it's better)
-#[derive(Deserialize, Default, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
 pub struct Config {
+    /// name errors about this entry refer to it by.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// where the entry was read among the run's configs, for errors to name
+    /// it by even once others were left out or merged into it.
+    #[serde(skip)]
+    pub(crate) index: usize,
+    /// a relative destination is from here, where a relative one is itself
+    /// from the config file's folder; the config file's folder when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_dir: Option<PathBuf>,
     pub destination: PathBuf,
     pub kind: Kind,
-    pub patterns: Vec<String>,
+    pub patterns: Vec<Pattern>,
+    /// an item has to match every pattern rather than any of them. Only
+    /// useful with wildcards, e.g. `name:*temp*` and `ext:dat`, as a name
+    /// can't be equal to two different patterns.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub match_all: bool,
+    /// compare names and patterns in one Unicode form, so an accented letter
+    /// matches whether it's stored composed or decomposed. On by default on
+    /// macOS, where names often come back decomposed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalize_unicode: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub exclude: Option<Vec<String>>,
+    /// names for `--group` to pick the config by, e.g. `aggressive`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// overrides the run's policy for this destination.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_missing_destination: Option<MissingPolicy>,
+    /// overrides the run's policy for a destination which is a symlink.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_linked_destination: Option<LinkedPolicy>,
+    /// permission bits to test, Unix only (e.g. `2` for world-writable).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode_mask: Option<u32>,
+    /// expected value of the masked bits, all of `mode_mask` when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode_value: Option<u32>,
+    /// only remove items owned by this user (name or uid), Unix only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// only symlinks pointing at a path matching this glob (or below one),
+    /// the link is removed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_target_pattern: Option<String>,
+    /// only symlinks whose target is gone, the link is removed. Goes with
+    /// the patterns, `parent_name` and `link_target_pattern` like any
+    /// other condition.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub broken_links: bool,
+    /// test the destination itself against the patterns too, removing it
+    /// as a whole when it matches.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub match_destination: bool,
+    /// only folders with nothing in them, and files of zero bytes.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub only_empty: bool,
+    /// only folders with at least this many entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_children: Option<u64>,
+    /// only folders with at most this many entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_children: Option<u64>,
+    /// count the entries of sub-folders too for `min_children` and
+    /// `max_children`, not only those right inside.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub children_recursive: bool,
+    /// empty a matched folder, keeping the folder itself.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub contents_only: bool,
+    /// with `contents_only`, give the emptied folder back the access and
+    /// modification times it had.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub preserve_times: bool,
+    /// once the run took everything a folder held, remove the folder too
+    /// when its name matches this pattern (`*` for any); the emptied folder
+    /// itself with `contents_only`. Never the destination.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prune_emptied: Option<String>,
+    /// with `contents_only`, move the entries into a folder next to it
+    /// first, so it's empty at once, and remove them from there in the
+    /// background.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub move_aside: bool,
+    /// among matched hard links to the same file keep one, remove the rest.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub dedup_hardlinks: bool,
+    /// among matched files with the same content keep only this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup_content: Option<KeepCopy>,
+    /// only items carrying this extended attribute (linux and macOS).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xattr_name: Option<String>,
+    /// and only when `xattr_name` has exactly this value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xattr_value: Option<String>,
+    /// only files whose first line matches this glob (e.g. `#!*python*`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_line_matches: Option<String>,
+    /// only items whose parent folder's name matches this glob (e.g. `debug`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_name: Option<String>,
+    /// only items whose name is at least this many characters long.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_len_min: Option<usize>,
+    /// only items whose name is at most this many characters long.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_len_max: Option<usize>,
+    /// only items whose whole name is made of these characters, e.g. `hex`
+    /// for the random names some tools give their temp files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_charset: Option<Charset>,
+    /// only items next to an entry matching this glob, e.g. a `target`
+    /// folder next to `Cargo.toml`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_to: Option<String>,
+    /// how often the config is due with `--due`, e.g. `12h`, `7d` or `2w`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+    /// regex whose `key` group picks a sortable token from matched names,
+    /// e.g. `app-(?P<key>\d{4}-\d{2}-\d{2})\.log`; only the newest are kept.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation_key: Option<String>,
+    /// how many of each rotated series to keep, 1 when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_newest: Option<usize>,
+    /// what to do with symlinks, left alone unless told otherwise.
+    #[serde(default, skip_serializing_if = "SymlinkPolicy::is_skip")]
+    pub symlinks: SymlinkPolicy,
+    /// shell command run once the config removed something for real.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    /// file of the destination a run which went well writes its time to,
+    /// for other tools to tell when it was last cleaned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub touch_after: Option<String>,
+    /// never go into (or remove) what's on another device than the
+    /// destination, as `find -xdev`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub one_filesystem: bool,
+    /// only items of the user running, changed (by ctime) since their login
+    /// session started; unix only, and the session start is only known on
+    /// linux.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub since_session_start: bool,
+    /// fewest items a run of the config is expected to remove (or find, in
+    /// dry-run), as fewer usually means a path moved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_matches: Option<usize>,
+    /// what falling short of `min_matches` does, an error when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_few_matches: Option<ShortfallPolicy>,
+    /// only items whose `time_basis` is at least this old, e.g. `30d`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub older_than: Option<String>,
+    /// timestamp `older_than` looks at, the modification time by default.
+    #[serde(default, skip_serializing_if = "TimeBasis::is_mtime")]
+    pub time_basis: TimeBasis,
+    /// size the matches of a folder may take together, e.g. `2GB`; the
+    /// oldest (by `time_basis`) go until the rest fits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir_quota: Option<String>,
+    /// only clean when the destination's volume is over this percent full.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub high_water: Option<u8>,
+    /// and stop once it would be back under this percent, `high_water` when
+    /// not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low_water: Option<u8>,
+    /// only clean when this percent of the volume's inodes are in use,
+    /// Unix only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inode_high_water: Option<u8>,
+    /// and stop once back under this percent, `inode_high_water` when not
+    /// set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inode_low_water: Option<u8>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
 }
 
 impl Config {
@@ -32,12 +482,599 @@ impl Config {
         I: IntoIterator<Item = S>,
     {
         Config {
+            label: None,
+            index: 0,
+            base_dir: None,
             destination: destination.into(),
             kind,
-            patterns: patterns.into_iter().map(Into::into).collect(),
+            patterns: patterns
+                .into_iter()
+                .map(|p| Pattern::from(p.into()))
+                .collect(),
             exclude: exclude.map(|e| e.into_iter().map(Into::into).collect()),
+            tags: None,
+            on_missing_destination: None,
+            on_linked_destination: None,
+            mode_mask: None,
+            mode_value: None,
+            owner: None,
+            link_target_pattern: None,
+            broken_links: false,
+            match_destination: false,
+            only_empty: false,
+            min_children: None,
+            max_children: None,
+            children_recursive: false,
+            contents_only: false,
+            preserve_times: false,
+            move_aside: false,
+            prune_emptied: None,
+            match_all: false,
+            normalize_unicode: None,
+            dedup_hardlinks: false,
+            dedup_content: None,
+            xattr_name: None,
+            xattr_value: None,
+            first_line_matches: None,
+            parent_name: None,
+            name_len_min: None,
+            name_len_max: None,
+            name_charset: None,
+            next_to: None,
+            interval: None,
+            rotation_key: None,
+            keep_newest: None,
+            symlinks: SymlinkPolicy::Skip,
+            after: None,
+            touch_after: None,
+            one_filesystem: false,
+            since_session_start: false,
+            min_matches: None,
+            on_few_matches: None,
+            older_than: None,
+            dir_quota: None,
+            time_basis: TimeBasis::Mtime,
+            high_water: None,
+            low_water: None,
+            inode_high_water: None,
+            inode_low_water: None,
+        }
+    }
+}
+
+impl Config {
+    /// Stable hash (a `digest` of the JSON form) identifying this config
+    /// across runs and versions. The label only names the rules, renaming
+    /// them keeps the same fingerprint.
+    pub fn fingerprint(&self) -> String {
+        let unlabeled = Config {
+            label: None,
+            ..self.clone()
+        };
+        self::digest(&unlabeled).unwrap_or_default()
+    }
+}
+
+impl Config {
+    /// Whether `other` only differs by its patterns and excludes, so both
+    /// can run as a single walk, with the patterns and excludes of both.
+    /// With `match_all` more patterns would narrow the rule, and
+    /// `min_matches` counts a config's own matches, such configs never merge.
+    pub fn mergeable(&self, other: &Config) -> bool {
+        fn rest(config: &Config) -> Config {
+            Config {
+                index: 0,
+                patterns: vec![],
+                exclude: None,
+                ..config.clone()
+            }
+        }
+        !self.match_all && self.min_matches.is_none() && rest(self) == rest(other)
+    }
+}
+
+/// An entry of a config file: a config, or `{"include": "<file>"}` for the
+/// configs of another file, from the folder of this one, in its place.
+#[derive(Debug, PartialEq)]
+pub enum ConfigEntry {
+    Config(Box<Config>),
+    Include(PathBuf),
+}
+
+impl ConfigEntry {
+    /// Read an entry, with every `${NAME}` in its strings replaced by the
+    /// environment variable `NAME`, which has to be set.
+    pub fn from_value(mut value: Value) -> crate::Result<ConfigEntry> {
+        expand(&mut value)?;
+        if let Value::Object(map) = &value
+            && map.len() == 1
+            && let Some(Value::String(file)) = map.get("include")
+        {
+            return Ok(ConfigEntry::Include(PathBuf::from(file)));
+        }
+        Ok(ConfigEntry::Config(Box::new(serde_json::from_value(
+            value,
+        )?)))
+    }
+}
+
+fn expand(value: &mut Value) -> crate::Result<()> {
+    match value {
+        Value::String(text) => *text = template(text)?,
+        Value::Array(items) => items.iter_mut().try_for_each(expand)?,
+        Value::Object(map) => map.values_mut().try_for_each(expand)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn template(text: &str) -> crate::Result<String> {
+    let mut expanded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find('}').ok_or_else(|| {
+            AppError::new(AppErrorKind::Usage, format!("unclosed '${{' in '{}'", text))
+        })?;
+        let name = &after[..end];
+        let value = std::env::var(name).map_err(|_| {
+            AppError::new(
+                AppErrorKind::Usage,
+                format!("environment variable '{}' of '{}' isn't set", name, text),
+            )
+        })?;
+        expanded.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+impl Config {
+    /// Read a JSON list of config entries from `reader` one at a time, each
+    /// handed to `each` as soon as it's parsed, so only the entries kept
+    /// take memory. An entry which is no config is handed over as the error
+    /// saying why, for `each` to leave it out or not; the first error of
+    /// `each` stops the reading.
+    pub fn stream<R, F>(reader: R, each: F) -> crate::Result<()>
+    where
+        R: Read,
+        F: FnMut(crate::Result<ConfigEntry>) -> crate::Result<()>,
+    {
+        struct Entries<F> {
+            each: F,
+            failed: Option<AppError>,
+        }
+
+        impl<'de, F> Visitor<'de> for &mut Entries<F>
+        where
+            F: FnMut(crate::Result<ConfigEntry>) -> crate::Result<()>,
+        {
+            type Value = ();
+
+            fn expecting(&self, f: &mut Formatter) -> FmtResult {
+                write!(f, "a list of configs")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+                while let Some(value) = seq.next_element::<Value>()? {
+                    if let Err(e) = (self.each)(ConfigEntry::from_value(value)) {
+                        self.failed = Some(e);
+                        return Err(de::Error::custom("config rejected"));
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        let mut entries = Entries { each, failed: None };
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        let result = deserializer.deserialize_seq(&mut entries);
+        if let Some(e) = entries.failed {
+            return Err(e);
+        }
+        result?;
+        deserializer.end()?;
+        Ok(())
+    }
+
+    /// Make a relative `destination` absolute, from `base_dir` or else from
+    /// `dir`, the folder of the config file it was read from. Its `..` are
+    /// folded in either way, for the destination to compare with the paths
+    /// it holds.
+    pub fn resolve(&mut self, dir: &Path) -> crate::Result<()> {
+        if self.destination.is_relative() {
+            let base = match &self.base_dir {
+                Some(base) => dir.join(base),
+                None => dir.to_path_buf(),
+            };
+            self.destination = std::path::absolute(base.join(&self.destination))?;
+        }
+        self.destination = self::lexical(&self.destination);
+        Ok(())
+    }
+
+    /// Patterns match the name of an item (or, with a separator inside, its
+    /// path from the destination), so folder patterns lose any leading `./`
+    /// and trailing separator.
+    pub fn normalize(&mut self) {
+        for pattern in &mut self.patterns {
+            pattern.normalize(&self.kind);
+        }
+    }
+}
+
+impl Config {
+    /// `interval` as a duration, `None` when unset or not understood.
+    pub fn interval(&self) -> Option<Duration> {
+        self::duration(self.interval.as_deref()?)
+    }
+
+    /// `high_water` and `low_water` in percent, `None` without a high
+    /// water mark.
+    pub fn water_marks(&self) -> Option<(u8, u8)> {
+        let high = self.high_water?;
+        Some((high, self.low_water.unwrap_or(high)))
+    }
+
+    /// `inode_high_water` and `inode_low_water`, as `water_marks`.
+    pub fn inode_marks(&self) -> Option<(u8, u8)> {
+        let high = self.inode_high_water?;
+        Some((high, self.inode_low_water.unwrap_or(high)))
+    }
+
+    /// Whether names are normalized before matching, see `normalize_unicode`.
+    pub fn unicode(&self) -> bool {
+        self.normalize_unicode.unwrap_or(cfg!(target_os = "macos"))
+    }
+
+    /// `older_than` as a duration, `None` when unset or not understood.
+    pub fn older_than(&self) -> Option<Duration> {
+        self::duration(self.older_than.as_deref()?)
+    }
+
+    /// `dir_quota` in bytes, `None` when unset or not understood.
+    pub fn dir_quota(&self) -> Option<u64> {
+        self::bytes(self.dir_quota.as_deref()?)
+    }
+
+    /// Whether what the config matches changes with time alone, with no
+    /// folder modified: items growing old, or the session starting anew.
+    pub fn time_dependent(&self) -> bool {
+        self.older_than.is_some() || self.dir_quota.is_some() || self.since_session_start
+    }
+}
+
+/// A number followed by `B`, `KB`, `MB`, `GB` or `TB`, any case, as bytes;
+/// units are powers of 1024 and a bare number is bytes.
+fn bytes(text: &str) -> Option<u64> {
+    let text = text.trim().to_uppercase();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (count, unit) = text.split_at(split);
+    let power = match unit.trim() {
+        "" | "B" => 0,
+        "KB" => 1,
+        "MB" => 2,
+        "GB" => 3,
+        "TB" => 4,
+        _ => return None,
+    };
+    let count: u64 = count.parse().ok()?;
+    count.checked_mul(1024u64.pow(power))
+}
+
+/// `path` without its `.` and `..` components, each `..` dropping the
+/// component before it; the disk isn't looked at.
+pub(crate) fn lexical(path: &Path) -> PathBuf {
+    let mut clean = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(clean.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                clean.pop();
+            }
+            // nothing is above the root
+            Component::ParentDir if clean.has_root() => {}
+            component => clean.push(component),
         }
     }
+    clean
+}
+
+/// SHA-256 of `value` as compact JSON, in hex: the hash of configs and
+/// manifests alike.
+pub(crate) fn digest<T: Serialize + ?Sized>(value: &T) -> crate::Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let digest = Sha256::digest(serde_json::to_vec(value)?);
+    Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// A number followed by `s`, `m`, `h`, `d` or `w` as a duration.
+fn duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    // the unit may be any character, so split on its boundary
+    let (split, unit) = text.char_indices().next_back()?;
+    let count = &text[..split];
+    let unit = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let count: u64 = count.parse().ok()?;
+    Some(Duration::from_secs(count.checked_mul(unit)?))
+}
+
+impl Config {
+    /// `rotation_key` compiled, which has to capture a `key` group.
+    pub fn rotation(&self) -> crate::Result<Option<Regex>> {
+        let Some(pattern) = &self.rotation_key else {
+            return Ok(None);
+        };
+        let invalid = |reason: String| {
+            AppError::new(
+                AppErrorKind::Usage,
+                format!("invalid rotation_key '{}': {}", pattern, reason),
+            )
+        };
+        let regex = Regex::new(pattern).map_err(|e| invalid(e.to_string()))?;
+        if !regex.capture_names().any(|name| name == Some("key")) {
+            return Err(invalid(String::from("no group named 'key'")));
+        }
+        Ok(Some(regex))
+    }
+}
+
+impl Config {
+    /// `symlinks` as it applies: a `link_target_pattern` or `broken_links`
+    /// rule is about removing links, so those are never skipped there.
+    pub fn symlink_policy(&self) -> SymlinkPolicy {
+        match self.symlinks {
+            SymlinkPolicy::Skip if self.link_target_pattern.is_some() || self.broken_links => {
+                SymlinkPolicy::DeleteLink
+            }
+            ref policy => policy.clone(),
+        }
+    }
+}
+
+impl Display for SymlinkPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Skip => write!(f, "skip"),
+            Self::DeleteLink => write!(f, "delete-link"),
+            Self::FollowAndDelete => write!(f, "follow-and-delete"),
+        }
+    }
+}
+
+impl Display for TimeBasis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Mtime => write!(f, "mtime"),
+            Self::Atime => write!(f, "atime"),
+            Self::Ctime => write!(f, "ctime"),
+        }
+    }
+}
+
+impl Display for KeepCopy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Oldest => write!(f, "oldest"),
+            Self::Newest => write!(f, "newest"),
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            Some(kind) => write!(f, "{} ({})", self.pattern, kind),
+            None => write!(f, "{}", self.pattern),
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Folder => write!(f, "folder"),
+            Self::File => write!(f, "file"),
+        }
+    }
+}
+
+impl Display for MissingPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Skip => write!(f, "skip"),
+            Self::Warn => write!(f, "warn"),
+        }
+    }
+}
+
+impl Display for Charset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Hex => write!(f, "hex"),
+            Self::Alnum => write!(f, "alnum"),
+            Self::Alpha => write!(f, "alpha"),
+            Self::Digit => write!(f, "digit"),
+        }
+    }
+}
+
+impl Display for ShortfallPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warn => write!(f, "warn"),
+        }
+    }
+}
+
+impl Display for LinkedPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Follow => write!(f, "follow"),
+            Self::Refuse => write!(f, "refuse"),
+        }
+    }
+}
+
+/// Rule block as `show` prints it, one setting per line.
+impl Display for Config {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        writeln!(f, "{}", self.destination.display())?;
+        if let Some(label) = &self.label {
+            writeln!(f, "  label: {}", label)?;
+        }
+        writeln!(f, "  kind: {}", self.kind)?;
+        let patterns: Vec<String> = self.patterns.iter().map(Pattern::to_string).collect();
+        write!(f, "  patterns: {}", patterns.join(", "))?;
+        match &self.exclude {
+            Some(exclude) if exclude.is_empty() => write!(f, "\n  exclude: none")?,
+            Some(exclude) => write!(f, "\n  exclude: {}", exclude.join(", "))?,
+            None => {}
+        }
+        if let Some(policy) = &self.on_missing_destination {
+            write!(f, "\n  on missing destination: {}", policy)?;
+        }
+        if let Some(policy) = &self.on_linked_destination {
+            write!(f, "\n  on linked destination: {}", policy)?;
+        }
+        if let Some(mask) = self.mode_mask {
+            let value = self.mode_value.unwrap_or(mask);
+            write!(f, "\n  mode: {:o} masked with {:o}", value, mask)?;
+        }
+        if let Some(owner) = &self.owner {
+            write!(f, "\n  owner: {}", owner)?;
+        }
+        if let Some(target) = &self.link_target_pattern {
+            write!(f, "\n  link target: {}", target)?;
+        }
+        if self.broken_links {
+            write!(f, "\n  broken links only: yes")?;
+        }
+        if self.match_all {
+            write!(f, "\n  match all patterns: yes")?;
+        }
+        if let Some(normalize) = self.normalize_unicode {
+            let normalize = if normalize { "yes" } else { "no" };
+            write!(f, "\n  normalize unicode: {}", normalize)?;
+        }
+        if self.match_destination {
+            write!(f, "\n  match destination: yes")?;
+        }
+        if self.only_empty {
+            write!(f, "\n  only empty: yes")?;
+        }
+        if let Some(min) = self.min_children {
+            write!(f, "\n  min children: {}", min)?;
+        }
+        if let Some(max) = self.max_children {
+            write!(f, "\n  max children: {}", max)?;
+        }
+        if self.children_recursive {
+            write!(f, "\n  children recursive: yes")?;
+        }
+        if self.contents_only {
+            write!(f, "\n  contents only: yes")?;
+        }
+        if self.preserve_times {
+            write!(f, "\n  preserve times: yes")?;
+        }
+        if self.move_aside {
+            write!(f, "\n  move aside: yes")?;
+        }
+        if let Some(pattern) = &self.prune_emptied {
+            write!(f, "\n  prune emptied: {}", pattern)?;
+        }
+        if self.dedup_hardlinks {
+            write!(f, "\n  dedup hardlinks: yes")?;
+        }
+        if let Some(keep) = &self.dedup_content {
+            write!(f, "\n  dedup content: keep {}", keep)?;
+        }
+        if let Some(name) = &self.xattr_name {
+            match &self.xattr_value {
+                Some(value) => write!(f, "\n  xattr: {} = {}", name, value)?,
+                None => write!(f, "\n  xattr: {}", name)?,
+            }
+        }
+        if let Some(pattern) = &self.first_line_matches {
+            write!(f, "\n  first line: {}", pattern)?;
+        }
+        if let Some(pattern) = &self.parent_name {
+            write!(f, "\n  parent name: {}", pattern)?;
+        }
+        match (self.name_len_min, self.name_len_max) {
+            (Some(min), Some(max)) => write!(f, "\n  name length: {} to {}", min, max)?,
+            (Some(min), None) => write!(f, "\n  name length: at least {}", min)?,
+            (None, Some(max)) => write!(f, "\n  name length: at most {}", max)?,
+            (None, None) => {}
+        }
+        if let Some(charset) = &self.name_charset {
+            write!(f, "\n  name charset: {}", charset)?;
+        }
+        if let Some(pattern) = &self.next_to {
+            write!(f, "\n  next to: {}", pattern)?;
+        }
+        if let Some(tags) = &self.tags {
+            write!(f, "\n  tags: {}", tags.join(", "))?;
+        }
+        if let Some(interval) = &self.interval {
+            write!(f, "\n  interval: {}", interval)?;
+        }
+        if !self.symlinks.is_skip() {
+            write!(f, "\n  symlinks: {}", self.symlinks)?;
+        }
+        if let Some(rotation) = &self.rotation_key {
+            let keep = self.keep_newest.unwrap_or(1);
+            write!(f, "\n  rotation: keep {} newest by {}", keep, rotation)?;
+        }
+        if let Some(older_than) = &self.older_than {
+            write!(f, "\n  older than: {} by {}", older_than, self.time_basis)?;
+        }
+        if let Some(quota) = &self.dir_quota {
+            write!(f, "\n  folder quota: {} by {}", quota, self.time_basis)?;
+        }
+        if let Some((high, low)) = self.water_marks() {
+            write!(f, "\n  water marks: over {}% until under {}%", high, low)?;
+        }
+        if let Some((high, low)) = self.inode_marks() {
+            write!(f, "\n  inode marks: over {}% until under {}%", high, low)?;
+        }
+        if let Some(after) = &self.after {
+            write!(f, "\n  after: {}", after)?;
+        }
+        if let Some(name) = &self.touch_after {
+            write!(f, "\n  touch after: {}", name)?;
+        }
+        if self.one_filesystem {
+            write!(f, "\n  one filesystem: yes")?;
+        }
+        if self.since_session_start {
+            write!(f, "\n  since session start: yes")?;
+        }
+        if let Some(min) = self.min_matches {
+            write!(f, "\n  min matches: {}", min)?;
+        }
+        if let Some(policy) = &self.on_few_matches {
+            write!(f, "\n  on few matches: {}", policy)?;
+        }
+        Ok(())
+    }
 }
 
 impl AsRef<Config> for Config {
@@ -70,11 +1107,12 @@ mod tests {
                 destination: PathBuf::from("/Users/abhinath/productive/pool/Project"),
                 kind: Kind::Folder,
                 patterns: vec![
-                    String::from("build"),
-                    String::from("debug"),
-                    String::from("release"),
+                    Pattern::from("build"),
+                    Pattern::from("debug"),
+                    Pattern::from("release"),
                 ],
                 exclude: None,
+                ..Default::default()
             }
         );
     }
@@ -100,6 +1138,206 @@ mod tests {
         assert_eq!(file_config.kind, Kind::File);
     }
 
+    #[test]
+    fn fingerprint() {
+        let config = Config::new("/pool/node", Kind::Folder, vec!["dist"], None);
+        let same = Config::new("/pool/node", Kind::Folder, vec!["dist"], None);
+        let other = Config::new("/pool/node", Kind::Folder, vec!["node_modules"], None);
+
+        assert_eq!(config.fingerprint(), same.fingerprint());
+        assert_ne!(config.fingerprint(), other.fingerprint());
+
+        // naming the rules doesn't change them
+        let mut labeled = same.clone();
+        labeled.label = Some(String::from("web"));
+        assert_eq!(config.fingerprint(), labeled.fingerprint());
+        assert_eq!(config.fingerprint().len(), 64);
+    }
+
+    #[test]
+    fn mergeable() {
+        let config = Config::new("/pool/node", Kind::Folder, vec!["dist"], Some(vec![".git"]));
+        let other = Config::new("/pool/node", Kind::Folder, vec!["out"], Some(vec![".GIT"]));
+        assert!(config.mergeable(&other));
+
+        let none = Config::new("/pool/node", Kind::Folder, vec!["dist"], None);
+        let empty = Config::new("/pool/node", Kind::Folder, vec!["out"], Some(vec![]));
+        assert!(none.mergeable(&empty));
+        // excludes differing too
+        assert!(config.mergeable(&none));
+        let vendor = Config::new(
+            "/pool/node",
+            Kind::Folder,
+            vec!["out"],
+            Some(vec!["vendor"]),
+        );
+        assert!(config.mergeable(&vendor));
+
+        let file = Config::new("/pool/node", Kind::File, vec!["dist"], Some(vec![".git"]));
+        assert!(!config.mergeable(&file));
+
+        let mut owned = Config::new("/pool/node", Kind::Folder, vec!["out"], Some(vec![".git"]));
+        owned.owner = Some(String::from("root"));
+        assert!(!config.mergeable(&owned));
+
+        let mut all = config.clone();
+        all.match_all = true;
+        let mut other = other.clone();
+        other.match_all = true;
+        assert!(!all.mergeable(&other));
+
+        let mut counted = config.clone();
+        counted.min_matches = Some(1);
+        let mut other = Config::new("/pool/node", Kind::Folder, vec!["out"], Some(vec![".git"]));
+        other.min_matches = Some(1);
+        assert!(!counted.mergeable(&other));
+    }
+
+    #[test]
+    fn config_entry() {
+        let value = serde_json::json!({ "include": "more.json" });
+        assert_eq!(
+            ConfigEntry::from_value(value).unwrap(),
+            ConfigEntry::Include(PathBuf::from("more.json"))
+        );
+
+        // environment variables fill in any string, nested ones too
+        let value = serde_json::json!({
+            "destination": "/tmp/${CARGO_PKG_NAME}",
+            "kind": "file",
+            "patterns": ["${CARGO_PKG_NAME}-*.log", "$HOME"]
+        });
+        let ConfigEntry::Config(config) = ConfigEntry::from_value(value).unwrap() else {
+            panic!("not a config");
+        };
+        let name = env!("CARGO_PKG_NAME");
+        assert_eq!(config.destination, PathBuf::from(format!("/tmp/{}", name)));
+        assert_eq!(
+            config.patterns,
+            vec![format!("{}-*.log", name).as_str(), "$HOME"]
+        );
+
+        // one which isn't set, or isn't closed, fails the entry
+        for pattern in ["${NEATEN_NOT_SET_ANYWHERE}", "${CARGO_PKG_NAME"] {
+            let value = serde_json::json!({
+                "destination": "/tmp",
+                "kind": "file",
+                "patterns": [pattern]
+            });
+            assert!(ConfigEntry::from_value(value).is_err());
+        }
+    }
+
+    #[test]
+    fn interval() {
+        let mut config = Config::new("/pool/node", Kind::Folder, vec!["dist"], None);
+        assert_eq!(config.interval(), None);
+
+        for (interval, seconds) in [("90s", 90), ("30m", 1800), ("12h", 43200), ("1d", 86400)] {
+            config.interval = Some(String::from(interval));
+            assert_eq!(config.interval(), Some(Duration::from_secs(seconds)));
+        }
+        config.interval = Some(String::from("2w"));
+        assert_eq!(config.interval(), Some(Duration::from_secs(14 * 86400)));
+
+        for interval in ["", "d", "7", "7y", "-1d", "weekly", "7д", "д"] {
+            config.interval = Some(String::from(interval));
+            assert_eq!(config.interval(), None, "{}", interval);
+        }
+
+        // older_than is read the same way
+        config.older_than = Some(String::from("7д"));
+        assert_eq!(config.older_than(), None);
+        config.older_than = Some(String::from("7d"));
+        assert_eq!(config.older_than(), Some(Duration::from_secs(7 * 86400)));
+    }
+
+    #[test]
+    fn dir_quota() {
+        let mut config = Config::new("/pool/cache", Kind::File, vec!["bin"], None);
+        assert_eq!(config.dir_quota(), None);
+
+        let sizes = [
+            ("512", 512),
+            ("10b", 10),
+            ("4KB", 4096),
+            ("2 GB", 2 << 30),
+            ("1tb", 1 << 40),
+        ];
+        for (quota, bytes) in sizes {
+            config.dir_quota = Some(String::from(quota));
+            assert_eq!(config.dir_quota(), Some(bytes), "{}", quota);
+        }
+        for quota in ["", "GB", "2GiB", "-1MB", "1.5GB", "99999999999TB"] {
+            config.dir_quota = Some(String::from(quota));
+            assert_eq!(config.dir_quota(), None, "{}", quota);
+        }
+    }
+
+    #[test]
+    fn rotation() {
+        let mut config = Config::new("/var/log", Kind::File, vec!["log"], None);
+        assert!(config.rotation().unwrap().is_none());
+
+        config.rotation_key = Some(String::from(r"app-(?P<key>\d+)\.log"));
+        let rotation = config.rotation().unwrap().unwrap();
+        assert_eq!(&rotation.captures("app-42.log").unwrap()["key"], "42");
+
+        config.rotation_key = Some(String::from(r"app-(\d+)\.log"));
+        let error = config.rotation().unwrap_err().to_string();
+        assert!(error.contains("no group named 'key'"));
+
+        config.rotation_key = Some(String::from(r"app-(?P<key>\d+"));
+        assert!(config.rotation().is_err());
+    }
+
+    #[test]
+    fn pattern_kinds() {
+        let json = r#"{
+            "destination": "/pool/node",
+            "kind": "folder",
+            "patterns": ["target", { "pattern": "tmp", "kind": "File" }]
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.patterns[0], "target");
+        assert_eq!(
+            config.patterns[1],
+            Pattern {
+                pattern: String::from("tmp"),
+                kind: Some(Kind::File)
+            }
+        );
+        assert_eq!(config.patterns[1].kind_or(&config.kind), &Kind::File);
+
+        // plain ones stay plain strings
+        let value = serde_json::to_value(&config).unwrap();
+        assert_eq!(
+            value["patterns"],
+            serde_json::json!(["target", { "pattern": "tmp", "kind": "file" }])
+        );
+        assert!(config.to_string().contains("patterns: target, tmp (file)"));
+    }
+
+    #[test]
+    fn normalize_patterns() {
+        let mut config = Config::new(
+            "/pool/node",
+            Kind::Folder,
+            vec!["build/", "./dist", "././out//", ".\\bin\\", "/", "target"],
+            None,
+        );
+        config.patterns.push(Pattern {
+            pattern: String::from("./notes.txt"),
+            kind: Some(Kind::File),
+        });
+        config.normalize();
+        let patterns: Vec<&str> = config.patterns.iter().map(|p| p.pattern.as_str()).collect();
+        assert_eq!(
+            patterns,
+            vec!["build", "dist", "out", "bin", "/", "target", "./notes.txt"]
+        );
+    }
+
     #[test]
     fn check_lifetime() {
         let destination = "/pool/node";
@@ -111,8 +1349,9 @@ mod tests {
             Config {
                 destination: PathBuf::from("/pool/node"),
                 kind: Kind::Folder,
-                patterns: vec![String::from("dist"), String::from("node_modules")],
+                patterns: vec![Pattern::from("dist"), Pattern::from("node_modules")],
                 exclude: None,
+                ..Default::default()
             }
         );
 
@@ -125,8 +1364,9 @@ mod tests {
                 Config {
                     destination: PathBuf::from("/pool/node"),
                     kind: Kind::Folder,
-                    patterns: vec![String::from("dist"), String::from("node_modules")],
+                    patterns: vec![Pattern::from("dist"), Pattern::from("node_modules")],
                     exclude: None,
+                    ..Default::default()
                 }
             );
         }
@@ -0,0 +1,194 @@
+use crate::{Charset, Kind, TimeBasis};
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    path::{Path, PathBuf},
+};
+
+/// What happens to a visited item.
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    Remove(Reason),
+    Keep(Reason),
+    Skip(Reason),
+}
+
+/// Why an item got its `Decision`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Reason {
+    /// name (folder) or extension, stem or name (file) matched the pattern.
+    Pattern(String),
+    /// folder name isn't one of the patterns.
+    Name(String),
+    /// file extension isn't one of the patterns.
+    Extension(String),
+    /// name, shown lossily, isn't valid UTF-8, so no pattern matches it.
+    NotUnicode(String),
+    /// item isn't of the configured kind.
+    Kind(Kind),
+    /// name is in the exclude list.
+    Excluded(String),
+    /// config file of the run (or a folder holding it), or a `.cleanupignore`.
+    Control,
+    /// name is listed in a `.cleanupignore` on the way down.
+    Ignored(String),
+    /// permission bits, masked, aren't the expected value.
+    #[cfg(unix)]
+    Mode { mode: u32, mask: u32, value: u32 },
+    /// item is owned by someone else.
+    #[cfg(unix)]
+    Owner { uid: u32, owner: String },
+    /// first link seen to a hard-linked file, kept.
+    #[cfg(unix)]
+    FirstLink,
+    /// another link to the same file was kept.
+    #[cfg(unix)]
+    Linked,
+    /// same content as this file, which is kept.
+    Duplicate(PathBuf),
+    /// no other file with the same content, or the copy kept.
+    Unique,
+    /// file has no other hard link (or links can't be told on this platform).
+    Unlinked,
+    /// item is a symlink, which the config leaves alone.
+    Symlink,
+    /// item isn't a symlink.
+    NotLink,
+    /// folder has something in it, or file has some content.
+    NotEmpty,
+    /// folder has fewer entries than this.
+    FewChildren(u64),
+    /// folder has more entries than this.
+    ManyChildren(u64),
+    /// item isn't old enough by this timestamp.
+    Recent {
+        basis: TimeBasis,
+        older_than: String,
+    },
+    /// item doesn't carry the extended attribute, or not with this value.
+    Xattr { name: String, value: Option<String> },
+    /// first line of the file doesn't match.
+    FirstLine { line: String, pattern: String },
+    /// name of the parent folder doesn't match.
+    Parent { name: String, pattern: String },
+    /// nothing next to the item matches this.
+    Alone(String),
+    /// item is on another device than the destination.
+    OtherFilesystem,
+    /// item last changed before the login session started.
+    #[cfg(unix)]
+    BeforeSession,
+    /// name is this many characters long, out of the config's bounds.
+    NameLength(usize),
+    /// name has characters out of this set.
+    Charset(Charset),
+    /// among the newest of its rotated series, by this key.
+    Newest(String),
+    /// an older member of its rotated series, by this key.
+    Rotated(String),
+    /// name doesn't carry a rotation key.
+    NotRotated,
+    /// symlink points somewhere else.
+    LinkTarget { link: PathBuf, target: String },
+    /// match left out of a `--sample` of this percent.
+    Unsampled(f64),
+    /// folder the run left empty, its name matching this pattern.
+    Emptied(String),
+    /// among the newest matches of the folder fitting in this quota.
+    WithinQuota(String),
+    /// what the symlink points at is still there.
+    LinkAlive(PathBuf),
+}
+
+impl Decision {
+    /// One line telling what happened to `path` and why.
+    pub fn explain<P: AsRef<Path>>(&self, path: P) -> String {
+        let name = path.as_ref().file_name().unwrap_or_default();
+        let (verb, reason) = match self {
+            Self::Remove(reason) => ("removed", reason),
+            Self::Keep(reason) => ("kept", reason),
+            Self::Skip(reason) => ("skipped", reason),
+        };
+        format!("{} {}: {}", verb, name.to_string_lossy(), reason)
+    }
+}
+
+impl Reason {
+    /// Whether removing an item for this reason gives disk space back.
+    pub fn frees_space(&self) -> bool {
+        match self {
+            #[cfg(unix)]
+            Self::Linked => false,
+            // its contents were already counted
+            Self::Emptied(_) => false,
+            _ => true,
+        }
+    }
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Pattern(pattern) => write!(f, "matched pattern '{}'", pattern),
+            Self::Name(name) => write!(f, "name '{}' not in patterns", name),
+            Self::NotUnicode(name) => write!(f, "name '{}' isn't valid UTF-8", name),
+            Self::Extension(extn) => write!(f, "extension '{}' not in patterns", extn),
+            Self::Kind(Kind::Folder) => write!(f, "not a folder"),
+            Self::Kind(Kind::File) => write!(f, "not a file"),
+            Self::FirstLine { line, pattern } => {
+                write!(f, "first line '{}' doesn't match '{}'", line, pattern)
+            }
+            Self::Parent { name, pattern } => {
+                write!(f, "parent '{}' doesn't match '{}'", name, pattern)
+            }
+            Self::Alone(pattern) => write!(f, "nothing matching '{}' next to it", pattern),
+            Self::OtherFilesystem => write!(f, "on another filesystem than the destination"),
+            #[cfg(unix)]
+            Self::BeforeSession => write!(f, "changed before the session started"),
+            Self::NameLength(length) => write!(f, "name is {} characters long", length),
+            Self::Charset(charset) => write!(f, "name isn't all {}", charset),
+            Self::NotEmpty => write!(f, "not empty"),
+            Self::FewChildren(min) => write!(f, "fewer than {} entries", min),
+            Self::ManyChildren(max) => write!(f, "more than {} entries", max),
+            Self::Recent { basis, older_than } => {
+                write!(f, "{} within the last {}", basis, older_than)
+            }
+            Self::Xattr { name, value: None } => {
+                write!(f, "no extended attribute '{}'", name)
+            }
+            Self::Xattr {
+                name,
+                value: Some(value),
+            } => write!(f, "extended attribute '{}' isn't '{}'", name, value),
+            Self::Excluded(exclude) => write!(f, "excluded by '{}'", exclude),
+            Self::Newest(key) => write!(f, "newest by key '{}'", key),
+            Self::Rotated(key) => write!(f, "older by key '{}'", key),
+            Self::NotRotated => write!(f, "no rotation key in name"),
+            Self::Symlink => write!(f, "symlink left alone"),
+            Self::Control => write!(f, "controls the run"),
+            Self::Ignored(pattern) => write!(f, "ignored by '{}'", pattern),
+            #[cfg(unix)]
+            Self::Mode { mode, mask, value } => write!(
+                f,
+                "mode {:o} masked with {:o} isn't {:o}",
+                mode, mask, value
+            ),
+            #[cfg(unix)]
+            Self::Owner { uid, owner } => write!(f, "owned by uid {}, not '{}'", uid, owner),
+            #[cfg(unix)]
+            Self::FirstLink => write!(f, "first link to the file"),
+            #[cfg(unix)]
+            Self::Linked => write!(f, "another link to the file is kept"),
+            Self::Duplicate(kept) => write!(f, "same content as {:?}", kept),
+            Self::Unique => write!(f, "no copy of it to keep instead"),
+            Self::Unlinked => write!(f, "not a hard-linked file"),
+            Self::NotLink => write!(f, "not a symlink"),
+            Self::LinkTarget { link, target } => {
+                write!(f, "links to {:?}, not into '{}'", link, target)
+            }
+            Self::Unsampled(percent) => write!(f, "left out of the {}% sample", percent),
+            Self::Emptied(pattern) => write!(f, "left empty, matching '{}'", pattern),
+            Self::WithinQuota(quota) => write!(f, "newer, within the folder's quota of {}", quota),
+            Self::LinkAlive(link) => write!(f, "links to {:?}, which exists", link),
+        }
+    }
+}
@@ -1,14 +1,36 @@
-use super::Kind;
-use clap::{ArgAction, Parser};
+use super::{
+    ErrorPolicy, InvalidPolicy, Kind, LinkedPolicy, MissingPolicy, PermissionPolicy, Preset,
+    SummaryFormat,
+};
+use crate::Verbosity;
+use clap::{ArgAction, Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
 pub struct Engine {
-    /// config file path(either absolute or relative path).
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// config file path(either absolute or relative path). An entry
+    /// `{"include": "<file>"}` reads another file in its place, and `${NAME}`
+    /// in a string is the environment variable `NAME`.
     #[arg(long, short)]
     pub config: Option<PathBuf>,
 
+    /// directory whose every `*.json` config file runs, in name order.
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["config", "destination"])]
+    pub config_dir: Option<PathBuf>,
+
+    /// what to do with a file of `--config-dir` which can't be parsed.
+    #[arg(long, value_enum, default_value_t = InvalidPolicy::Error)]
+    pub on_invalid_config: InvalidPolicy,
+
+    /// run the valid entries of a config, leaving out with a warning the
+    /// ones which aren't, rather than running none.
+    #[arg(long)]
+    pub skip_invalid: bool,
+
     /// destination directory path(either absolute or relative path).
     #[arg(long, short)]
     pub destination: Option<PathBuf>,
@@ -17,10 +39,24 @@ pub struct Engine {
     #[arg(long, short, value_enum)]
     pub kind: Option<Kind>,
 
-    /// List of patterns to remove(comma separated value).
+    /// List of patterns to remove(comma separated value). Patterns match
+    /// names, not paths: `build`, `build/` and `./build` are the same folder
+    /// pattern, and patterns may use `*` and `?`. A folder pattern with a
+    /// separator inside (`build/release`) is a path from the destination,
+    /// where `*` stays within one folder and `**` spans any number of them
+    /// (`*/target` vs `**/target`).
+    /// File patterns match the extension, or the stem or name with `stem:`
+    /// or `name:`.
     #[arg(long, short, action = ArgAction::Append, value_delimiter = ',')]
     pub patterns: Option<Vec<String>>,
 
+    /// well-known folders to remove under the destination (comma separated
+    /// value), each only next to its project's manifest, e.g. `target` next
+    /// to `Cargo.toml` for `rust`. Stands in for `--kind` and `--patterns`,
+    /// or comes on top of them.
+    #[arg(long, value_enum, value_delimiter = ',', conflicts_with_all = ["config", "config_dir"])]
+    pub presets: Option<Vec<Preset>>,
+
     /// List of items to be excluded from remove(comma separated value).
     #[arg(long, short, action = ArgAction::Append, value_delimiter = ',')]
     pub exclude: Option<Vec<String>>,
@@ -28,6 +64,229 @@ pub struct Engine {
     /// dry-run to check list of item to be removed.
     #[arg(long)]
     pub dryrun: bool,
+
+    /// dry-run that fails with 1 when anything would be removed, e.g. in CI;
+    /// any error fails with 2.
+    #[arg(long)]
+    pub assert_clean: bool,
+
+    /// with a dry-run, exit with 3 when anything would be removed and 0
+    /// when there's nothing to do; any error exits with 2.
+    #[arg(long)]
+    pub exit_code: bool,
+
+    /// explain why each item was removed, kept or skipped.
+    #[arg(long)]
+    pub explain: bool,
+
+    /// show nothing but the summary.
+    #[arg(long, short, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// show excluded and skipped items too, and with `-vv` why every item
+    /// is removed or kept.
+    #[arg(long, short, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// print how much was removed and kept below each folder right in a
+    /// destination, once it's walked.
+    #[arg(long)]
+    pub rollup: bool,
+
+    /// what to do when a destination doesn't exist.
+    #[arg(long, value_enum, default_value_t = MissingPolicy::Error)]
+    pub on_missing_destination: MissingPolicy,
+
+    /// what to do when a destination is a symlink to a folder.
+    #[arg(long, value_enum, default_value_t = LinkedPolicy::Follow)]
+    pub on_linked_destination: LinkedPolicy,
+
+    /// write the removed items (would-be removed in dry-run) to this manifest.
+    #[arg(long)]
+    pub manifest: Option<PathBuf>,
+
+    /// append a line per item removed (or failed) to this file, with the
+    /// time, user and config hash; unlike the manifest it's kept across
+    /// runs.
+    #[arg(long, value_name = "FILE")]
+    pub audit_log: Option<PathBuf>,
+
+    /// record in this file what each config is about to remove, then each
+    /// item as soon as it's gone, for `--resume` to pick up an interrupted
+    /// run. Left alone in dry-run.
+    #[arg(long)]
+    pub progress: Option<PathBuf>,
+
+    /// remove what a previous run's `--progress` file planned and didn't get
+    /// to, without walking the tree again, and keep adding to it.
+    #[arg(long, conflicts_with = "progress")]
+    pub resume: Option<PathBuf>,
+
+    /// what a failed removal does to the rest of the run.
+    #[arg(long, value_enum, default_value_t = ErrorPolicy::KeepGoing)]
+    pub error_policy: ErrorPolicy,
+
+    /// what to do with an entry of a folder that can't be removed.
+    #[arg(long, value_enum, default_value_t = PermissionPolicy::Abort)]
+    pub on_permission_denied: PermissionPolicy,
+
+    /// count the space files take on disk rather than their length.
+    #[arg(long)]
+    pub allocated_size: bool,
+
+    /// give up on removing a single item after this many seconds.
+    #[arg(long, value_name = "SECS")]
+    pub item_timeout: Option<u64>,
+
+    /// only run the configs tagged with one of these (comma separated),
+    /// every config when none.
+    #[arg(long, action = ArgAction::Append, value_delimiter = ',', value_name = "TAG")]
+    pub group: Option<Vec<String>>,
+
+    /// stop the whole run once more than this many items couldn't be
+    /// removed; unlimited by default.
+    #[arg(long, value_name = "COUNT")]
+    pub max_errors: Option<usize>,
+
+    /// remove only this percent of the matches, picked at random, to try a
+    /// new rule on part of the tree first.
+    #[arg(long, value_name = "PERCENT")]
+    pub sample: Option<f64>,
+
+    /// seed of `--sample` picks, for a run to pick the same items again;
+    /// random by default.
+    #[arg(long, requires = "sample")]
+    pub seed: Option<u64>,
+
+    /// only run the configs whose `interval` elapsed since their last run.
+    #[arg(long)]
+    pub due: bool,
+
+    /// skip folders unchanged since a run found nothing to remove in them.
+    /// Only a folder's own modification time counts, so a change deeper
+    /// down goes unnoticed until every folder above it changed too. Configs with a rule
+    /// on time (`older_than`, `dir_quota`, `since_session_start`) are always
+    /// walked in full.
+    #[arg(long)]
+    pub incremental: bool,
+
+    /// ask before the first real run of a config, after showing its dry-run.
+    #[arg(long)]
+    pub confirm_first_run: bool,
+
+    /// print nothing when nothing was removed, the summary otherwise.
+    #[arg(long)]
+    pub summary_only_on_change: bool,
+
+    /// how to print the summary at the end of the run.
+    #[arg(long, value_enum, default_value_t = SummaryFormat::Human)]
+    pub summary_format: SummaryFormat,
+
+    /// print the summary as JSON, progress goes to stderr.
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// stream every action as a JSON line, then the summary.
+    #[arg(long, conflicts_with = "json")]
+    pub json_lines: bool,
+
+    /// write the summary (or the JSON lines) to this file instead of stdout,
+    /// which only gets the brief summary (and no item lines unless `-v`).
+    #[arg(long)]
+    pub report_file: Option<PathBuf>,
+
+    /// remove exactly the items listed in a previous manifest file.
+    #[arg(long, conflicts_with_all = ["config", "config_dir", "destination"])]
+    pub replay: Option<PathBuf>,
+
+    /// with `--replay`, accept a manifest from before manifests carried a
+    /// checksum, which nothing can tell apart from an edited one.
+    #[arg(
+        long,
+        requires = "replay",
+        conflicts_with_all = ["config", "config_dir", "destination"]
+    )]
+    pub allow_unsigned_manifest: bool,
+
+    /// move matched items into this folder instead of removing them.
+    #[arg(long, value_name = "DIR")]
+    pub quarantine: Option<PathBuf>,
+
+    /// move every item quarantined by a failed run back where it was.
+    #[arg(long, requires = "quarantine")]
+    pub rollback_on_error: bool,
+
+    /// skip items another process has open instead of failing on them.
+    #[arg(long)]
+    pub skip_in_use: bool,
+
+    /// run up to this many configs at once, those whose destinations are
+    /// apart from each other; their progress lines interleave.
+    #[arg(long, default_value_t = 1)]
+    pub parallel: usize,
+
+    /// with `--parallel`, run configs on the same device one after another.
+    #[arg(long)]
+    pub one_per_device: bool,
+
+    /// read the headers of matched zip and gzip files, for the plan to show
+    /// what they hold once unpacked; they're still removed as one file.
+    #[arg(long)]
+    pub inspect_archives: bool,
+
+    /// in dry-run, sum up the items removed for the same reason in one
+    /// line, e.g. `src/**/target: 312 item(s)`, rather than one line each.
+    #[arg(long)]
+    pub prune_dry_run_output: bool,
+
+    /// list every item after all, overriding `--prune-dry-run-output`.
+    #[arg(long)]
+    pub full: bool,
+
+    /// a destination holding a file of this name is left alone, e.g.
+    /// during maintenance; an empty name turns the check off.
+    #[arg(long, default_value = ".no-cleanup")]
+    pub sentinel: String,
+
+    /// print what the run would remove as a JSON plan, then stop.
+    #[arg(long, conflicts_with_all = ["replay", "execute_plan"])]
+    pub print_plan_json: bool,
+
+    /// print the plan as newline-delimited JSON instead, one action per
+    /// line for `jq` and the like, then stop.
+    #[arg(long, conflicts_with_all = ["print_plan_json", "replay", "execute_plan"])]
+    pub print_plan_ndjson: bool,
+
+    /// remove exactly the items of a plan from `--print-plan-json` or
+    /// `--print-plan-ndjson`.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["config", "config_dir", "destination", "replay"]
+    )]
+    pub execute_plan: Option<PathBuf>,
+}
+
+impl Engine {
+    /// The level `--quiet` and `--verbose` ask for.
+    pub fn verbosity(&self) -> Verbosity {
+        match (self.quiet, self.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Subcommand)]
+pub enum Command {
+    /// print the configs that would run, with their includes read, their
+    /// `${NAME}` filled from the environment and their defaults filled in.
+    #[command(alias = "print-config")]
+    Show,
+    /// pick the items to remove from a checklist, then remove them.
+    Review,
 }
 
 #[cfg(test)]
@@ -189,4 +448,446 @@ mod tests {
         );
         assert!(engine.dryrun);
     }
+
+    #[test]
+    fn explain() {
+        let args = vec!["neaten", "-d", ".", "-k", "file", "-p", "log", "--explain"];
+        let result = Engine::try_parse_from(args);
+        assert!(result.is_ok());
+        assert!(result.unwrap().explain);
+
+        let args = vec!["neaten", "-d", ".", "-k", "file", "-p", "log"];
+        assert!(!Engine::try_parse_from(args).unwrap().explain);
+    }
+
+    #[test]
+    fn verbosity() {
+        for (args, verbosity) in [
+            (vec![], Verbosity::Normal),
+            (vec!["-q"], Verbosity::Quiet),
+            (vec!["--verbose"], Verbosity::Verbose),
+            (vec!["-vv"], Verbosity::Debug),
+            (vec!["-v", "-v", "-v"], Verbosity::Debug),
+        ] {
+            let args = [vec!["neaten", "-c", "config.json"], args].concat();
+            let engine = Engine::try_parse_from(args).unwrap();
+            assert_eq!(engine.verbosity(), verbosity);
+        }
+
+        let args = vec!["neaten", "-c", "config.json", "-q", "-v"];
+        assert!(Engine::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn on_missing_destination() {
+        let args = vec!["neaten", "-c", "config.json"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.on_missing_destination, MissingPolicy::Error);
+
+        let args = vec![
+            "neaten",
+            "-c",
+            "config.json",
+            "--on-missing-destination",
+            "warn",
+        ];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.on_missing_destination, MissingPolicy::Warn);
+    }
+
+    #[test]
+    fn presets() {
+        let args = vec!["neaten", "-d", ".", "--presets", "rust,node"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.presets, Some(vec![Preset::Rust, Preset::Node]));
+        assert!(engine.kind.is_none());
+
+        let args = vec!["neaten", "-c", "config.json", "--presets", "python"];
+        assert!(Engine::try_parse_from(args).is_err());
+        let args = vec!["neaten", "-d", ".", "--presets", "cobol"];
+        assert!(Engine::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn skip_invalid() {
+        let args = vec!["neaten", "-c", "config.json"];
+        assert!(!Engine::try_parse_from(args).unwrap().skip_invalid);
+        let args = vec!["neaten", "-c", "config.json", "--skip-invalid"];
+        assert!(Engine::try_parse_from(args).unwrap().skip_invalid);
+    }
+
+    #[test]
+    fn config_dir() {
+        let args = vec!["neaten", "--config-dir", "configs"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.config_dir.unwrap(), PathBuf::from("configs"));
+        assert_eq!(engine.on_invalid_config, InvalidPolicy::Error);
+
+        let args = vec![
+            "neaten",
+            "--config-dir",
+            "configs",
+            "--on-invalid-config",
+            "skip",
+        ];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.on_invalid_config, InvalidPolicy::Skip);
+
+        let args = vec!["neaten", "--config-dir", "configs", "-c", "config.json"];
+        assert!(Engine::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn on_linked_destination() {
+        let args = vec!["neaten", "-c", "config.json"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.on_linked_destination, LinkedPolicy::Follow);
+
+        let args = vec![
+            "neaten",
+            "-c",
+            "config.json",
+            "--on-linked-destination",
+            "refuse",
+        ];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.on_linked_destination, LinkedPolicy::Refuse);
+    }
+
+    #[test]
+    fn manifest_and_replay() {
+        let args = vec!["neaten", "-c", "config.json", "--manifest", "run.json"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.manifest.unwrap(), PathBuf::from("run.json"));
+
+        let args = vec!["neaten", "--replay", "run.json", "--dryrun"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.replay.unwrap(), PathBuf::from("run.json"));
+        assert!(!engine.allow_unsigned_manifest);
+
+        let args = vec![
+            "neaten",
+            "--replay",
+            "old.json",
+            "--allow-unsigned-manifest",
+        ];
+        assert!(
+            Engine::try_parse_from(args)
+                .unwrap()
+                .allow_unsigned_manifest
+        );
+        let args = vec!["neaten", "-c", "config.json", "--allow-unsigned-manifest"];
+        assert!(Engine::try_parse_from(args).is_err());
+
+        let args = vec!["neaten", "-c", "config.json", "--replay", "run.json"];
+        assert!(Engine::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn progress_and_resume() {
+        let args = vec!["neaten", "-c", "config.json", "--progress", "run.jsonl"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.progress, Some(PathBuf::from("run.jsonl")));
+
+        let args = vec!["neaten", "-c", "config.json", "--resume", "run.jsonl"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.resume, Some(PathBuf::from("run.jsonl")));
+
+        let args = vec!["neaten", "-c", "c.json", "--resume", "a", "--progress", "b"];
+        assert!(Engine::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn assert_clean() {
+        let args = vec!["neaten", "-c", "config.json", "--assert-clean"];
+        assert!(Engine::try_parse_from(args).unwrap().assert_clean);
+    }
+
+    #[test]
+    fn exit_code() {
+        let args = vec!["neaten", "-c", "config.json", "--dryrun"];
+        assert!(!Engine::try_parse_from(args).unwrap().exit_code);
+
+        let args = vec!["neaten", "-c", "config.json", "--dryrun", "--exit-code"];
+        assert!(Engine::try_parse_from(args).unwrap().exit_code);
+    }
+
+    #[test]
+    fn quarantine() {
+        let args = vec!["neaten", "-c", "config.json", "--quarantine", "held"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.quarantine.unwrap(), PathBuf::from("held"));
+        assert!(!engine.rollback_on_error);
+
+        let args = vec!["neaten", "-c", "config.json", "--rollback-on-error"];
+        assert!(Engine::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn skip_in_use() {
+        let args = vec!["neaten", "-c", "config.json", "--skip-in-use"];
+        assert!(Engine::try_parse_from(args).unwrap().skip_in_use);
+    }
+
+    #[test]
+    fn inspect_archives() {
+        let args = vec!["neaten", "-c", "config.json", "--inspect-archives"];
+        assert!(Engine::try_parse_from(args).unwrap().inspect_archives);
+    }
+
+    #[test]
+    fn prune_dry_run_output() {
+        let args = vec!["neaten", "-c", "config.json", "--prune-dry-run-output"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert!(engine.prune_dry_run_output);
+        assert!(!engine.full);
+
+        let args = vec!["neaten", "-c", "c.json", "--prune-dry-run-output", "--full"];
+        assert!(Engine::try_parse_from(args).unwrap().full);
+    }
+
+    #[test]
+    fn parallel() {
+        let args = vec!["neaten", "-c", "config.json"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.parallel, 1);
+        assert!(!engine.one_per_device);
+
+        let args = vec![
+            "neaten",
+            "-c",
+            "config.json",
+            "--parallel",
+            "4",
+            "--one-per-device",
+        ];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.parallel, 4);
+        assert!(engine.one_per_device);
+    }
+
+    #[test]
+    fn sentinel() {
+        let args = vec!["neaten", "-c", "config.json"];
+        assert_eq!(
+            Engine::try_parse_from(args).unwrap().sentinel,
+            ".no-cleanup"
+        );
+
+        let args = vec!["neaten", "-c", "config.json", "--sentinel", ".frozen"];
+        assert_eq!(Engine::try_parse_from(args).unwrap().sentinel, ".frozen");
+    }
+
+    #[test]
+    fn print_and_execute_plan() {
+        let args = vec!["neaten", "-c", "config.json", "--print-plan-json"];
+        assert!(Engine::try_parse_from(args).unwrap().print_plan_json);
+
+        let args = vec!["neaten", "-c", "config.json", "--print-plan-ndjson"];
+        assert!(Engine::try_parse_from(args).unwrap().print_plan_ndjson);
+        let args = vec![
+            "neaten",
+            "-c",
+            "config.json",
+            "--print-plan-json",
+            "--print-plan-ndjson",
+        ];
+        assert!(Engine::try_parse_from(args).is_err());
+
+        let args = vec!["neaten", "--execute-plan", "plan.json"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.execute_plan.unwrap(), PathBuf::from("plan.json"));
+
+        let args = vec!["neaten", "-c", "config.json", "--execute-plan", "plan.json"];
+        assert!(Engine::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn error_policy() {
+        let args = vec!["neaten", "-c", "config.json"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.error_policy, ErrorPolicy::KeepGoing);
+
+        let args = vec!["neaten", "-c", "config.json", "--error-policy", "fail-fast"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.error_policy, ErrorPolicy::FailFast);
+    }
+
+    #[test]
+    fn json() {
+        let args = vec!["neaten", "-c", "config.json", "--json"];
+        assert!(Engine::try_parse_from(args).unwrap().json);
+    }
+
+    #[test]
+    fn confirm_first_run() {
+        let args = vec!["neaten", "-c", "config.json", "--confirm-first-run"];
+        assert!(Engine::try_parse_from(args).unwrap().confirm_first_run);
+    }
+
+    #[test]
+    fn on_permission_denied() {
+        let args = vec!["neaten", "-c", "config.json"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.on_permission_denied, PermissionPolicy::Abort);
+
+        let args = vec![
+            "neaten",
+            "-c",
+            "config.json",
+            "--on-permission-denied",
+            "fix",
+        ];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.on_permission_denied, PermissionPolicy::Fix);
+    }
+
+    #[test]
+    fn due() {
+        let args = vec!["neaten", "-c", "config.json", "--due"];
+        assert!(Engine::try_parse_from(args).unwrap().due);
+    }
+
+    #[test]
+    fn rollup() {
+        let args = vec!["neaten", "-c", "config.json", "--rollup"];
+        assert!(Engine::try_parse_from(args).unwrap().rollup);
+    }
+
+    #[test]
+    fn incremental() {
+        let args = vec!["neaten", "-c", "config.json", "--incremental"];
+        assert!(Engine::try_parse_from(args).unwrap().incremental);
+    }
+
+    #[test]
+    fn allocated_size() {
+        let args = vec!["neaten", "-c", "config.json", "--allocated-size"];
+        assert!(Engine::try_parse_from(args).unwrap().allocated_size);
+
+        let args = vec!["neaten", "-c", "config.json"];
+        assert!(!Engine::try_parse_from(args).unwrap().allocated_size);
+    }
+
+    #[test]
+    fn item_timeout() {
+        let args = vec!["neaten", "-c", "config.json", "--item-timeout", "30"];
+        assert_eq!(Engine::try_parse_from(args).unwrap().item_timeout, Some(30));
+
+        let args = vec!["neaten", "-c", "config.json"];
+        assert!(Engine::try_parse_from(args).unwrap().item_timeout.is_none());
+    }
+
+    #[test]
+    fn group() {
+        let args = vec!["neaten", "-c", "config.json", "--group", "ci,nightly"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(
+            engine.group,
+            Some(vec![String::from("ci"), String::from("nightly")])
+        );
+
+        let args = vec!["neaten", "-c", "config.json"];
+        assert!(Engine::try_parse_from(args).unwrap().group.is_none());
+    }
+
+    #[test]
+    fn max_errors() {
+        let args = vec!["neaten", "-c", "config.json", "--max-errors", "100"];
+        assert_eq!(Engine::try_parse_from(args).unwrap().max_errors, Some(100));
+
+        let args = vec!["neaten", "-c", "config.json"];
+        assert!(Engine::try_parse_from(args).unwrap().max_errors.is_none());
+    }
+
+    #[test]
+    fn sample() {
+        let args = vec![
+            "neaten",
+            "-c",
+            "config.json",
+            "--sample",
+            "1.5",
+            "--seed",
+            "7",
+        ];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!((engine.sample, engine.seed), (Some(1.5), Some(7)));
+
+        // a seed means nothing without a sample
+        let args = vec!["neaten", "-c", "config.json", "--seed", "7"];
+        assert!(Engine::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn summary_format() {
+        let args = vec!["neaten", "-c", "config.json"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.summary_format, SummaryFormat::Human);
+
+        let args = vec!["neaten", "-c", "config.json", "--summary-format", "logfmt"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.summary_format, SummaryFormat::Logfmt);
+    }
+
+    #[test]
+    fn json_lines() {
+        let args = vec!["neaten", "-c", "config.json", "--json-lines"];
+        assert!(Engine::try_parse_from(args).unwrap().json_lines);
+
+        let args = vec!["neaten", "-c", "config.json", "--json-lines", "--json"];
+        assert!(Engine::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn audit_log() {
+        let args = vec!["neaten", "-c", "config.json", "--audit-log", "audit.log"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.audit_log, Some(PathBuf::from("audit.log")));
+    }
+
+    #[test]
+    fn report_file() {
+        let args = vec!["neaten", "-c", "config.json", "--json"];
+        assert!(Engine::try_parse_from(args).unwrap().report_file.is_none());
+
+        let args = vec![
+            "neaten",
+            "-c",
+            "config.json",
+            "--report-file",
+            "out/run.json",
+        ];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.report_file, Some(PathBuf::from("out/run.json")));
+    }
+
+    #[test]
+    fn show() {
+        let args = vec!["neaten", "-c", "config.json", "show"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.command, Some(Command::Show));
+        assert!(!engine.json);
+
+        let args = vec!["neaten", "-c", "config.json", "print-config", "--json"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.command, Some(Command::Show));
+        assert!(engine.json);
+
+        let args = vec!["neaten", "-c", "config.json"];
+        assert!(Engine::try_parse_from(args).unwrap().command.is_none());
+    }
+
+    #[test]
+    fn review() {
+        let args = vec!["neaten", "-c", "config.json", "review"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert_eq!(engine.command, Some(Command::Review));
+    }
+
+    #[test]
+    fn summary_only_on_change() {
+        let args = vec!["neaten", "-c", "config.json", "--summary-only-on-change"];
+        assert!(Engine::try_parse_from(args).unwrap().summary_only_on_change);
+    }
 }
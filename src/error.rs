@@ -1,6 +1,7 @@
 // https://learning-rust.github.io/docs/custom-error-types/
 // https://www.youtube.com/watch?v=KrZ0nmpNVOw&t=1401s
 
+use crate::config::Config;
 use clap::Error as ClapError;
 use serde_json::Error as SerdeJsonError;
 use std::{
@@ -9,6 +10,7 @@ use std::{
     error::Error,
     fmt::{Debug, Display, Formatter, Result as FmtResult},
     io::Error as IoError,
+    path::Path,
 };
 
 fn is_backtrace_enabled() -> bool {
@@ -21,6 +23,10 @@ fn is_backtrace_enabled() -> bool {
 pub struct AppError {
     kind: AppErrorKind,
     message: String,
+    /// position of the config entry the error is about, among the run's.
+    config_index: Option<usize>,
+    /// `label` of that config entry, when it has one.
+    label: Option<String>,
     backtrace: Backtrace,
     // source: Option<Box<dyn Error>>,
 }
@@ -34,18 +40,67 @@ pub enum AppErrorKind {
 }
 
 impl AppError {
+    /// Exit code of a run which failed with an error, whatever the error.
+    pub const EXIT_CODE: i32 = 2;
+
     pub fn new(kind: AppErrorKind, message: impl Into<String>) -> Self {
         Self {
             kind,
             message: message.into(),
+            config_index: None,
+            label: None,
             backtrace: Backtrace::capture(),
         }
     }
 
+    /// Name `config`, by where it was read among the run's configs, as the
+    /// one the error is about, unless one already is.
+    pub fn in_config(mut self, config: &Config) -> Self {
+        if self.config_index.is_none() {
+            self.config_index = Some(config.index);
+            self.label = config.label.clone();
+        }
+        self
+    }
+
+    /// Name the config `file` the error was found in, keeping the config
+    /// entry it's about.
+    pub fn in_file(mut self, file: &Path) -> Self {
+        self.message = format!("config file {:?} is invalid, {}", file, self.message);
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn config_index(&self) -> Option<usize> {
+        self.config_index
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// The message, after the config entry it's about.
+    pub(crate) fn described(&self) -> String {
+        match (self.config_index, &self.label) {
+            (Some(index), Some(label)) => {
+                format!("config #{} '{}': {}", index, label, self.message)
+            }
+            (Some(index), None) => format!("config #{}: {}", index, self.message),
+            (None, _) => self.message.clone(),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        Self::EXIT_CODE
+    }
+
     pub fn exit(&self) -> ! {
         // TODO: replace eprintln!() with user defined/passing Formatter.
         eprintln!("{:?}", self);
-        std::process::exit(0);
+        std::process::exit(self.exit_code());
     }
 
     // fn message(&self) -> String {
@@ -69,7 +124,10 @@ impl AppError {
     }
 
     fn formatted_usage(&self) -> String {
-        let mut msg = format!("\u{1b}[1m\u{1b}[31merror:\u{1b}[0m {}!\n\n", &self.message);
+        let mut msg = format!(
+            "\u{1b}[1m\u{1b}[31merror:\u{1b}[0m {}!\n\n",
+            self.described()
+        );
         msg = format!(
             "{}\u{1b}[1m\u{1b}[4mUsage:\u{1b}[0m \u{1b}[1m{}\u{1b}[0m [OPTIONS]\n\n",
             msg,
@@ -83,11 +141,17 @@ impl AppError {
     }
 
     fn formatted_functional(&self) -> String {
-        format!("\n\u{1b}[1m\u{1b}[31merror:\u{1b}[0m {}!\n", &self.message)
+        format!(
+            "\n\u{1b}[1m\u{1b}[31merror:\u{1b}[0m {}!\n",
+            self.described()
+        )
     }
 
     fn formatted_internal(&self) -> String {
-        let mut msg = format!("\u{1b}[1m\u{1b}[31merror:\u{1b}[0m {}!\n\n", &self.message);
+        let mut msg = format!(
+            "\u{1b}[1m\u{1b}[31merror:\u{1b}[0m {}!\n\n",
+            self.described()
+        );
         msg = format!(
             "{}\u{1b}[1m{}: {}\u{1b}[0m\n\n",
             msg,
@@ -0,0 +1,128 @@
+use crate::ExecutionSummary;
+use serde::Serialize;
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// One action of a run, streamed as a JSON line with `--json-lines`.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// item removed, or would be in dry-run.
+    Removed {
+        path: &'a Path,
+        size: u64,
+        dryrun: bool,
+    },
+    /// item left alone without looking further.
+    Skipped { path: &'a Path, reason: String },
+    /// item which couldn't be removed.
+    Failed { path: &'a Path, error: String },
+    /// totals of the whole run, always the last event.
+    Summary(&'a ExecutionSummary),
+}
+
+/// Progress of a run, for a library user to show it however they like.
+#[derive(Debug, PartialEq)]
+pub enum ProgressEvent<'a> {
+    /// a config starts walking its destination.
+    Started { destination: &'a Path },
+    /// item removed, or found in dry-run, with the run's totals so far.
+    Item {
+        path: &'a Path,
+        size: u64,
+        removed: usize,
+        bytes: u64,
+    },
+    /// the run is over, with its totals.
+    Finished { removed: usize, bytes: u64 },
+}
+
+type Callback = Box<dyn FnMut(ProgressEvent) + Send>;
+
+/// Callback of `Manager::set_progress_callback`, if any, with the totals so
+/// far: configs running in parallel share it.
+#[derive(Clone, Default)]
+pub struct Listener(Option<Arc<Mutex<(Callback, usize, u64)>>>);
+
+impl Listener {
+    pub fn new(callback: impl FnMut(ProgressEvent) + Send + 'static) -> Self {
+        Self(Some(Arc::new(Mutex::new((Box::new(callback), 0, 0)))))
+    }
+
+    pub fn started(&self, destination: &Path) {
+        self.call(|_, _| ProgressEvent::Started { destination });
+    }
+
+    pub fn item(&self, path: &Path, size: u64) {
+        self.call(|removed, bytes| {
+            *removed += 1;
+            *bytes += size;
+            ProgressEvent::Item {
+                path,
+                size,
+                removed: *removed,
+                bytes: *bytes,
+            }
+        });
+    }
+
+    /// Report the end of the run, and start the totals over for the next.
+    pub fn finished(&self, summary: &ExecutionSummary) {
+        self.call(|removed, bytes| {
+            (*removed, *bytes) = (0, 0);
+            ProgressEvent::Finished {
+                removed: summary.removed,
+                bytes: summary.bytes,
+            }
+        });
+    }
+
+    fn call<'a>(&self, event: impl FnOnce(&mut usize, &mut u64) -> ProgressEvent<'a>) {
+        if let Some(Ok(mut listener)) = self.0.as_ref().map(|l| l.lock()) {
+            let (callback, removed, bytes) = &mut *listener;
+            callback(event(removed, bytes));
+        }
+    }
+}
+
+impl Debug for Listener {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Listener")
+    }
+}
+
+// who listens doesn't change what a run is asked to do
+impl PartialEq for Listener {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialize() {
+        let path = Path::new("target");
+        let event = Event::Removed {
+            path,
+            size: 3,
+            dryrun: false,
+        };
+        assert_eq!(
+            serde_json::to_value(event).unwrap(),
+            json!({ "event": "removed", "path": "target", "size": 3, "dryrun": false })
+        );
+
+        let summary = ExecutionSummary::new(true);
+        let value = serde_json::to_value(Event::Summary(&summary)).unwrap();
+        assert_eq!(value["event"], "summary");
+        assert_eq!(value["dryrun"], true);
+        assert_eq!(value["removed"], 0);
+    }
+}
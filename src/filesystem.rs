@@ -0,0 +1,162 @@
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    fs, io,
+    ops::Deref,
+    path::Path,
+    sync::Arc,
+};
+
+/// Filesystem operations a run removes items through. Every method defaults
+/// to `std::fs`, so a replacement only overrides what it needs to.
+pub trait FileSystem: Send + Sync {
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn hard_link(&self, original: &Path, link: &Path) -> io::Result<()> {
+        fs::hard_link(original, link)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<u64> {
+        fs::copy(from, to)
+    }
+
+    /// Size of the volume holding `path` and the bytes still available on
+    /// it, in that order.
+    fn space(&self, path: &Path) -> io::Result<(u64, u64)> {
+        self::space(path)
+    }
+
+    /// Inodes of the volume holding `path` and those still free, in that
+    /// order; a total of 0 where the volume doesn't count them.
+    fn inodes(&self, path: &Path) -> io::Result<(u64, u64)> {
+        self::inodes(path)
+    }
+
+    /// Device `path` (or what it links to) is on, `None` where that can't
+    /// be told.
+    fn device(&self, path: &Path) -> Option<u64> {
+        self::device(path)
+    }
+}
+
+#[cfg(unix)]
+fn device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::metadata(path).ok().map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+fn space(path: &Path) -> io::Result<(u64, u64)> {
+    let stat = nix::sys::statvfs::statvfs(path)?;
+    let fragment = stat.fragment_size() as u64;
+    Ok((
+        stat.blocks() as u64 * fragment,
+        stat.blocks_available() as u64 * fragment,
+    ))
+}
+
+#[cfg(unix)]
+fn inodes(path: &Path) -> io::Result<(u64, u64)> {
+    let stat = nix::sys::statvfs::statvfs(path)?;
+    Ok((stat.files() as u64, stat.files_available() as u64))
+}
+
+#[cfg(not(unix))]
+fn inodes(_: &Path) -> io::Result<(u64, u64)> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "inodes aren't counted on this platform",
+    ))
+}
+
+#[cfg(windows)]
+fn space(path: &Path) -> io::Result<(u64, u64)> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    let (mut available, mut total) = (0u64, 0u64);
+    // SAFETY: `wide` is NUL-terminated and outlives the call, the out
+    // pointers are to live locals.
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            &mut total,
+            std::ptr::null_mut(),
+        )
+    };
+    match ok {
+        0 => Err(io::Error::last_os_error()),
+        _ => Ok((total, available)),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn space(_: &Path) -> io::Result<(u64, u64)> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "free space isn't known on this platform",
+    ))
+}
+
+/// The actual disk.
+pub struct RealFs;
+
+impl FileSystem for RealFs {}
+
+/// Shared handle on the `FileSystem` a `Manager` uses.
+#[derive(Clone)]
+pub struct Handle(Arc<dyn FileSystem>);
+
+impl Handle {
+    pub fn new(fs: impl FileSystem + 'static) -> Self {
+        Self(Arc::new(fs))
+    }
+}
+
+impl Default for Handle {
+    fn default() -> Self {
+        Self::new(RealFs)
+    }
+}
+
+impl Deref for Handle {
+    type Target = dyn FileSystem;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl Debug for Handle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "FileSystem")
+    }
+}
+
+// the backend doesn't change what a run is asked to do
+impl PartialEq for Handle {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
@@ -1,10 +1,37 @@
+mod archive;
+mod audit;
+mod cancel;
 mod config;
+mod decision;
 mod engine;
 mod error;
+mod event;
+mod filesystem;
 mod manager;
+mod manifest;
+mod output;
+mod plan;
+mod prompt;
+mod render;
+mod review;
+mod state;
+mod summary;
+mod unicode;
 
-pub use config::{Config, Kind};
-pub use engine::Engine;
+pub use cancel::Cancel;
+pub use config::{
+    Charset, Config, ConfigEntry, ErrorPolicy, InvalidPolicy, KeepCopy, Kind, LinkedPolicy,
+    MissingPolicy, Pattern, PermissionPolicy, Preset, ShortfallPolicy, SymlinkPolicy, TimeBasis,
+};
+pub use engine::{Command, Engine};
+pub use event::{Event, ProgressEvent};
+pub use filesystem::{FileSystem, RealFs};
 pub use manager::Manager;
+pub use manifest::Manifest;
+pub use output::{Output, Verbosity};
+pub use plan::PlannedAction;
+pub use prompt::Prompt;
+pub use render::{SummaryFormat, SummaryRenderer};
+pub use summary::{DestinationSummary, ExecutionSummary, SampleSummary, VolumeSummary};
 
 pub type Result<T> = std::result::Result<T, error::AppError>;
@@ -1,13 +1,51 @@
 use clap::Parser;
-use neaten::{Engine, Manager};
+use neaten::{Command, Engine, Manager, Output, SummaryFormat};
 
 fn main() {
     // setup command using clap
     let engine = Engine::parse();
 
+    // a summary for another program to read
+    let print_plan = engine.print_plan_json || engine.print_plan_ndjson;
+    let machine = print_plan
+        || engine.report_file.is_none()
+            && (engine.json
+                || engine.json_lines
+                || !matches!(
+                    engine.summary_format,
+                    SummaryFormat::Human | SummaryFormat::Silent
+                ));
+    let command = engine.command.clone();
+
     let mut manager = Manager::new();
+    // keep stdout for the summary
+    if machine {
+        manager.set_output(Output::stderr());
+    }
+    // finish the item in progress on Ctrl-C, then stop
+    let cancel = manager.cancel_handle();
+    ctrlc::set_handler(move || cancel.cancel()).expect("Ctrl-C handler is set once");
+
     // validate user input
     manager.validate(engine).unwrap_or_else(|err| err.exit());
-    // execute
-    manager.execute().unwrap(); //.unwrap_or_else(|err| err.exit());
+    if print_plan {
+        manager.print_plan().unwrap_or_else(|err| err.exit());
+        return;
+    }
+    let summary = match command {
+        Some(Command::Show) => {
+            manager.show().unwrap_or_else(|err| err.exit());
+            return;
+        }
+        Some(Command::Review) => manager.review(),
+        // execute
+        None => manager.execute(),
+    };
+    let summary = summary.unwrap_or_else(|err| err.exit());
+    manager.report(&summary).unwrap_or_else(|err| err.exit());
+
+    let code = manager.exit_code(&summary);
+    if code != 0 {
+        std::process::exit(code);
+    }
 }
@@ -1,17 +1,120 @@
 use crate::{
-    Config, Engine, Kind,
+    Cancel, Config, ConfigEntry, Engine, ErrorPolicy, Event, ExecutionSummary, FileSystem,
+    InvalidPolicy, KeepCopy, Kind, LinkedPolicy, Manifest, MissingPolicy, Output, Pattern,
+    PermissionPolicy, PlannedAction, ProgressEvent, Prompt, SampleSummary, ShortfallPolicy,
+    SummaryFormat, SymlinkPolicy, TimeBasis, Verbosity, VolumeSummary, archive, audit, config,
+    decision::{Decision, Reason},
     error::{AppError, AppErrorKind},
+    event::Listener,
+    filesystem::{self, RealFs},
+    manifest, plan, review,
+    state::{self, Index, LastRuns, Trusted},
+    unicode,
 };
+use regex::Regex;
 use serde::Deserialize;
 use std::{
+    collections::{HashMap, HashSet},
     fs,
+    io::{BufReader, IsTerminal},
     path::{self, Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
 };
 
 #[derive(Deserialize, Debug, PartialEq)]
 pub struct Manager {
     configs: Vec<Config>,
     dryrun: bool,
+    assert_clean: bool,
+    /// dry-run exits with `PENDING` when it found anything.
+    pending_code: bool,
+    explain: bool,
+    rollup: bool,
+    on_missing_destination: MissingPolicy,
+    on_linked_destination: LinkedPolicy,
+    manifest: Option<PathBuf>,
+    progress: Option<PathBuf>,
+    /// leave out the config entries which aren't valid rather than failing.
+    skip_invalid: bool,
+    /// file every run appends what it removed to, never truncated.
+    audit_log: Option<PathBuf>,
+    /// items an interrupted run already removed, from `--resume`.
+    #[serde(skip)]
+    done: HashSet<PathBuf>,
+    /// what an interrupted run planned and didn't get to, from `--resume`;
+    /// none when its progress file holds no plan.
+    #[serde(skip)]
+    resumed: Option<Vec<PlannedAction>>,
+    replay: Option<PathBuf>,
+    /// whether `replay` takes a manifest without a checksum, with a warning.
+    allow_unsigned_manifest: bool,
+    plan_file: Option<PathBuf>,
+    /// `print_plan` writes one action per line rather than an array.
+    plan_lines: bool,
+    quarantine: Option<PathBuf>,
+    rollback_on_error: bool,
+    skip_in_use: bool,
+    /// configs run at once at most.
+    parallel: usize,
+    one_per_device: bool,
+    inspect_archives: bool,
+    /// dry-run prints one line per group of items rather than per item.
+    collapse: bool,
+    sentinel: String,
+    error_policy: ErrorPolicy,
+    /// tags a config needs one of to run, from `--group`; any config runs
+    /// when empty.
+    groups: Vec<String>,
+    /// failed removals a run goes on after, unlimited when none.
+    max_errors: Option<usize>,
+    /// failed removals of the run so far.
+    #[serde(skip)]
+    failures: helper::Failures,
+    /// percent of the matches to remove, the rest kept.
+    sample: Option<f64>,
+    /// what `sample` picks from, the same seed picking the same items.
+    seed: u64,
+    on_permission_denied: PermissionPolicy,
+    item_timeout: Option<Duration>,
+    allocated_size: bool,
+    confirm_first_run: bool,
+    due: bool,
+    incremental: bool,
+    config_file: Option<PathBuf>,
+    config_dir: Option<PathBuf>,
+    state_dir: Option<PathBuf>,
+    summary_only_on_change: bool,
+    json: bool,
+    json_lines: bool,
+    summary_format: SummaryFormat,
+    report_file: Option<PathBuf>,
+    #[serde(skip)]
+    output: Output,
+    #[serde(skip)]
+    report: Output,
+    #[serde(skip)]
+    prompt: Prompt,
+    #[serde(skip)]
+    cancel: Cancel,
+    #[serde(skip)]
+    fs: filesystem::Handle,
+    #[serde(skip)]
+    listener: Listener,
+    /// when the login session began, for `since_session_start`; asked of
+    /// the system when not set.
+    #[serde(skip)]
+    session_start: Option<SystemTime>,
+    /// uid whose items `since_session_start` takes, the running user's when
+    /// not set.
+    #[serde(skip)]
+    session_user: Option<u32>,
+    /// whether stdin and stdout are a terminal, for the review checklist.
+    #[serde(skip)]
+    terminal: bool,
+    /// every config file read, given or in `config_dir`, with those they
+    /// include.
+    #[serde(skip)]
+    sources: Vec<PathBuf>,
 }
 
 impl Manager {
@@ -19,21 +122,213 @@ impl Manager {
         Manager {
             configs: vec![],
             dryrun: false,
+            assert_clean: false,
+            pending_code: false,
+            explain: false,
+            rollup: false,
+            on_missing_destination: MissingPolicy::Error,
+            on_linked_destination: LinkedPolicy::Follow,
+            manifest: None,
+            progress: None,
+            skip_invalid: false,
+            audit_log: None,
+            done: HashSet::new(),
+            resumed: None,
+            replay: None,
+            allow_unsigned_manifest: false,
+            plan_file: None,
+            plan_lines: false,
+            quarantine: None,
+            rollback_on_error: false,
+            skip_in_use: false,
+            parallel: 1,
+            one_per_device: false,
+            inspect_archives: false,
+            collapse: false,
+            sentinel: String::from(".no-cleanup"),
+            error_policy: ErrorPolicy::KeepGoing,
+            groups: Vec::new(),
+            max_errors: None,
+            failures: helper::Failures::default(),
+            sample: None,
+            seed: 0,
+            on_permission_denied: PermissionPolicy::Abort,
+            item_timeout: None,
+            allocated_size: false,
+            confirm_first_run: false,
+            due: false,
+            incremental: false,
+            config_file: None,
+            config_dir: None,
+            state_dir: None,
+            summary_only_on_change: false,
+            json: false,
+            json_lines: false,
+            summary_format: SummaryFormat::Human,
+            report_file: None,
+            output: Output::stdout(),
+            report: Output::stdout(),
+            prompt: Prompt::stdin(),
+            cancel: Cancel::default(),
+            fs: filesystem::Handle::default(),
+            listener: Listener::default(),
+            session_start: None,
+            session_user: None,
+            terminal: std::io::stdin().is_terminal() && std::io::stdout().is_terminal(),
+            sources: Vec::new(),
         }
     }
 
+    /// Send progress lines somewhere other than stdout.
+    pub fn set_output(&mut self, output: Output) {
+        self.output = output;
+    }
+
+    /// Send the final summary somewhere other than stdout.
+    pub fn set_report_output(&mut self, report: Output) {
+        self.report = report;
+    }
+
+    /// Handle to interrupt a run (e.g. from a Ctrl-C handler), which then
+    /// stops once the item in progress is done.
+    pub fn cancel_handle(&self) -> Cancel {
+        self.cancel.clone()
+    }
+
+    /// Ask yes/no questions through `prompt` instead of the terminal.
+    pub fn set_prompt(&mut self, prompt: Prompt) {
+        self.prompt = prompt;
+    }
+
+    /// Keep state between runs in `dir` instead of the platform's default.
+    pub fn set_state_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.state_dir = Some(dir.into());
+    }
+
+    /// Call `callback` as the run goes: when each config starts, for each
+    /// item removed (or found, in dry-run), and once the run is over.
+    pub fn set_progress_callback(&mut self, callback: impl FnMut(ProgressEvent) + Send + 'static) {
+        self.listener = Listener::new(callback);
+    }
+
+    /// Remove items through `fs` instead of the real disk.
+    pub fn set_filesystem(&mut self, fs: impl FileSystem + 'static) {
+        self.fs = filesystem::Handle::new(fs);
+    }
+
     pub fn validate(&mut self, engine: Engine) -> crate::Result<()> {
-        // dryrun
-        self.dryrun = engine.dryrun;
+        // dryrun, always when only checking
+        self.dryrun = engine.dryrun || engine.assert_clean;
+        self.assert_clean = engine.assert_clean;
+        self.pending_code = engine.exit_code;
+        self.explain = engine.explain;
+        // with a report file only the brief summary goes to the terminal,
+        // unless more was asked for
+        let verbosity = match engine.verbosity() {
+            Verbosity::Normal if engine.report_file.is_some() => Verbosity::Quiet,
+            verbosity => verbosity,
+        };
+        self.output.set_verbosity(verbosity);
+        self.skip_invalid = engine.skip_invalid;
+        self.rollup = engine.rollup;
+        self.on_missing_destination = engine.on_missing_destination;
+        self.on_linked_destination = engine.on_linked_destination;
+        self.manifest = engine.manifest;
+        self.audit_log = engine.audit_log;
+        if let Some(path) = engine.resume {
+            let progress = manifest::resume(&path)?;
+            self.done = progress.done.clone();
+            // a list of removed items only, from before plans were recorded,
+            // is resumed by walking again
+            if !progress.planned.is_empty() {
+                self.resumed = Some(progress.left());
+            }
+            self.progress = Some(path);
+        } else if let Some(path) = engine.progress
+            && !self.dryrun
+        {
+            // a fresh run starts a fresh list
+            fs::write(&path, "")?;
+            self.progress = Some(path);
+        }
+        self.error_policy = engine.error_policy;
+        self.on_permission_denied = engine.on_permission_denied;
+        self.item_timeout = engine.item_timeout.map(Duration::from_secs);
+        self.groups = engine.group.unwrap_or_default();
+        self.max_errors = engine.max_errors;
+        if let Some(percent) = engine.sample
+            && !(percent > 0.0 && percent <= 100.0)
+        {
+            return Err(AppError::new(
+                AppErrorKind::Usage,
+                "sample takes a percent above 0 and up to 100",
+            ));
+        }
+        self.sample = engine.sample;
+        self.seed = engine.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64
+        });
+        self.allocated_size = engine.allocated_size;
+        self.confirm_first_run = engine.confirm_first_run;
+        self.due = engine.due;
+        self.incremental = engine.incremental;
+        self.quarantine = engine.quarantine.map(path::absolute).transpose()?;
+        self.rollback_on_error = engine.rollback_on_error;
+        self.skip_in_use = engine.skip_in_use;
+        self.parallel = engine.parallel.max(1);
+        self.one_per_device = engine.one_per_device;
+        self.inspect_archives = engine.inspect_archives;
+        self.collapse = engine.prune_dry_run_output && !engine.full;
+        self.sentinel = engine.sentinel;
+        self.summary_only_on_change = engine.summary_only_on_change;
+        self.json = engine.json;
+        self.json_lines = engine.json_lines;
+        // `--json` is the short way to ask for it
+        self.summary_format = if engine.json {
+            SummaryFormat::Json
+        } else {
+            engine.summary_format
+        };
+        self.report_file = engine.report_file;
+        self.plan_lines = engine.print_plan_ndjson;
+        // the JSON lines wait for the end of the run to go into the file
+        if self.report_file.is_some() && self.json_lines {
+            self.report = Output::null();
+            self.report.hold();
+        }
+
+        // replay a previous run, no rule involved
+        if let Some(path) = engine.replay {
+            if !path.exists() {
+                return Err(AppError::new(
+                    AppErrorKind::Usage,
+                    "manifest file doesn't exists",
+                ));
+            }
+            self.replay = Some(path);
+            self.allow_unsigned_manifest = engine.allow_unsigned_manifest;
+            return Ok(());
+        }
+
+        // an approved plan, no rule involved either
+        if let Some(path) = engine.execute_plan {
+            if !path.exists() {
+                return Err(AppError::new(
+                    AppErrorKind::Usage,
+                    "plan file doesn't exists",
+                ));
+            }
+            self.plan_file = Some(path);
+            return Ok(());
+        }
 
         // config
         if let Some(mut path) = engine.config {
             // check relative or absolute path
-            path = if path.is_relative() {
-                path::absolute(path)?
-            } else {
-                path
-            };
+            path = config::lexical(&path::absolute(path)?);
 
             // config file exists or not
             if !path.exists() {
@@ -58,371 +353,9185 @@ impl Manager {
             }
 
             // parse config file
-            self.parse(path)?;
-            Ok(())
+            self.parse(&path)?;
+            self.config_file = Some(path);
+            self.check()
+        } else if let Some(dir) = engine.config_dir {
+            let dir = config::lexical(&path::absolute(dir)?);
+            if !dir.is_dir() {
+                return Err(AppError::new(
+                    AppErrorKind::Usage,
+                    "config directory doesn't exists",
+                ));
+            }
+            self.parse_dir(&dir, &engine.on_invalid_config)?;
+            self.config_dir = Some(dir);
+            self.check()
         } else {
             let destination = engine.destination.ok_or(AppError::new(
                 AppErrorKind::Usage,
                 "Please provide destination",
             ))?;
-            let kind = engine
-                .kind
-                .ok_or(AppError::new(AppErrorKind::Usage, "Please provide kind"))?;
-
-            let patterns = engine.patterns.ok_or(AppError::new(
-                AppErrorKind::Usage,
-                "Please provide patterns",
-            ))?;
-
-            // validate destination path exists or not
-            if !destination.exists() {
-                return Err(AppError::new(
-                    AppErrorKind::Usage,
-                    "destination doesn't exists",
-                ));
-            }
-
             // make sure destination path is a folder, not file or symlink
-            if !destination.is_dir() {
+            if destination.exists() && !destination.is_dir() {
                 return Err(AppError::new(
                     AppErrorKind::Usage,
                     "destination is not a directory, please provide directory path as destination!",
                 ));
             }
 
-            // format user input
-            self.format(destination, kind, patterns, engine.exclude)?;
-            Ok(())
+            // presets stand in for a kind and patterns, or come on top of them
+            let presets = engine.presets.unwrap_or_default();
+            if presets.is_empty() || engine.kind.is_some() || engine.patterns.is_some() {
+                let kind = engine
+                    .kind
+                    .ok_or(AppError::new(AppErrorKind::Usage, "Please provide kind"))?;
+
+                let patterns = engine.patterns.ok_or(AppError::new(
+                    AppErrorKind::Usage,
+                    "Please provide patterns",
+                ))?;
+
+                // format user input
+                self.format(&destination, kind, patterns, engine.exclude.clone())?;
+            }
+            for mut config in presets.iter().flat_map(|p| p.configs(&destination)) {
+                config.exclude = engine.exclude.clone();
+                config.normalize();
+                self.add(config);
+            }
+            self.check()
         }
     }
 
-    pub fn execute(&self) -> crate::Result<()> {
-        // loop over each config
+    fn check(&mut self) -> crate::Result<()> {
+        self.select_groups()?;
+        self.merge_configs();
         for config in &self.configs {
-            helper::remove(
-                &config.destination,
-                &config.kind,
-                &config.patterns,
-                &config.exclude.clone().unwrap_or_default(),
-                self.dryrun,
-            );
+            Self::check_entry(config)?;
+        }
+        self.check_destinations()?;
+        self.check_config_home()?;
+        self.check_duplicates();
+        self.check_owners()
+    }
 
-            // let mut item = helper::Remove {
-            //     destination: config.destination.clone(),
-            //     kind: config.kind.clone(),
-            //     patterns: config.patterns.clone(),
-            //     dryrun: self.dryrun,
-            // };
-            // helper::remove_as_mut(&mut item);
+    /// What a single config has to get right by itself: every duration
+    /// understood, water marks as percents with the low one not above the
+    /// high one, and a rotation key which compiles.
+    fn check_entry(config: &Config) -> crate::Result<()> {
+        let invalid =
+            |message: String| Err(AppError::new(AppErrorKind::Usage, message).in_config(config));
+        if let Some(interval) = &config.interval
+            && config.interval().is_none()
+        {
+            return invalid(format!(
+                "invalid interval '{}', expected e.g. 12h, 7d or 2w",
+                interval
+            ));
+        }
+        if let Some(older_than) = &config.older_than
+            && config.older_than().is_none()
+        {
+            return invalid(format!(
+                "invalid older_than '{}', expected e.g. 12h, 7d or 2w",
+                older_than
+            ));
+        }
+        if let Some(quota) = &config.dir_quota
+            && config.dir_quota().is_none()
+        {
+            return invalid(format!(
+                "invalid dir_quota '{}', expected e.g. 500MB or 2GB",
+                quota
+            ));
+        }
+        if let Some((high, low)) = config.water_marks()
+            && (high > 100 || low > high)
+        {
+            return invalid(format!(
+                "invalid water marks {}%/{}% of {:?}, expected low_water <= high_water <= 100",
+                high, low, config.destination
+            ));
+        }
+        if let Some((high, low)) = config.inode_marks()
+            && (high > 100 || low > high)
+        {
+            return invalid(format!(
+                "invalid inode marks {}%/{}% of {:?}, expected inode_low_water <= inode_high_water <= 100",
+                high, low, config.destination
+            ));
+        }
+        if let Some(name) = &config.touch_after
+            && Path::new(name).file_name() != Some(name.as_ref())
+        {
+            return invalid(format!(
+                "invalid touch_after '{}', expected a file name",
+                name
+            ));
+        }
+        if let (Some(min), Some(max)) = (config.name_len_min, config.name_len_max)
+            && min > max
+        {
+            return invalid(format!(
+                "invalid name length bounds, name_len_min {} is over name_len_max {}",
+                min, max
+            ));
+        }
+        if let (Some(min), Some(max)) = (config.min_children, config.max_children)
+            && min > max
+        {
+            return invalid(format!(
+                "invalid children bounds, min_children {} is over max_children {}",
+                min, max
+            ));
         }
+        if config.preserve_times && !config.contents_only {
+            return invalid(String::from(
+                "preserve_times only applies with contents_only",
+            ));
+        }
+        if let Some(pattern) = &config.prune_emptied
+            && pattern.is_empty()
+        {
+            return invalid(String::from(
+                "prune_emptied can't be empty, `*` matches any name",
+            ));
+        }
+        if config.move_aside && !config.contents_only {
+            return invalid(String::from("move_aside only applies with contents_only"));
+        }
+        config.rotation().map_err(|e| e.in_config(config))?;
         Ok(())
     }
 
-    fn add(&mut self, config: Config) {
-        self.configs.push(config);
+    /// Keep only the configs tagged with one of `groups`, ignoring case,
+    /// when there are any. Picking no config at all is an error.
+    fn select_groups(&mut self) -> crate::Result<()> {
+        if self.groups.is_empty() {
+            return Ok(());
+        }
+        let groups = &self.groups;
+        self.configs.retain(|config| {
+            config
+                .tags
+                .iter()
+                .flatten()
+                .any(|tag| helper::find(tag, groups, false).is_some())
+        });
+        match self.configs.is_empty() {
+            true => Err(AppError::new(
+                AppErrorKind::Usage,
+                format!("no config is tagged {}", self.groups.join(" or ")),
+            )),
+            false => Ok(()),
+        }
     }
 
-    fn parse<T: AsRef<Path>>(&mut self, path: T) -> crate::Result<()> {
-        let json_data = fs::read_to_string(path)?;
-        self.configs = serde_json::from_str(&json_data)?;
-        Ok(())
+    /// Fold every config into the first one it's `mergeable` with, so a
+    /// destination isn't walked once per config. The merged config has the
+    /// patterns and the excludes of both, each name once.
+    fn merge_configs(&mut self) {
+        let mut configs: Vec<Config> = Vec::with_capacity(self.configs.len());
+        for config in std::mem::take(&mut self.configs) {
+            match configs.iter_mut().find(|merged| merged.mergeable(&config)) {
+                Some(merged) => {
+                    for pattern in config.patterns {
+                        let merged_patterns: Vec<String> =
+                            merged.patterns.iter().map(Pattern::to_string).collect();
+                        if helper::find(pattern.to_string(), &merged_patterns, false).is_none() {
+                            merged.patterns.push(pattern);
+                        }
+                    }
+                    for name in config.exclude.into_iter().flatten() {
+                        let exclude = merged.exclude.get_or_insert_with(Vec::new);
+                        if helper::find(&name, exclude, false).is_none() {
+                            exclude.push(name);
+                        }
+                    }
+                }
+                None => configs.push(config),
+            }
+        }
+        self.configs = configs;
     }
 
-    fn format<T: Into<PathBuf>>(
-        &mut self,
-        destination: T,
-        kind: Kind,
-        patterns: Vec<String>,
-        exclude: Option<Vec<String>>,
-    ) -> crate::Result<()> {
-        self.add(Config::new(destination.into(), kind, patterns, exclude));
-        Ok(())
+    /// Warn about patterns (and excludes) repeating an earlier one, as names
+    /// are matched ignoring case.
+    fn check_duplicates(&self) {
+        for config in &self.configs {
+            let exclude = config.exclude.as_deref().unwrap_or_default();
+            let patterns: Vec<String> = config.patterns.iter().map(Pattern::to_string).collect();
+            for (what, list) in [("pattern", &patterns[..]), ("exclude", exclude)] {
+                for (index, item) in list.iter().enumerate() {
+                    if let Some(first) =
+                        helper::find(item, &list[..index], false).map(|first| &list[first])
+                    {
+                        self.output.line(format_args!(
+                            "\u{1b}[33mWarning\u{1b}[0m {} '{}' repeats '{}' for {:?}...",
+                            what, item, first, config.destination
+                        ));
+                    }
+                }
+            }
+        }
     }
-}
 
-impl Default for Manager {
-    fn default() -> Self {
-        Self::new()
+    /// Leave out the config at `index`, which failed a check with `error`,
+    /// with a warning under `skip_invalid`, or else fail with `error`.
+    fn skip(&self, index: usize, error: AppError) -> crate::Result<()> {
+        if !self.skip_invalid {
+            return Err(error);
+        }
+        self.output.line(format_args!(
+            "\u{1b}[33mWarning\u{1b}[0m config #{} is invalid, {}, skipping...",
+            index,
+            error.message()
+        ));
+        Ok(())
     }
-}
 
-mod helper {
-    use super::*;
+    /// Every `owner` has to name an existing user.
+    #[cfg(unix)]
+    fn check_owners(&mut self) -> crate::Result<()> {
+        let mut configs = Vec::with_capacity(self.configs.len());
+        for config in std::mem::take(&mut self.configs) {
+            if let Some(owner) = &config.owner
+                && helper::uid_of(owner).is_none()
+            {
+                let error =
+                    AppError::new(AppErrorKind::Usage, format!("unknown owner '{}'", owner));
+                self.skip(config.index, error.in_config(&config))?;
+                continue;
+            }
+            configs.push(config);
+        }
+        self.configs = configs;
+        Ok(())
+    }
 
-    #[allow(dead_code)]
-    pub struct Remove {
-        pub destination: PathBuf,
-        pub kind: Kind,
-        pub patterns: Vec<String>,
-        pub exclude: Vec<String>,
-        pub dryrun: bool,
+    #[cfg(not(unix))]
+    fn check_owners(&mut self) -> crate::Result<()> {
+        Ok(())
     }
 
-    impl AsMut<Remove> for Remove {
-        fn as_mut(&mut self) -> &mut Remove {
-            self
+    /// Refuse a config which would remove a folder holding a config file of
+    /// the run, given, in the config directory or included, with everything
+    /// next to it. The walk would leave such a
+    /// folder alone, but a rule that broad is most likely a mistake.
+    fn check_config_home(&mut self) -> crate::Result<()> {
+        let mut configs = Vec::with_capacity(self.configs.len());
+        for config in std::mem::take(&mut self.configs) {
+            let home = path::absolute(&config.destination)
+                .ok()
+                .and_then(|destination| {
+                    self.sources.iter().find_map(|file| {
+                        file.ancestors()
+                            .skip(1)
+                            .take_while(|folder| {
+                                *folder != destination && folder.starts_with(&destination)
+                            })
+                            .find(|folder| {
+                                matches!(helper::judge(folder, &config), Decision::Remove(_))
+                            })
+                            .map(|folder| (folder.to_path_buf(), file))
+                    })
+                });
+            if let Some((home, file)) = home {
+                let error = AppError::new(
+                    AppErrorKind::Usage,
+                    format!(
+                        "would remove {:?}, which holds the config file {:?}",
+                        home, file
+                    ),
+                );
+                self.skip(config.index, error.in_config(&config))?;
+                continue;
+            }
+            configs.push(config);
         }
+        self.configs = configs;
+        Ok(())
     }
 
-    #[allow(dead_code)]
-    pub fn remove_as_mut<T: AsMut<Remove>>(item: &mut T) {
-        let item = item.as_mut();
-
-        if item.destination.exists() {
-            // get child item of kind
-            let children = self::childern(&item.destination, &item.exclude);
+    /// Drop configs whose destination doesn't exist, or fail, as their
+    /// `on_missing_destination` policy (or the run's) says.
+    fn check_destinations(&mut self) -> crate::Result<()> {
+        let mut configs = Vec::with_capacity(self.configs.len());
+        for config in std::mem::take(&mut self.configs) {
+            if config.destination.exists() {
+                match self.check_link(&config) {
+                    Ok(()) => configs.push(config),
+                    Err(e) => self.skip(config.index, e.in_config(&config))?,
+                }
+                continue;
+            }
 
-            // iterate over each child
-            for child in &children {
-                // if match, then remove
-                match self::pattern_check(child, &item.patterns, &item.kind) {
-                    Some(_) => {
-                        // remove child
-                        println!("Removing {:?}...", child);
-                        if !&item.dryrun {
-                            match self::remove_item(child) {
-                                Ok(_) => println!("Removed {:?}...", child),
-                                Err(e) => eprintln!("Error: {}", e),
-                            }
-                        }
-                    }
-                    None => {
-                        if child.is_dir() {
-                            item.destination = child.to_path_buf();
-                            self::remove_as_mut(item);
-                        }
-                    }
+            let policy = config
+                .on_missing_destination
+                .as_ref()
+                .unwrap_or(&self.on_missing_destination);
+            match policy {
+                MissingPolicy::Error => {
+                    let error = AppError::new(
+                        AppErrorKind::Usage,
+                        format!("destination {:?} doesn't exists", config.destination),
+                    );
+                    self.skip(config.index, error.in_config(&config))?;
                 }
+                MissingPolicy::Warn => self.output.line(format_args!(
+                    "\u{1b}[33mWarning\u{1b}[0m destination {:?} doesn't exists, skipping...",
+                    config.destination
+                )),
+                MissingPolicy::Skip => {}
             }
         }
-    }
 
-    // TODO: think remove need to return Result<...>?
-    pub fn remove<P: AsRef<Path>>(
-        destination: P,
-        kind: &Kind,
-        patterns: &[String],
-        exclude: &[String],
-        dryrun: bool,
-    ) {
-        // pub fn remove(destination: &Path, kind: &Kind, patterns: &[String], dryrun: bool) {
-        let destination = destination.as_ref();
-        if destination.exists() {
-            // get child item of kind
-            let children = self::childern(destination, exclude);
+        self.configs = configs;
+        Ok(())
+    }
 
-            // iterate over each child
-            for child in &children {
-                // if match, then remove
-                match self::pattern_check(child, patterns, kind) {
-                    Some(_) => {
-                        // remove child
-                        println!("\u{1b}[91mRemoving\u{1b}[0m {:?}...", child);
-                        if !dryrun {
-                            match self::remove_item(child) {
-                                Ok(_) => println!("\u{1b}[31mRemoved\u{1b}[0m {:?}...", child),
-                                Err(e) => eprintln!("Error: {}", e),
-                            }
-                        }
-                    }
-                    None => {
-                        if child.is_dir() {
-                            self::remove(child, kind, patterns, exclude, dryrun);
-                        }
-                    }
-                }
+    /// Say so when the destination of `config` is a symlink, whose target
+    /// gets walked unless the policy refuses it.
+    fn check_link(&self, config: &Config) -> crate::Result<()> {
+        let Ok(target) = fs::read_link(&config.destination) else {
+            return Ok(());
+        };
+        let policy = config
+            .on_linked_destination
+            .as_ref()
+            .unwrap_or(&self.on_linked_destination);
+        match policy {
+            LinkedPolicy::Follow => {
+                self.output.line(format_args!(
+                    "\u{1b}[33mLinked\u{1b}[0m destination {:?} walks {:?}...",
+                    config.destination, target
+                ));
+                Ok(())
             }
+            LinkedPolicy::Refuse => Err(AppError::new(
+                AppErrorKind::Usage,
+                format!(
+                    "destination {:?} is a symlink to {:?}",
+                    config.destination, target
+                ),
+            )),
         }
     }
 
-    // TODO: return Result<Vec<PathBuf>, AppError>
-    pub fn childern<P: AsRef<Path>>(parent: P, exclude: &[String]) -> Vec<PathBuf> {
-        let mut children = Vec::new();
+    pub fn execute(&self) -> crate::Result<ExecutionSummary> {
+        self.timed(|| self.run())
+    }
 
-        match fs::read_dir(parent) {
-            Ok(entries) => {
-                for entry in entries {
-                    match entry {
-                        Ok(entry) => {
-                            // don't add path that exists in exclude list
-                            let path = entry.path();
-                            let name = path
-                                .file_name()
-                                .unwrap_or_default()
-                                .to_str()
-                                .unwrap_or_default();
-                            match self::find(name, exclude) {
-                                Some(_) => {
-                                    // println!("index: {:?}", index);
-                                    // println!("path: {:?}", name);
-                                    // println!("exclude: {:?}", exclude);
-
-                                    println!("\u{1b}[33mExclude\u{1b}[0m {:?}...", path);
-                                }
-                                None => children.push(path),
-                            }
+    /// `run`, holding back its output as asked and timing it.
+    fn timed<F>(&self, run: F) -> crate::Result<ExecutionSummary>
+    where
+        F: FnOnce() -> crate::Result<ExecutionSummary>,
+    {
+        self.failures.reset();
+        // whether anything gets printed is only known at the end
+        if self.summary_only_on_change {
+            self.output.hold();
+        }
 
-                            // check child is matching with patterns or not
-                            // if *kind == Kind::Folder && entry.file_type().unwrap().is_dir() {
-                            //     children.push(entry.path());
-                            // } else if *kind == Kind::File && entry.file_type().unwrap().is_file() {
-                            //     children.push(entry.path());
-                            // }
-                        }
-                        Err(e) => {
-                            eprintln!("Error reading directory entry: {}", e);
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                eprintln!("Error reading directory: {}", e);
-            }
+        let start = Instant::now();
+        let result = run();
+        if result.is_err() {
+            self.output.release();
         }
+        result.map(|mut summary| {
+            summary.time(start.elapsed());
+            summary
+        })
+    }
 
-        children
+    /// Print the summary of a finished run in the chosen format. With
+    /// `summary_only_on_change` a run which changed nothing prints nothing.
+    pub fn report(&self, summary: &ExecutionSummary) -> crate::Result<()> {
+        let changed = summary.removed > 0 || summary.failures() > 0 || summary.cancelled;
+        if self.summary_only_on_change && !changed {
+            self.output.discard();
+            return Ok(());
+        }
+
+        self.output.release();
+        if let Some(path) = &self.report_file {
+            return self.report_to(path, summary);
+        }
+        if self.json_lines {
+            self.emit(Event::Summary(summary));
+            return Ok(());
+        }
+        let text = self.summary_format.renderer().render(summary)?;
+        if !text.is_empty() {
+            self.report.line(format_args!("{}", text));
+        }
+        Ok(())
     }
 
-    fn find<T: AsRef<str>>(item: T, list: &[String]) -> Option<usize> {
-        let item = item.as_ref();
-        list.iter()
-            .position(|n| n.to_lowercase() == item.to_lowercase())
+    /// Write the summary in the chosen format to `path`, after the JSON lines
+    /// of the run if any, and print only the brief summary.
+    fn report_to(&self, path: &Path, summary: &ExecutionSummary) -> crate::Result<()> {
+        let mut contents = self.report.take();
+        let text = if self.json_lines {
+            serde_json::to_string(&Event::Summary(summary))?
+        } else {
+            self.summary_format.renderer().render(summary)?
+        };
+        if !text.is_empty() {
+            contents.extend_from_slice(text.as_bytes());
+            contents.push(b'\n');
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)?;
+
+        self.output.always(format_args!("{}", summary.brief()));
+        Ok(())
     }
 
-    pub fn pattern_check<P: AsRef<Path>>(
-        path: P,
-        patterns: &[String],
-        kind: &Kind,
-    ) -> Option<usize> {
+    /// Remove `path` alone (or only report it in dry-run), with the same
+    /// output, error policy and safety checks as an item found by a rule.
+    pub fn remove_path<P: AsRef<Path>>(&self, path: P, dryrun: bool) -> crate::Result<()> {
         let path = path.as_ref();
-        // check for folder
-        if *kind == Kind::Folder && path.is_dir() {
-            let name = path
-                .file_name()
-                .unwrap_or_default()
-                .to_str()
-                .unwrap_or_default();
-            self::find(name, patterns)
-            // patterns
-            //     .iter()
-            //     .position(|n| n.to_lowercase() == name.to_lowercase())
-        } else if *kind == Kind::File && path.is_file() {
-            let extn = path
-                .extension()
-                .unwrap_or_default()
-                .to_str()
-                .unwrap_or_default();
-            self::find(extn, patterns)
-            // patterns
-            //     .iter()
-            //     .position(|n| n.to_lowercase() == extn.to_lowercase())
-        } else {
-            None
+        if fs::symlink_metadata(path).is_err() {
+            return Err(AppError::new(
+                AppErrorKind::Usage,
+                format!("{:?} doesn't exists", path),
+            ));
+        }
+        if helper::protected(self, path, None)? {
+            return Err(AppError::new(
+                AppErrorKind::Usage,
+                format!("refusing to remove protected path {:?}", path),
+            ));
+        }
+
+        let mut summary = ExecutionSummary::new(dryrun);
+        let size = helper::size(path, self.allocated_size);
+        helper::remove_one(self, path, size, dryrun, None, &mut summary)?;
+        let summary = self.conclude(summary)?;
+        match summary.errors.first() {
+            Some((path, error)) => Err(AppError::new(
+                AppErrorKind::Functionality,
+                format!("{:?} couldn't be removed: {}", path, error),
+            )),
+            None => Ok(()),
         }
     }
 
-    pub fn remove_item<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
-        if path.as_ref().is_file() {
-            fs::remove_file(path)
+    /// Configs as they would run: destinations made absolute and every
+    /// setting left to a default filled in.
+    pub fn resolved(&self) -> crate::Result<Vec<Config>> {
+        self.configs
+            .iter()
+            .map(|config| self.resolve(config))
+            .collect()
+    }
+
+    fn resolve(&self, config: &Config) -> crate::Result<Config> {
+        let mut config = config.clone();
+        config.destination = path::absolute(&config.destination)?;
+        config.exclude.get_or_insert_with(Vec::new);
+        config
+            .on_missing_destination
+            .get_or_insert_with(|| self.on_missing_destination.clone());
+        config.mode_value = config.mode_value.or(config.mode_mask);
+        Ok(config)
+    }
+
+    /// SHA-256 of the `resolved` configs as compact JSON, the same for any
+    /// run of the same configs.
+    pub fn config_hash(&self) -> crate::Result<String> {
+        config::digest(&self.resolved()?)
+    }
+
+    /// Print the resolved configs instead of running them, as JSON when
+    /// asked to.
+    pub fn show(&self) -> crate::Result<()> {
+        let configs = self.resolved()?;
+        if self.json {
+            self.report
+                .line(format_args!("{}", serde_json::to_string_pretty(&configs)?));
         } else {
-            fs::remove_dir_all(path)
+            for config in &configs {
+                self.report.line(format_args!("{}\n", config));
+            }
         }
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Exit code of a dry-run with `--exit-code` which found something to
+    /// remove.
+    pub const PENDING: i32 = 3;
 
-    #[test]
-    fn create_manager() {
-        let manager = Manager::new();
-        assert_eq!(
-            manager,
-            Manager {
-                configs: vec![],
-                dryrun: false
-            }
-        );
+    /// Exit code for a finished run: 130 when interrupted, as a shell gives
+    /// a process killed by SIGINT, `AppError::EXIT_CODE` when any item or
+    /// step failed, 1 with `assert_clean` and `PENDING` with `--exit-code` when
+    /// anything would have been removed. A run which stopped on an error
+    /// exits with `AppError::EXIT_CODE` too.
+    pub fn exit_code(&self, summary: &ExecutionSummary) -> i32 {
+        if summary.cancelled {
+            130
+        } else if summary.failures() > 0 {
+            AppError::EXIT_CODE
+        } else if self.assert_clean && summary.removed > 0 {
+            1
+        } else if self.pending_code && summary.dryrun && summary.removed > 0 {
+            Self::PENDING
+        } else {
+            0
+        }
+    }
+
+    /// Every item the configs would remove, found without removing (or
+    /// printing) anything. A matched folder is a single action sized with
+    /// all it holds, nothing in it is listed by itself, even when another
+    /// config matches it.
+    pub fn plan(&self) -> crate::Result<Vec<PlannedAction>> {
+        let mut actions = Vec::new();
+        for config in self.configs.iter().filter(|c| !helper::paused(self, c)) {
+            actions.extend(helper::plan(self, config, helper::ToFree::default())?);
+        }
+        let paths: HashSet<PathBuf> = actions.iter().map(|a| a.path.clone()).collect();
+        actions.retain(|action| !action.path.ancestors().skip(1).any(|p| paths.contains(p)));
+        Ok(actions)
+    }
+
+    /// Print `plan` as JSON, for `--execute-plan` to carry out later: an
+    /// array, or with `plan_lines` one action per line.
+    pub fn print_plan(&self) -> crate::Result<()> {
+        let actions = self.plan()?;
+        if self.plan_lines {
+            for action in &actions {
+                self.report
+                    .line(format_args!("{}", serde_json::to_string(action)?));
+            }
+            return Ok(());
+        }
+        let json = serde_json::to_string_pretty(&actions)?;
+        self.report.line(format_args!("{}", json));
+        Ok(())
+    }
+
+    /// Remove exactly `actions` (or only report them in dry-run), as
+    /// `execute` would have removed them, without walking the tree again.
+    /// Each item is looked at anew when its turn comes: one gone since is
+    /// skipped, one of another kind now is removed as what it is.
+    pub fn execute_plan(&self, actions: &[PlannedAction]) -> crate::Result<ExecutionSummary> {
+        self.timed(|| self.run_plan(actions))
+    }
+
+    fn run_plan(&self, actions: &[PlannedAction]) -> crate::Result<ExecutionSummary> {
+        let mut summary = ExecutionSummary::new(self.dryrun);
+        let mut result = Ok(());
+        for action in actions {
+            if self.cancel.is_cancelled() {
+                summary.cancelled = true;
+                break;
+            }
+            summary.start(&action.destination);
+            let removed = summary.removed;
+            result = helper::remove_one(
+                self,
+                &action.path,
+                action.size,
+                self.dryrun,
+                None,
+                &mut summary,
+            );
+            if result.is_err() {
+                break;
+            }
+            if self.collapse && summary.removed > removed {
+                summary.planned.push(action.clone());
+            }
+        }
+
+        // what went is on record even when the run stopped short
+        let written = match &self.manifest {
+            Some(path) => Manifest::from(&summary).write(path),
+            None => Ok(()),
+        };
+        result?;
+        written?;
+        self.conclude(summary)
+    }
+
+    /// Plan the run, let the user pick which items go, then remove those.
+    /// On a terminal that's a checklist, otherwise a single question about
+    /// the whole list.
+    pub fn review(&self) -> crate::Result<ExecutionSummary> {
+        let actions = self.plan()?;
+        if actions.is_empty() {
+            return self.execute_plan(&actions);
+        }
+
+        let chosen = if self.terminal {
+            review::pick(&actions)?
+        } else {
+            for action in &actions {
+                self.output.line(format_args!(
+                    "\u{1b}[91mRemove\u{1b}[0m {:?}...",
+                    action.path
+                ));
+            }
+            let question = format!("Remove these {} item(s)?", actions.len());
+            if self.prompt.ask(&question) {
+                actions
+            } else {
+                Vec::new()
+            }
+        };
+        self.execute_plan(&chosen)
+    }
+
+    /// Stream `event` as a JSON line, with `--json-lines` only.
+    fn emit(&self, event: Event) {
+        if self.json_lines {
+            match serde_json::to_string(&event) {
+                Ok(line) => self.report.line(format_args!("{}", line)),
+                Err(e) => self.output.line(format_args!("Error: {}", e)),
+            }
+        }
+    }
+
+    fn run(&self) -> crate::Result<ExecutionSummary> {
+        if let Some(path) = &self.replay {
+            return self.replay(path, self.dryrun);
+        }
+        if let Some(path) = &self.plan_file {
+            let actions = plan::parse(&fs::read_to_string(path)?)?;
+            return self.run_plan(&actions);
+        }
+        if let Some(actions) = &self.resumed {
+            return self.run_plan(actions);
+        }
+
+        let mut summary = ExecutionSummary::new(self.dryrun);
+        summary.config_hash = Some(self.config_hash()?);
+        summary.sample = self.sample.map(|_| SampleSummary {
+            seed: self.seed,
+            ..Default::default()
+        });
+        let result = self.walk(&mut summary);
+        // only moves can be undone
+        if self.rollback_on_error && (result.is_err() || summary.failures() > 0) {
+            self.rollback(&mut summary);
+        }
+        // what went is on record even when the run stopped short
+        let written = match &self.manifest {
+            Some(path) => Manifest::from(&summary).write(path),
+            None => Ok(()),
+        };
+        result?;
+        written?;
+        if summary.dryrun {
+            summary.volumes = helper::volumes(self, &summary);
+        }
+        self.conclude(summary)
+    }
+
+    /// Every config in turn, accounted in `summary`.
+    fn walk(&self, summary: &mut ExecutionSummary) -> crate::Result<()> {
+        let mut trusted = if self.confirm_first_run && !self.dryrun {
+            Some(self.trusted()?)
+        } else {
+            None
+        };
+        let mut last_runs = if self.due {
+            Some(LastRuns::load(self.state()?)?)
+        } else {
+            None
+        };
+
+        // with `parallel` the configs only run once all are looked at
+        let mut jobs = Vec::new();
+        // how much a config with water marks frees depends on what ran
+        // before it, so these run one at a time after the others
+        let mut marked = Vec::new();
+
+        // loop over each config
+        for config in &self.configs {
+            if self.cancel.is_cancelled() {
+                summary.cancelled = true;
+                break;
+            }
+
+            if helper::paused(self, config) {
+                self.output.line(format_args!(
+                    "\u{1b}[33mPaused\u{1b}[0m {:?}, it holds {:?}...",
+                    config.destination, self.sentinel
+                ));
+                self.emit(Event::Skipped {
+                    path: &config.destination,
+                    reason: format!("holds the sentinel file {:?}", self.sentinel),
+                });
+                if !summary.skipped.contains(&config.destination) {
+                    summary.skipped.push(config.destination.clone());
+                }
+                continue;
+            }
+
+            let fingerprint = config.fingerprint();
+            if let Some(last_runs) = &last_runs
+                && !helper::due(config, last_runs.get(&fingerprint))
+            {
+                self.output.line(format_args!(
+                    "\u{1b}[33mNot due\u{1b}[0m {:?}...",
+                    config.destination
+                ));
+                continue;
+            }
+
+            if self.parallel > 1
+                && (config.water_marks().is_some() || config.inode_marks().is_some())
+            {
+                marked.push((config, fingerprint));
+                continue;
+            }
+            let Some(job) = self.prepare(config, fingerprint, &trusted)? else {
+                continue;
+            };
+            if self.parallel > 1 {
+                jobs.push(job);
+                continue;
+            }
+            let part = helper::run_job(self, &job);
+            self.finish(job, part, summary, &mut last_runs, &mut trusted)?;
+
+            // let mut item = helper::Remove {
+            //     destination: config.destination.clone(),
+            //     kind: config.kind.clone(),
+            //     patterns: config.patterns.clone(),
+            //     dryrun: self.dryrun,
+            // };
+            // helper::remove_as_mut(&mut item);
+        }
+
+        for (job, part) in helper::run_parallel(self, jobs) {
+            self.finish(job, part, summary, &mut last_runs, &mut trusted)?;
+        }
+        for (config, fingerprint) in marked {
+            if self.cancel.is_cancelled() {
+                summary.cancelled = true;
+                break;
+            }
+            if let Some(job) = self.prepare(config, fingerprint, &trusted)? {
+                let part = helper::run_job(self, &job);
+                self.finish(job, part, summary, &mut last_runs, &mut trusted)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The job of `config`, with the space it has to free as of now; none
+    /// when it has nothing to free or its first run wasn't confirmed.
+    fn prepare<'a>(
+        &self,
+        config: &'a Config,
+        fingerprint: String,
+        trusted: &Option<Trusted>,
+    ) -> crate::Result<Option<helper::Job<'a>>> {
+        let to_free = helper::to_free(self, config).map_err(|e| e.in_config(config))?;
+        if to_free.nothing() {
+            self.output.line(format_args!(
+                "\u{1b}[33mNot full\u{1b}[0m {:?}...",
+                config.destination
+            ));
+            return Ok(None);
+        }
+
+        // a config never run before is only trusted once confirmed
+        let first_run = match trusted {
+            Some(trusted) => !trusted.contains(&fingerprint),
+            None => false,
+        };
+        if first_run
+            && !self
+                .confirm(config, to_free)
+                .map_err(|e| e.in_config(config))?
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(helper::Job {
+            config,
+            to_free,
+            fingerprint,
+            first_run,
+        }))
+    }
+
+    /// Account the run of `job` in `summary`, then what follows a config
+    /// which ran: its `after` command, its last run and its trust.
+    fn finish(
+        &self,
+        job: helper::Job,
+        (part, result): helper::Ran,
+        summary: &mut ExecutionSummary,
+        last_runs: &mut Option<LastRuns>,
+        trusted: &mut Option<Trusted>,
+    ) -> crate::Result<()> {
+        let config = job.config;
+        let failures = summary.failures();
+        let count = part.removed;
+        let removed = count > 0;
+        // what went before an error counts all the same
+        summary.merge(part);
+        result?;
+
+        // fewer matches than expected usually means the rule went stale
+        if let Some(min) = config.min_matches
+            && count < min
+            && !summary.cancelled
+        {
+            let message = format!("matched {} of at least {}", count, min);
+            match config.on_few_matches.clone().unwrap_or_default() {
+                ShortfallPolicy::Error => summary.fail_step(&config.destination, message),
+                ShortfallPolicy::Warn => self.output.line(format_args!(
+                    "\u{1b}[33mWarning\u{1b}[0m {:?} {}",
+                    config.destination, message
+                )),
+            }
+        }
+
+        // the follow-up only once something actually went
+        if let Some(after) = &config.after
+            && !self.dryrun
+            && removed
+            && let Err(e) = helper::after(&self.output, after)
+        {
+            summary.fail_step(&config.destination, e);
+        }
+
+        // a complete run leaves its mark for other tools to see
+        if let Some(name) = &config.touch_after
+            && !self.dryrun
+            && !summary.cancelled
+            && summary.failures() == failures
+        {
+            let sentinel = config.destination.join(name);
+            if let Err(e) = helper::touch(&sentinel) {
+                summary.fail_step(
+                    &config.destination,
+                    format!("couldn't touch sentinel {:?}: {}", sentinel, e),
+                );
+            }
+        }
+
+        // only a complete run resets the interval
+        if let Some(last_runs) = last_runs.as_mut()
+            && !self.dryrun
+            && !summary.cancelled
+            && summary.failures() == failures
+        {
+            last_runs.set(job.fingerprint.clone(), SystemTime::now())?;
+        }
+
+        if let Some(trusted) = trusted.as_mut()
+            && job.first_run
+        {
+            trusted.insert(job.fingerprint)?;
+        }
+        Ok(())
+    }
+
+    /// Move every quarantined item of `summary` back where it came from,
+    /// the last moved first, and out of its totals: the way it went in, so
+    /// copied back from another device. What can't be is one more failure.
+    fn rollback(&self, summary: &mut ExecutionSummary) {
+        while let Some((origin, held)) = summary.quarantined.pop() {
+            let folder = fs::symlink_metadata(&held).is_ok_and(|m| m.is_dir());
+            match helper::hold(
+                &*self.fs,
+                &held,
+                &origin,
+                folder,
+                &self.on_permission_denied,
+                &self.output,
+            ) {
+                Ok(_) => {
+                    self.output
+                        .line(format_args!("\u{1b}[33mRestored\u{1b}[0m {:?}...", origin));
+                    summary.unrecord(&origin);
+                }
+                Err(e) => {
+                    self.output.line(format_args!("Error: {}", e));
+                    summary.fail(&origin, format!("couldn't restore from {:?}: {}", held, e));
+                }
+            }
+        }
+    }
+
+    fn trusted(&self) -> crate::Result<Trusted> {
+        Trusted::load(self.state()?)
+    }
+
+    fn state(&self) -> crate::Result<PathBuf> {
+        self.state_dir
+            .clone()
+            .or_else(state::dir)
+            .ok_or(AppError::new(
+                AppErrorKind::Functionality,
+                "couldn't find a directory to keep state in",
+            ))
+    }
+
+    /// Show what `config` would remove and ask whether to go ahead for real.
+    fn confirm(&self, config: &Config, to_free: helper::ToFree) -> crate::Result<bool> {
+        self.output.line(format_args!(
+            "\u{1b}[1mFirst run\u{1b}[0m of {:?}, checking what it would remove...",
+            config.destination
+        ));
+        let mut preview = ExecutionSummary::new(true);
+        helper::remove(self, config, &mut preview, to_free)?;
+
+        let question = format!(
+            "Remove these {} item(s) from {:?} for real?",
+            preview.removed, config.destination
+        );
+        // what's asked about is shown first, even while output is held back
+        self.output.flush();
+        let confirmed = self.prompt.ask(&question);
+        if !confirmed {
+            self.output.line(format_args!(
+                "\u{1b}[33mSkip\u{1b}[0m {:?}...",
+                config.destination
+            ));
+        }
+        Ok(confirmed)
+    }
+
+    /// Remove exactly the items recorded in the manifest at `path`, skipping
+    /// the ones that are already gone. No pattern is evaluated. A manifest
+    /// without a checksum is only taken with `allow_unsigned_manifest`.
+    pub fn replay<P: AsRef<Path>>(&self, path: P, dryrun: bool) -> crate::Result<ExecutionSummary> {
+        let path = path.as_ref();
+        let manifest = Manifest::read(path)?;
+        if manifest.checksum.is_none() {
+            if !self.allow_unsigned_manifest {
+                return Err(AppError::new(
+                    AppErrorKind::Functionality,
+                    format!(
+                        "manifest {:?} has no checksum, so it can't be told from an edited one; \
+                         replay it with --allow-unsigned-manifest if it's that old",
+                        path
+                    ),
+                ));
+            }
+            self.output.always(format_args!(
+                "\u{1b}[33mWarning\u{1b}[0m manifest {:?} has no checksum, replayed unverified",
+                path
+            ));
+        }
+        let mut summary = ExecutionSummary::new(dryrun);
+
+        for entry in &manifest.entries {
+            if self.cancel.is_cancelled() {
+                summary.cancelled = true;
+                break;
+            }
+
+            let path = &entry.path;
+            if fs::symlink_metadata(path).is_err() {
+                self.output
+                    .line(format_args!("\u{1b}[33mMissing\u{1b}[0m {:?}...", path));
+                continue;
+            }
+            let size = helper::size(path, self.allocated_size);
+            helper::remove_one(self, path, size, dryrun, None, &mut summary)?;
+        }
+        self.conclude(summary)
+    }
+
+    /// Under `ErrorPolicy::KeepGoing` the failures of a finished run stay in
+    /// its summary, reported with the rest once everything else has been
+    /// removed; see `exit_code`.
+    fn conclude(&self, summary: ExecutionSummary) -> crate::Result<ExecutionSummary> {
+        self.listener.finished(&summary);
+        if summary.dryrun && self.collapse {
+            for group in plan::collapse(&summary.planned) {
+                self.output.line(format_args!(
+                    "\u{1b}[91mRemoving\u{1b}[0m {:?}: {}: {} item(s) ({} bytes)",
+                    group.destination, group.pattern, group.count, group.size
+                ));
+            }
+        }
+        Ok(summary)
+    }
+
+    fn add(&mut self, mut config: Config) {
+        config.index = self.configs.len();
+        self.configs.push(config);
+    }
+
+    /// Read the configs of the JSON file at `path` entry by entry, each
+    /// checked by itself as soon as it's read, and those of the files it
+    /// includes in their place. Relative destinations are from the file's
+    /// folder, not the current one. With `skip_invalid` an entry which isn't
+    /// valid is left out with a warning rather than failing the whole file.
+    fn parse<T: AsRef<Path>>(&mut self, path: T) -> crate::Result<()> {
+        let mut reading = helper::Reading::default();
+        self.read(&path::absolute(path)?, &mut reading)?;
+        self.configs = reading.configs;
+        self.sources.append(&mut reading.files);
+        Ok(())
+    }
+
+    fn read(&self, path: &Path, reading: &mut helper::Reading) -> crate::Result<()> {
+        let path = config::lexical(path);
+        if reading.within.contains(&path) {
+            return Err(AppError::new(
+                AppErrorKind::Usage,
+                format!("config file {:?} includes itself", path),
+            ));
+        }
+        let file = fs::File::open(&path).map_err(|e| {
+            AppError::new(
+                AppErrorKind::Usage,
+                format!("config file {:?} can't be read, {}", path, e),
+            )
+        })?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        reading.within.push(path.clone());
+        reading.files.push(path.clone());
+        Config::stream(BufReader::new(file), |entry| {
+            let config = match entry {
+                Ok(ConfigEntry::Include(file)) => return self.read(&dir.join(file), reading),
+                Ok(ConfigEntry::Config(config)) => Ok(*config),
+                Err(e) => Err(e),
+            };
+            let index = reading.read;
+            reading.read += 1;
+            let checked = config.and_then(|mut config| {
+                config.index = index;
+                config.normalize();
+                config.resolve(dir)?;
+                Self::check_entry(&config)?;
+                Ok(config)
+            });
+            match checked {
+                Ok(config) => reading.configs.push(config),
+                Err(e) => self.skip(index, e)?,
+            }
+            Ok(())
+        })?;
+        reading.within.pop();
+        Ok(())
+    }
+
+    /// `parse` every JSON file in `dir` by name, one after the other, their
+    /// configs numbered on from those of the files before. A file which
+    /// can't be parsed fails the run or is left out, as `policy` says.
+    fn parse_dir<T: AsRef<Path>>(&mut self, dir: T, policy: &InvalidPolicy) -> crate::Result<()> {
+        let mut files: Vec<PathBuf> = helper::entries(dir.as_ref(), &self.output)
+            .into_iter()
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|extn| extn.eq_ignore_ascii_case("json"))
+            })
+            .collect();
+        files.sort();
+
+        let mut reading = helper::Reading::default();
+        for file in files {
+            let (configs, files) = (reading.configs.len(), reading.files.len());
+            if let Err(e) = self.read(&file, &mut reading) {
+                reading.configs.truncate(configs);
+                reading.files.truncate(files);
+                reading.within.clear();
+                let error = e.in_file(&file);
+                match policy {
+                    InvalidPolicy::Error => return Err(error),
+                    InvalidPolicy::Skip => {
+                        self.output.line(format_args!(
+                            "\u{1b}[33mWarning\u{1b}[0m {}, skipping...",
+                            error.described()
+                        ));
+                    }
+                }
+            }
+        }
+        self.configs = reading.configs;
+        self.sources.append(&mut reading.files);
+        Ok(())
+    }
+
+    fn format<T: Into<PathBuf>>(
+        &mut self,
+        destination: T,
+        kind: Kind,
+        patterns: Vec<String>,
+        exclude: Option<Vec<String>>,
+    ) -> crate::Result<()> {
+        let mut config = Config::new(destination.into(), kind, patterns, exclude);
+        config.normalize();
+        self.add(config);
+        Ok(())
+    }
+}
+
+impl Default for Manager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+mod helper {
+    use super::*;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    };
+
+    /// The configs of a file read so far, with the includes it's in the
+    /// middle of, for one which includes itself again to fail rather than
+    /// loop.
+    #[derive(Default)]
+    pub struct Reading {
+        pub configs: Vec<Config>,
+        pub read: usize,
+        pub within: Vec<PathBuf>,
+        pub files: Vec<PathBuf>,
+    }
+
+    #[allow(dead_code)]
+    pub struct Remove {
+        pub destination: PathBuf,
+        pub kind: Kind,
+        pub patterns: Vec<String>,
+        pub exclude: Vec<String>,
+        pub dryrun: bool,
+        pub output: Output,
+    }
+
+    impl AsMut<Remove> for Remove {
+        fn as_mut(&mut self) -> &mut Remove {
+            self
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn remove_as_mut<T: AsMut<Remove>>(item: &mut T) {
+        let item = item.as_mut();
+
+        if item.destination.exists() {
+            // get child item of kind
+            let out = item.output.clone();
+            let children = self::childern(&item.destination, &item.exclude, &out);
+            let patterns: Vec<Pattern> = item.patterns.iter().map(|p| p.as_str().into()).collect();
+
+            // iterate over each child
+            for child in &children {
+                // if match, then remove
+                match self::decide(child, &patterns, &item.kind, false) {
+                    Decision::Remove(_) => {
+                        // remove child
+                        out.line(format_args!("Removing {:?}...", child));
+                        if !&item.dryrun {
+                            match self::remove_item(
+                                &RealFs,
+                                child,
+                                &PermissionPolicy::Abort,
+                                None,
+                                &out,
+                            ) {
+                                Ok(_) => out.line(format_args!("Removed {:?}...", child)),
+                                Err(e) => out.line(format_args!("Error: {}", e)),
+                            }
+                        }
+                    }
+                    _ => {
+                        if child.is_dir() {
+                            item.destination = child.to_path_buf();
+                            self::remove_as_mut(item);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// A folder `move_aside` moved items into, removed on a thread of its
+    /// own, with the items (and sizes) to record once it's gone.
+    struct Aside {
+        folder: PathBuf,
+        moved: Vec<(PathBuf, u64)>,
+        thread: std::thread::JoinHandle<std::io::Result<()>>,
+    }
+
+    /// State of walking the destination of a single config.
+    struct Walk<'a> {
+        manager: &'a Manager,
+        config: &'a Config,
+        summary: &'a mut ExecutionSummary,
+        /// where progress of the walk goes.
+        out: Output,
+        /// matches collected instead of removed, when only planning.
+        plan: Option<Vec<PlannedAction>>,
+        /// patterns of every `.cleanupignore` from the destination down.
+        ignores: Vec<String>,
+        /// compiled `rotation_key` of the config.
+        rotation: Option<Regex>,
+        /// folders walked into through a symlink, to never loop.
+        followed: HashSet<PathBuf>,
+        /// folders found clean before, with `incremental`.
+        index: Option<Index>,
+        /// with `dedup_content`, every copy to remove with the one kept.
+        duplicates: Option<HashMap<PathBuf, PathBuf>>,
+        /// with water marks, the `summary.bytes` to stop removing at.
+        until: Option<u64>,
+        /// with inode marks, the number of `inodes` to stop removing at.
+        inodes_until: Option<u64>,
+        /// inodes freed so far, only counted with inode marks.
+        inodes: u64,
+        /// with `since_session_start`, when the user's login session began.
+        session: Option<SystemTime>,
+        /// with `one_filesystem`, the device of the destination.
+        device: Option<u64>,
+        /// folder the walk just took everything out of, for `prune`.
+        emptied: Option<PathBuf>,
+        /// with `move_aside`, the folders being removed in the background.
+        aside: Vec<Aside>,
+        /// items kept (or skipped) so far.
+        kept: usize,
+        /// (device, inode) of the hard-linked files kept so far.
+        #[cfg(unix)]
+        links: HashSet<(u64, u64)>,
+    }
+
+    impl<'a> Walk<'a> {
+        fn new(
+            manager: &'a Manager,
+            config: &'a Config,
+            summary: &'a mut ExecutionSummary,
+            out: Output,
+            plan: Option<Vec<PlannedAction>>,
+        ) -> crate::Result<Self> {
+            // an unchanged folder may hold a match by now with a rule on time
+            let index = match manager.incremental && !config.time_dependent() {
+                true => Some(Index::load(manager.state()?, &config.fingerprint())?),
+                false => None,
+            };
+            let duplicates = match &config.dedup_content {
+                Some(keep) => Some(self::duplicates(manager, config, keep)?),
+                None => None,
+            };
+            let session = match config.since_session_start {
+                true => Some(
+                    manager
+                        .session_start
+                        .or_else(self::session_start)
+                        .ok_or_else(|| {
+                            AppError::new(
+                                AppErrorKind::Functionality,
+                                "couldn't tell when the login session started",
+                            )
+                        })?,
+                ),
+                false => None,
+            };
+            Ok(Self {
+                manager,
+                config,
+                summary,
+                out,
+                plan,
+                ignores: Vec::new(),
+                rotation: config.rotation()?,
+                followed: HashSet::new(),
+                index,
+                duplicates,
+                until: None,
+                inodes_until: None,
+                inodes: 0,
+                session,
+                device: config
+                    .one_filesystem
+                    .then(|| manager.fs.device(&config.destination))
+                    .flatten(),
+                emptied: None,
+                aside: Vec::new(),
+                kept: 0,
+                #[cfg(unix)]
+                links: HashSet::new(),
+            })
+        }
+
+        /// Stop taking items once `to_free` is freed.
+        fn until_free(&mut self, to_free: ToFree) {
+            self.until = to_free
+                .bytes
+                .filter(|bytes| *bytes > 0)
+                .map(|bytes| self.summary.bytes + bytes);
+            self.inodes_until = to_free.inodes.filter(|inodes| *inodes > 0);
+        }
+    }
+
+    // TODO: think remove need to return Result<...>?
+    pub fn remove(
+        manager: &Manager,
+        config: &Config,
+        summary: &mut ExecutionSummary,
+        to_free: ToFree,
+    ) -> crate::Result<()> {
+        // what's about to go is recorded first, for `--resume` to pick up
+        if let Some(progress) = &manager.progress
+            && !summary.dryrun
+        {
+            let actions = self::plan(manager, config, to_free)?;
+            if let Err(e) = manifest::plan(progress, &actions) {
+                self::unrecorded(manager, progress, e);
+            }
+        }
+        let mut walk = Walk::new(manager, config, summary, manager.output.clone(), None)?;
+        walk.until_free(to_free);
+        let walked = self::walk_destination(&mut walk);
+        let cleared = self::clear_aside(&mut walk);
+        walked?;
+        cleared?;
+        match &walk.index {
+            Some(index) => index.save(),
+            None => Ok(()),
+        }
+    }
+
+    /// A config about to run, once the run decided it should.
+    pub struct Job<'a> {
+        pub config: &'a Config,
+        pub to_free: ToFree,
+        pub fingerprint: String,
+        pub first_run: bool,
+    }
+
+    /// `remove` for `job`, accounted in a summary of its own.
+    pub fn run_job(manager: &Manager, job: &Job) -> Ran {
+        let mut part = ExecutionSummary::new(manager.dryrun);
+        // the job's own config, for the audit log to tell which one
+        // removed what
+        if manager.audit_log.is_some() {
+            let config = manager.resolve(job.config);
+            part.config_hash = match config.and_then(|config| config::digest(&[config])) {
+                Ok(hash) => Some(hash),
+                Err(e) => return (part, Err(e)),
+            };
+        }
+        if manager.cancel.is_cancelled() {
+            part.cancelled = true;
+            return (part, Ok(()));
+        }
+        part.start(&job.config.destination);
+        manager.listener.started(&job.config.destination);
+        let result = self::remove(manager, job.config, &mut part, job.to_free)
+            .map_err(|e| e.in_config(job.config));
+        (part, result)
+    }
+
+    /// What a job did, kept even when it stopped on an error, and how it
+    /// ended.
+    pub type Ran = (ExecutionSummary, crate::Result<()>);
+
+    /// Run `jobs` on up to `parallel` threads, handed back in their order
+    /// with how each went. Jobs which can't run side by side, see
+    /// `groups`, go one after another on the same thread. Under
+    /// `ErrorPolicy::FailFast` the jobs which never started after a failure
+    /// aren't handed back.
+    pub fn run_parallel<'a>(manager: &Manager, jobs: Vec<Job<'a>>) -> Vec<(Job<'a>, Ran)> {
+        use std::{sync::Mutex, thread};
+
+        let groups = Mutex::new(self::groups(manager, &jobs).into_iter());
+        let parts = Mutex::new(HashMap::new());
+        let failed = AtomicBool::new(false);
+        let threads = manager.parallel.min(jobs.len());
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    while let Some(group) = groups.lock().ok().and_then(|mut g| g.next()) {
+                        for i in group {
+                            // under FailFast a failure anywhere stops the
+                            // jobs not started yet, which are left out
+                            let stop = manager.error_policy == ErrorPolicy::FailFast
+                                && (failed.load(Ordering::SeqCst) || manager.failures.count() > 0);
+                            let part = match stop {
+                                true => None,
+                                false => Some(self::run_job(manager, &jobs[i])),
+                            };
+                            if matches!(part, Some((_, Err(_)))) {
+                                failed.store(true, Ordering::SeqCst);
+                            }
+                            if let Ok(mut parts) = parts.lock() {
+                                parts.insert(i, part);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        let mut parts = parts.into_inner().unwrap_or_default();
+        jobs.into_iter()
+            .enumerate()
+            .filter_map(|(i, job)| {
+                let part = match parts.remove(&i) {
+                    Some(None) => return None,
+                    Some(Some(part)) => part,
+                    None => (
+                        ExecutionSummary::new(manager.dryrun),
+                        Err(AppError::new(
+                            AppErrorKind::Functionality,
+                            "a worker thread stopped before its config ran",
+                        )),
+                    ),
+                };
+                Some((job, part))
+            })
+            .collect()
+    }
+
+    /// `jobs` (by position) gathered so that two jobs in different groups
+    /// can run at the same time: their destinations are apart from each
+    /// other and, with `one_per_device`, on different devices.
+    pub fn groups(manager: &Manager, jobs: &[Job]) -> Vec<Vec<usize>> {
+        let devices: Vec<Option<u64>> = jobs
+            .iter()
+            // an unknown device counts as one and the same
+            .map(|job| manager.fs.device(&job.config.destination))
+            .collect();
+        let conflict = |a: usize, b: usize| {
+            let (a_dest, b_dest) = (&jobs[a].config.destination, &jobs[b].config.destination);
+            a_dest.starts_with(b_dest)
+                || b_dest.starts_with(a_dest)
+                || (manager.one_per_device && devices[a] == devices[b])
+        };
+
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for i in 0..jobs.len() {
+            let (joined, mut apart): (Vec<_>, Vec<_>) = groups
+                .into_iter()
+                .partition(|group| group.iter().any(|&j| conflict(i, j)));
+            let mut group = joined.concat();
+            group.push(i);
+            group.sort();
+            apart.push(group);
+            groups = apart;
+        }
+        groups
+    }
+
+    /// Free space of each volume the destinations of `summary` are on, now
+    /// and once their bytes are freed. A destination whose device isn't
+    /// known counts as a volume of its own, one whose free space isn't
+    /// known is left out.
+    pub fn volumes(manager: &Manager, summary: &ExecutionSummary) -> Vec<VolumeSummary> {
+        let mut volumes: Vec<(Option<u64>, VolumeSummary)> = Vec::new();
+        for destination in &summary.destinations {
+            let device = manager.fs.device(&destination.destination);
+            let known = volumes
+                .iter_mut()
+                .find(|(other, _)| device.is_some() && *other == device);
+            let volume = match known {
+                Some((_, volume)) => volume,
+                None => {
+                    let Ok((_, free)) = manager.fs.space(&destination.destination) else {
+                        continue;
+                    };
+                    let volume = VolumeSummary {
+                        free,
+                        ..Default::default()
+                    };
+                    volumes.push((device, volume));
+                    &mut volumes.last_mut().unwrap().1
+                }
+            };
+            volume.destinations.push(destination.destination.clone());
+            volume.freed += destination.bytes;
+            volume.projected_free = volume.free + volume.freed;
+        }
+        volumes.into_iter().map(|(_, volume)| volume).collect()
+    }
+
+    /// What `remove` would remove for `config`, silently and touching
+    /// nothing.
+    pub fn plan(
+        manager: &Manager,
+        config: &Config,
+        to_free: ToFree,
+    ) -> crate::Result<Vec<PlannedAction>> {
+        let mut summary = ExecutionSummary::new(true);
+        let plan = Some(Vec::new());
+        let mut walk = Walk::new(manager, config, &mut summary, Output::null(), plan)?;
+        walk.until_free(to_free);
+        self::walk_destination(&mut walk)?;
+        if let Some(index) = &walk.index {
+            index.save()?;
+        }
+        Ok(walk.plan.unwrap_or_default())
+    }
+
+    /// What the water marks of a config ask it to free, `None` for a mark
+    /// it doesn't have and 0 while the volume isn't over it.
+    #[derive(Clone, Copy, Default, Debug, PartialEq)]
+    pub struct ToFree {
+        pub bytes: Option<u64>,
+        pub inodes: Option<u64>,
+    }
+
+    impl ToFree {
+        /// Whether the config has marks and the volume is over none of them.
+        pub fn nothing(&self) -> bool {
+            (self.bytes.is_some() || self.inodes.is_some())
+                && self.bytes.unwrap_or(0) == 0
+                && self.inodes.unwrap_or(0) == 0
+        }
+    }
+
+    /// What `config` has to free to get its destination's volume back under
+    /// its low water marks.
+    pub fn to_free(manager: &Manager, config: &Config) -> crate::Result<ToFree> {
+        Ok(ToFree {
+            bytes: self::bytes_to_free(manager, config)?,
+            inodes: self::inodes_to_free(manager, config)?,
+        })
+    }
+
+    /// With inode marks, how many inodes `config` has to free to get back
+    /// under `inode_low_water`. Where the volume doesn't count inodes the
+    /// marks are ignored, with a warning.
+    fn inodes_to_free(manager: &Manager, config: &Config) -> crate::Result<Option<u64>> {
+        let Some((high, low)) = config.inode_marks() else {
+            return Ok(None);
+        };
+        if !config.destination.exists() {
+            return Ok(None);
+        }
+        let (total, free) = match manager.fs.inodes(&config.destination) {
+            Ok((total, free)) if total > 0 => (total, free),
+            Ok(_) => return Ok(self::no_inodes(manager, config)),
+            Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+                return Ok(self::no_inodes(manager, config));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let used = total.saturating_sub(free);
+        let mark = |percent: u8| (u128::from(total) * u128::from(percent) / 100) as u64;
+        if used <= mark(high) {
+            return Ok(Some(0));
+        }
+        Ok(Some(used - mark(low)))
+    }
+
+    fn no_inodes(manager: &Manager, config: &Config) -> Option<u64> {
+        manager.output.line(format_args!(
+            "\u{1b}[33mWarning\u{1b}[0m no inode counts for {:?}, ignoring its inode marks...",
+            config.destination
+        ));
+        None
+    }
+
+    /// With water marks, how many bytes `config` has to free to get its
+    /// destination's volume back under `low_water`; 0 while the volume isn't
+    /// over `high_water`.
+    fn bytes_to_free(manager: &Manager, config: &Config) -> crate::Result<Option<u64>> {
+        let Some((high, low)) = config.water_marks() else {
+            return Ok(None);
+        };
+        // a missing destination is up to `on_missing_destination`
+        if !config.destination.exists() {
+            return Ok(None);
+        }
+        let (total, available) = manager.fs.space(&config.destination)?;
+        let used = total.saturating_sub(available);
+        let mark = |percent: u8| (u128::from(total) * u128::from(percent) / 100) as u64;
+        if used <= mark(high) {
+            return Ok(Some(0));
+        }
+        Ok(Some(used - mark(low)))
+    }
+
+    /// Whether `config`, last run at `last_run`, is due again. A config
+    /// without an interval always is, as is one never run.
+    pub fn due(config: &Config, last_run: Option<SystemTime>) -> bool {
+        match (config.interval(), last_run) {
+            (Some(interval), Some(last_run)) => match last_run.elapsed() {
+                Ok(elapsed) => elapsed >= interval,
+                // last run in the future, the clock went back
+                Err(_) => false,
+            },
+            _ => true,
+        }
+    }
+
+    /// Run `command` through the shell, its output shown as progress. `Err`
+    /// tells why it didn't succeed.
+    pub fn after(out: &Output, command: &str) -> Result<(), String> {
+        out.line(format_args!("\u{1b}[33mAfter\u{1b}[0m {}...", command));
+        #[cfg(windows)]
+        let output = std::process::Command::new("cmd")
+            .args(["/C", command])
+            .output();
+        #[cfg(not(windows))]
+        let output = std::process::Command::new("sh")
+            .args(["-c", command])
+            .output();
+        let output =
+            output.map_err(|e| format!("after command '{}' couldn't run: {}", command, e))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        for line in stdout.lines().chain(stderr.lines()) {
+            out.line(format_args!("  {}", line));
+        }
+        match output.status.code() {
+            _ if output.status.success() => Ok(()),
+            Some(code) => Err(format!("after command '{}' exited with {}", command, code)),
+            None => Err(format!(
+                "after command '{}' failed, {}",
+                command, output.status
+            )),
+        }
+    }
+
+    /// Per-directory list of names (or globs) to keep within its subtree.
+    const IGNORE_FILE: &str = ".cleanupignore";
+
+    /// With `match_destination` the destination goes as a whole when it
+    /// matches itself and isn't `protected`, otherwise what's in it is
+    /// walked.
+    fn walk_destination(walk: &mut Walk) -> crate::Result<()> {
+        let destination = &walk.config.destination;
+        if walk.config.match_destination && destination.exists() {
+            let decision = self::classify(walk, destination, 0, &HashSet::new(), None);
+            if walk.manager.explain || walk.out.shows(Verbosity::Debug) {
+                walk.out
+                    .line(format_args!("{}", decision.explain(destination)));
+            }
+            if let Decision::Remove(reason) = decision {
+                if !self::protected(walk.manager, destination, Some(walk.config))? {
+                    // the destination itself is never pruned
+                    return self::take(walk, destination, reason).map(|_| ());
+                }
+                walk.out.line(format_args!(
+                    "\u{1b}[33mProtected\u{1b}[0m {:?}, only what it holds goes...",
+                    destination
+                ));
+            }
+        }
+        self::remove_at(walk, destination, 1)
+    }
+
+    fn remove_at(walk: &mut Walk, destination: &Path, depth: usize) -> crate::Result<()> {
+        // patterns apply below the directory holding the file only
+        let inherited = walk.ignores.len();
+        walk.ignores.extend(self::ignores(destination));
+        let result = self::remove_in(walk, destination, depth);
+        walk.ignores.truncate(inherited);
+        result
+    }
+
+    /// `remove_at` for a kept `folder`. With an index, a folder unchanged
+    /// since a walk found nothing in it is left out, and one found clean now
+    /// is added.
+    fn remove_below(walk: &mut Walk, folder: &Path, depth: usize) -> crate::Result<()> {
+        let modified = match walk.index {
+            Some(_) => fs::metadata(folder).and_then(|m| m.modified()).ok(),
+            None => None,
+        };
+        if let (Some(index), Some(modified)) = (walk.index.as_mut(), modified) {
+            if index.unchanged(folder, modified) {
+                walk.out
+                    .verbose(format_args!("\u{1b}[33mUnchanged\u{1b}[0m {:?}...", folder));
+                return Ok(());
+            }
+            index.remove(folder);
+        }
+
+        let found = |walk: &Walk| {
+            let planned = walk.plan.as_ref().map_or(0, Vec::len);
+            (walk.summary.removed, walk.summary.errors.len(), planned)
+        };
+        let before = found(walk);
+        self::remove_at(walk, folder, depth)?;
+        let clean = found(walk) == before && !walk.summary.cancelled && !self::freed(walk);
+        if let (Some(index), Some(modified), true) = (walk.index.as_mut(), modified, clean) {
+            index.insert(folder.to_path_buf(), modified);
+        }
+        Ok(())
+    }
+
+    fn remove_in(walk: &mut Walk, destination: &Path, depth: usize) -> crate::Result<()> {
+        let manager = walk.manager;
+        let config = walk.config;
+        let out = &walk.out.clone();
+
+        if destination.exists() {
+            let children = self::entries(destination, out);
+            let newest = match &walk.rotation {
+                Some(rotation) => self::newest(&children, config, rotation),
+                None => HashSet::new(),
+            };
+            let within = config
+                .dir_quota()
+                .map(|quota| self::within_quota(&children, config, quota, manager));
+
+            // iterate over each child, counting those gone
+            let mut taken = 0;
+            for child in &children {
+                if manager.cancel.is_cancelled() {
+                    walk.summary.cancelled = true;
+                    return Ok(());
+                }
+                if self::freed(walk) {
+                    return Ok(());
+                }
+
+                let decision = self::classify(walk, child, depth, &newest, within.as_ref());
+                if manager.explain || out.shows(Verbosity::Debug) {
+                    out.line(format_args!("{}", decision.explain(child)));
+                }
+
+                // if match, then remove
+                match decision {
+                    Decision::Remove(reason) => {
+                        if self::take(walk, child, reason)? || self::prune(walk, child)? {
+                            taken += 1;
+                        }
+                    }
+                    Decision::Keep(reason) => {
+                        walk.kept += 1;
+                        // a match left out of the sample is left whole
+                        if !matches!(reason, Reason::Unsampled(_)) && self::descend(walk, child) {
+                            let before = (walk.summary.removed, walk.kept);
+                            self::remove_below(walk, child, depth + 1)?;
+                            if manager.rollup && depth == 1 {
+                                out.line(format_args!(
+                                    "\u{1b}[1mScanned\u{1b}[0m {:?}, {} removed, {} kept...",
+                                    child,
+                                    walk.summary.removed - before.0,
+                                    walk.kept - before.1
+                                ));
+                            }
+                            if self::prune(walk, child)? {
+                                taken += 1;
+                            }
+                        }
+                    }
+                    Decision::Skip(_) if walk.plan.is_some() => walk.kept += 1,
+                    Decision::Skip(reason) => {
+                        walk.kept += 1;
+                        manager.emit(Event::Skipped {
+                            path: child,
+                            reason: reason.to_string(),
+                        })
+                    }
+                }
+            }
+            if taken > 0 && taken == children.len() {
+                walk.emptied = Some(destination.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the walk already freed what the water marks (of space and
+    /// of inodes) asked for, all of it.
+    fn freed(walk: &Walk) -> bool {
+        let bytes = walk.until.map(|until| walk.summary.bytes >= until);
+        let inodes = walk.inodes_until.map(|until| walk.inodes >= until);
+        match (bytes, inodes) {
+            (None, None) => false,
+            (bytes, inodes) => bytes.unwrap_or(true) && inodes.unwrap_or(true),
+        }
+    }
+
+    /// Remove `item`, matched for `reason`, or add it to the plan. With
+    /// `contents_only` a folder is emptied instead. Whether the item itself
+    /// goes.
+    fn take(walk: &mut Walk, item: &Path, reason: Reason) -> crate::Result<bool> {
+        if walk.config.contents_only && fs::symlink_metadata(item).is_ok_and(|m| m.is_dir()) {
+            self::empty(walk, item, reason)?;
+            return Ok(false);
+        }
+        self::take_whole(walk, item, reason)?;
+        Ok(true)
+    }
+
+    /// With `prune_emptied`, take `folder` too when the walk just took all
+    /// it held and its name matches. In a real run it has to be empty
+    /// indeed, nothing left over by a failure. Whether it goes.
+    fn prune(walk: &mut Walk, folder: &Path) -> crate::Result<bool> {
+        let emptied = walk.emptied.take().is_some_and(|emptied| emptied == folder);
+        let Some(pattern) = &walk.config.prune_emptied else {
+            return Ok(false);
+        };
+        if !emptied
+            || !self::name(folder)
+                .is_some_and(|name| self::glob(pattern, name, walk.config.unicode()))
+        {
+            return Ok(false);
+        }
+        if walk.plan.is_none()
+            && !walk.summary.dryrun
+            && fs::read_dir(folder).map_or(true, |mut entries| entries.next().is_some())
+        {
+            return Ok(false);
+        }
+        let reason = Reason::Emptied(pattern.clone());
+        if walk.manager.explain || walk.out.shows(Verbosity::Debug) {
+            let decision = Decision::Remove(reason.clone());
+            walk.out.line(format_args!("{}", decision.explain(folder)));
+        }
+        self::take_whole(walk, folder, reason)?;
+        Ok(true)
+    }
+
+    /// Take every entry of `folder` and keep the folder, with the times it
+    /// had before when `preserve_times` asks for it.
+    fn empty(walk: &mut Walk, folder: &Path, reason: Reason) -> crate::Result<()> {
+        let before = match walk.config.preserve_times {
+            true => Some(fs::symlink_metadata(folder)?),
+            false => None,
+        };
+        let mut children = self::entries(folder, &walk.out);
+        let emptied = !children.is_empty();
+        if walk.config.move_aside
+            && walk.plan.is_none()
+            && !walk.summary.dryrun
+            && walk.manager.quarantine.is_none()
+        {
+            children = self::move_aside(walk, folder, children, &reason)?;
+        }
+        for child in children {
+            self::take_whole(walk, &child, reason.clone())?;
+        }
+        if let Some(before) = before
+            && walk.plan.is_none()
+            && !walk.summary.dryrun
+        {
+            self::restore_times(folder, &before)?;
+        }
+        if emptied {
+            walk.emptied = Some(folder.to_path_buf());
+        }
+        Ok(())
+    }
+
+    /// Move `children` of `folder` into a new folder next to it and remove
+    /// that folder on a thread of its own, along with any an earlier run
+    /// left behind. What isn't moved, e.g. across devices, is handed back
+    /// to be removed in place.
+    fn move_aside(
+        walk: &mut Walk,
+        folder: &Path,
+        children: Vec<PathBuf>,
+        reason: &Reason,
+    ) -> crate::Result<Vec<PathBuf>> {
+        let manager = walk.manager;
+        for leftover in self::leftovers(folder, &walk.out) {
+            manager.output.verbose(format_args!(
+                "\u{1b}[33mLeftover\u{1b}[0m {:?}, removing...",
+                leftover
+            ));
+            let fs = manager.fs.clone();
+            let removing = leftover.clone();
+            walk.aside.push(Aside {
+                folder: leftover,
+                moved: Vec::new(),
+                thread: std::thread::spawn(move || fs.remove_dir_all(&removing)),
+            });
+        }
+        let Some(aside) = self::aside(folder) else {
+            return Ok(children);
+        };
+        let mut left = Vec::new();
+        let mut moved = Vec::new();
+        for child in children {
+            // removed in place, to be skipped as usual
+            if manager.done.contains(&child) || manager.skip_in_use && self::in_use(&child) {
+                left.push(child);
+                continue;
+            }
+            let size = match reason.frees_space() {
+                true => self::size(&child, manager.allocated_size),
+                false => 0,
+            };
+            let inodes = match walk.inodes_until {
+                Some(_) => 1 + self::count(&child, true, None),
+                None => 0,
+            };
+            let to = aside.join(child.file_name().unwrap_or_default());
+            if manager.fs.rename(&child, &to).is_err() {
+                left.push(child);
+                continue;
+            }
+            walk.inodes += inodes;
+            manager.output.line(format_args!(
+                "\u{1b}[31mMoved aside\u{1b}[0m {:?}...",
+                child
+            ));
+            moved.push((child, size));
+        }
+
+        let fs = manager.fs.clone();
+        let removing = aside.clone();
+        walk.aside.push(Aside {
+            folder: aside,
+            moved,
+            thread: std::thread::spawn(move || fs.remove_dir_all(&removing)),
+        });
+        Ok(left)
+    }
+
+    /// Folders `move_aside` made next to `folder` in an earlier run, which
+    /// was stopped before it removed them.
+    fn leftovers(folder: &Path, out: &Output) -> Vec<PathBuf> {
+        let (Some(parent), Some(name)) = (folder.parent(), folder.file_name()) else {
+            return Vec::new();
+        };
+        let prefix = format!(".{}.aside-", name.to_string_lossy());
+        self::entries(parent, out)
+            .into_iter()
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str()?.strip_prefix(&prefix))
+                    .is_some_and(|n| n.parse::<u8>().is_ok())
+                    && path.is_dir()
+            })
+            .collect()
+    }
+
+    /// A new, empty folder next to `folder` for `move_aside`.
+    fn aside(folder: &Path) -> Option<PathBuf> {
+        let parent = folder.parent()?;
+        let name = folder.file_name()?.to_string_lossy();
+        for n in 0..16 {
+            let aside = parent.join(format!(".{}.aside-{}", name, n));
+            match fs::create_dir(&aside) {
+                Ok(()) => return Some(aside),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(_) => return None,
+            }
+        }
+        None
+    }
+
+    /// Wait for the folders of `move_aside` to be gone, then record what
+    /// was moved into each as removed; a failure is recorded with the
+    /// folder left behind instead.
+    fn clear_aside(walk: &mut Walk) -> crate::Result<()> {
+        let manager = walk.manager;
+        for aside in std::mem::take(&mut walk.aside) {
+            let result = aside
+                .thread
+                .join()
+                .unwrap_or_else(|_| Err(std::io::Error::other("removal panicked")));
+            if let Err(e) = result {
+                walk.out.line(format_args!("Error: {}", e));
+                manager.emit(Event::Failed {
+                    path: &aside.folder,
+                    error: e.to_string(),
+                });
+                walk.summary.fail(&aside.folder, e.to_string());
+                continue;
+            }
+            for (item, size) in &aside.moved {
+                self::gone(manager, walk.summary, item, *size, audit::Action::Remove)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the access and modification times of `folder` back to `before`.
+    fn restore_times(folder: &Path, before: &fs::Metadata) -> std::io::Result<()> {
+        let times = fs::FileTimes::new()
+            .set_accessed(before.accessed()?)
+            .set_modified(before.modified()?);
+        // a folder only opens with backup semantics on Windows
+        #[cfg(windows)]
+        let file = {
+            use std::os::windows::fs::OpenOptionsExt;
+            use windows_sys::Win32::Storage::FileSystem::FILE_FLAG_BACKUP_SEMANTICS;
+
+            fs::OpenOptions::new()
+                .write(true)
+                .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+                .open(folder)?
+        };
+        #[cfg(not(windows))]
+        let file = fs::File::open(folder)?;
+        file.set_times(times)
+    }
+
+    fn take_whole(walk: &mut Walk, item: &Path, reason: Reason) -> crate::Result<()> {
+        let manager = walk.manager;
+        if walk.inodes_until.is_some() {
+            walk.inodes += 1 + self::count(item, true, None);
+        }
+        // size has to be taken before the item is gone
+        let size = if reason.frees_space() {
+            self::size(item, manager.allocated_size)
+        } else {
+            0
+        };
+        let dryrun = walk.summary.dryrun;
+        if walk.plan.is_none() && !dryrun {
+            return self::remove_one(manager, item, size, dryrun, walk.device, walk.summary);
+        }
+
+        let action = PlannedAction {
+            destination: walk.config.destination.clone(),
+            path: item.to_path_buf(),
+            size,
+            reason: reason.to_string(),
+            entries: self::count(item, true, None),
+            uncompressed: match manager.inspect_archives {
+                true => archive::uncompressed_size(item),
+                false => None,
+            },
+        };
+        match walk.plan.as_mut() {
+            Some(plan) => {
+                plan.push(action);
+                // for water marks to stop the plan where the run would
+                walk.summary.bytes += size;
+            }
+            None => {
+                let removed = walk.summary.removed;
+                self::remove_one(manager, item, size, dryrun, walk.device, walk.summary)?;
+                if manager.collapse && walk.summary.removed > removed {
+                    walk.summary.planned.push(action.clone());
+                }
+                // a dry-run is where a rule gets checked, flag what deserves a look
+                let home = self::home();
+                if action.high_risk(home.as_deref()) {
+                    manager.output.line(format_args!(
+                        "\u{1b}[31m⚠ high-risk\u{1b}[0m {:?}, {} bytes in {} entries",
+                        item, size, action.entries
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Decision for `child` of the walk: the run's own control files and
+    /// excludes first, then `.cleanupignore` patterns, then the config.
+    fn classify(
+        walk: &mut Walk,
+        child: &Path,
+        depth: usize,
+        newest: &HashSet<PathBuf>,
+        within: Option<&HashSet<PathBuf>>,
+    ) -> Decision {
+        let config = walk.config;
+        let out = &walk.out;
+        let exclude = config.exclude.as_deref().unwrap_or_default();
+        let name = self::name(child);
+
+        if self::control(walk.manager, config, child) {
+            out.verbose(format_args!("\u{1b}[33mProtect\u{1b}[0m {:?}...", child));
+            return Decision::Skip(Reason::Control);
+        }
+        if let Some(index) = self::excluded(name, exclude, config.unicode()) {
+            out.verbose(format_args!("\u{1b}[33mExclude\u{1b}[0m {:?}...", child));
+            return Decision::Skip(Reason::Excluded(exclude[index].clone()));
+        }
+        if let Some(pattern) = walk
+            .ignores
+            .iter()
+            .find(|p| name.is_some_and(|name| self::glob(p, name, config.unicode())))
+        {
+            out.verbose(format_args!("\u{1b}[33mIgnore\u{1b}[0m {:?}...", child));
+            return Decision::Skip(Reason::Ignored(pattern.clone()));
+        }
+        if self::is_link(child) && config.symlink_policy() == SymlinkPolicy::Skip {
+            return Decision::Skip(Reason::Symlink);
+        }
+        // a link is on the destination's device, what it leads to is up to
+        // `descend`
+        if let Some(device) = walk.device
+            && !self::is_link(child)
+            && walk.manager.fs.device(child) != Some(device)
+        {
+            out.verbose(format_args!(
+                "\u{1b}[33mOther filesystem\u{1b}[0m {:?}...",
+                child
+            ));
+            return Decision::Skip(Reason::OtherFilesystem);
+        }
+
+        walk.summary.reach(depth);
+        let mut decision = self::judge(child, config);
+        if let (Decision::Remove(_), Some(rotation)) = (&decision, &walk.rotation) {
+            decision = self::rotate(child, rotation, newest);
+        }
+        if matches!(decision, Decision::Remove(_)) && config.dedup_hardlinks {
+            decision = self::dedup(walk, child);
+        }
+        if let (Decision::Remove(_), Some(duplicates)) = (&decision, &walk.duplicates) {
+            decision = match duplicates.get(child) {
+                Some(kept) => Decision::Remove(Reason::Duplicate(kept.clone())),
+                None => Decision::Keep(Reason::Unique),
+            };
+        }
+        if let (Decision::Remove(_), Some(start)) = (&decision, walk.session)
+            && let Some(reason) = self::session_check(child, start, walk.manager.session_user)
+        {
+            decision = Decision::Keep(reason);
+        }
+        if let (Decision::Remove(_), Some(within)) = (&decision, within)
+            && within.contains(child)
+        {
+            decision = Decision::Keep(Reason::WithinQuota(
+                config.dir_quota.clone().unwrap_or_default(),
+            ));
+        }
+        if let (Decision::Remove(_), Some(percent)) = (&decision, walk.manager.sample) {
+            decision = self::sample(walk, child, percent, decision);
+        }
+        decision
+    }
+
+    /// `decision` when `path` is among the `percent` of the matches the seed
+    /// picks, relative to the destination so the same tree anywhere picks
+    /// the same items; kept otherwise.
+    fn sample(walk: &mut Walk, path: &Path, percent: f64, decision: Decision) -> Decision {
+        use sha2::{Digest, Sha256};
+
+        let relative = path.strip_prefix(&walk.config.destination).unwrap_or(path);
+        let mut hasher = Sha256::new();
+        hasher.update(walk.manager.seed.to_le_bytes());
+        hasher.update(relative.as_os_str().as_encoded_bytes());
+        let digest = hasher.finalize();
+        let draw = u64::from_le_bytes(digest[..8].try_into().unwrap_or_default());
+        let picked = (draw as f64 / u64::MAX as f64) * 100.0 < percent;
+
+        let sample = walk.summary.sample.get_or_insert(SampleSummary {
+            seed: walk.manager.seed,
+            ..Default::default()
+        });
+        sample.matched += 1;
+        if !picked {
+            return Decision::Keep(Reason::Unsampled(percent));
+        }
+        sample.sampled += 1;
+        decision
+    }
+
+    /// `Some(reason)` when the item isn't `user`'s (the running user's by
+    /// default), or its status last changed before `start`, the start of
+    /// their login session.
+    #[cfg(unix)]
+    fn session_check(path: &Path, start: SystemTime, user: Option<u32>) -> Option<Reason> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = fs::symlink_metadata(path).ok()?;
+        let me = user.unwrap_or_else(|| nix::unistd::Uid::effective().as_raw());
+        if metadata.uid() != me {
+            return Some(Reason::Owner {
+                uid: metadata.uid(),
+                owner: me.to_string(),
+            });
+        }
+        let seconds = u64::try_from(metadata.ctime()).unwrap_or_default();
+        let ctime = SystemTime::UNIX_EPOCH + Duration::new(seconds, metadata.ctime_nsec() as u32);
+        match ctime < start {
+            true => Some(Reason::BeforeSession),
+            false => None,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn session_check(_: &Path, _: SystemTime, _: Option<u32>) -> Option<Reason> {
+        None
+    }
+
+    /// When the login session of the process began: the start of its
+    /// session leader, from `/proc`. Linux only.
+    #[cfg(target_os = "linux")]
+    fn session_start() -> Option<SystemTime> {
+        // SAFETY: getsid and sysconf only read the process's own state
+        let (session, ticks) = unsafe { (libc::getsid(0), libc::sysconf(libc::_SC_CLK_TCK)) };
+        let stat = fs::read_to_string(format!("/proc/{}/stat", session)).ok()?;
+        // the command name in parentheses may hold spaces, the start time
+        // is the 22nd field
+        let started: u64 = stat
+            .rsplit_once(')')?
+            .1
+            .split_whitespace()
+            .nth(19)?
+            .parse()
+            .ok()?;
+        let boot: u64 = fs::read_to_string("/proc/stat")
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("btime "))?
+            .trim()
+            .parse()
+            .ok()?;
+        let ticks = u64::try_from(ticks).ok().filter(|ticks| *ticks > 0)?;
+        let since_boot = Duration::from_millis(started * 1000 / ticks);
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(boot) + since_boot)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn session_start() -> Option<SystemTime> {
+        None
+    }
+
+    fn is_link(path: &Path) -> bool {
+        fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_symlink())
+    }
+
+    /// Whether to walk into kept `path`: a folder, or under
+    /// `SymlinkPolicy::FollowAndDelete` a link to one not walked yet, on the
+    /// destination's device with `one_filesystem`.
+    fn descend(walk: &mut Walk, path: &Path) -> bool {
+        if !self::is_link(path) {
+            return path.is_dir();
+        }
+        if walk.config.symlink_policy() != SymlinkPolicy::FollowAndDelete || !path.is_dir() {
+            return false;
+        }
+        if let Some(device) = walk.device
+            && walk.manager.fs.device(path) != Some(device)
+        {
+            return false;
+        }
+        match fs::canonicalize(path) {
+            Ok(target) => walk.followed.insert(target),
+            Err(_) => false,
+        }
+    }
+
+    /// `key` the rotation regex captures from the name of `path`.
+    fn rotation_key<'n>(path: &'n Path, rotation: &Regex) -> Option<regex::Match<'n>> {
+        rotation.captures(self::name(path)?)?.name("key")
+    }
+
+    /// Among the `children` the config matches, the `keep_newest` of every
+    /// series by their rotation key. A series is the names only differing
+    /// by their key, e.g. `app-2024-01-01.log` and `app-2024-02-01.log`.
+    fn newest(children: &[PathBuf], config: &Config, rotation: &Regex) -> HashSet<PathBuf> {
+        let mut series: HashMap<String, Vec<(&str, &PathBuf)>> = HashMap::new();
+        for child in children {
+            if let Some(key) = self::rotation_key(child, rotation)
+                && matches!(self::judge(child, config), Decision::Remove(_))
+            {
+                let name = self::name(child).unwrap_or_default();
+                let series_name = format!("{}\0{}", &name[..key.start()], &name[key.end()..]);
+                series
+                    .entry(series_name)
+                    .or_default()
+                    .push((key.as_str(), child));
+            }
+        }
+
+        let keep = config.keep_newest.unwrap_or(1);
+        let mut newest = HashSet::new();
+        for mut files in series.into_values() {
+            files.sort_by(|a, b| self::compare_keys(b.0, a.0));
+            newest.extend(files.into_iter().take(keep).map(|(_, child)| child.clone()));
+        }
+        newest
+    }
+
+    /// Among the `children` the config matches, the newest (by its
+    /// `time_basis`) which fit in `quota` bytes together; the older ones
+    /// are to go. An item without the timestamp counts as the oldest.
+    fn within_quota(
+        children: &[PathBuf],
+        config: &Config,
+        quota: u64,
+        manager: &Manager,
+    ) -> HashSet<PathBuf> {
+        let mut matches: Vec<(Option<SystemTime>, &PathBuf)> = children
+            .iter()
+            .filter(|child| matches!(self::judge(child, config), Decision::Remove(_)))
+            .map(|child| (self::time(child, &config.time_basis), child))
+            .collect();
+        matches.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+
+        let mut total = 0u64;
+        matches
+            .into_iter()
+            .map_while(|(_, child)| {
+                total = total.saturating_add(self::size(child, manager.allocated_size));
+                (total <= quota).then(|| child.clone())
+            })
+            .collect()
+    }
+
+    /// Numbers compare as numbers, anything else (e.g. ISO dates) as text,
+    /// and every number comes before any text, for a series mixing both to
+    /// sort the same way whatever order it's found in.
+    pub fn compare_keys(a: &str, b: &str) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+
+        match (a.parse::<u64>(), b.parse::<u64>()) {
+            // `7` and `007` too, still in some order
+            (Ok(x), Ok(y)) => x.cmp(&y).then_with(|| a.cmp(b)),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => a.cmp(b),
+        }
+    }
+
+    fn rotate(path: &Path, rotation: &Regex, newest: &HashSet<PathBuf>) -> Decision {
+        match self::rotation_key(path, rotation) {
+            None => Decision::Keep(Reason::NotRotated),
+            Some(key) if newest.contains(path) => {
+                Decision::Keep(Reason::Newest(key.as_str().into()))
+            }
+            Some(key) => Decision::Remove(Reason::Rotated(key.as_str().into())),
+        }
+    }
+
+    /// Whether `path` is (or holds) something steering the run: the config
+    /// file in use, the config directory or anything in it, the quarantine
+    /// folder, a `.cleanupignore` or the `touch_after` file of `config`. No
+    /// rule removes those.
+    fn control(manager: &Manager, config: &Config, path: &Path) -> bool {
+        if self::name(path) == Some(self::IGNORE_FILE) {
+            return true;
+        }
+        if let Some(name) = &config.touch_after
+            && path == config.destination.join(name)
+        {
+            return true;
+        }
+        let Ok(path) = path::absolute(path) else {
+            return false;
+        };
+        [
+            &manager.config_file,
+            &manager.config_dir,
+            &manager.quarantine,
+        ]
+        .into_iter()
+        .flatten()
+        .any(|control| control.starts_with(&path))
+            || manager
+                .config_dir
+                .as_ref()
+                .is_some_and(|dir| path.starts_with(dir))
+    }
+
+    /// Among matched files sharing an inode keep the first one seen and
+    /// remove the other links, which frees no space by itself.
+    #[cfg(unix)]
+    fn dedup(walk: &mut Walk, path: &Path) -> Decision {
+        use std::os::unix::fs::MetadataExt;
+
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_file() && metadata.nlink() > 1 => {
+                if walk.links.insert((metadata.dev(), metadata.ino())) {
+                    Decision::Keep(Reason::FirstLink)
+                } else {
+                    Decision::Remove(Reason::Linked)
+                }
+            }
+            _ => Decision::Keep(Reason::Unlinked),
+        }
+    }
+
+    /// Hard links can't be told apart here, so nothing is removed.
+    #[cfg(not(unix))]
+    fn dedup(_: &mut Walk, _: &Path) -> Decision {
+        Decision::Keep(Reason::Unlinked)
+    }
+
+    /// Every file `config` matches with the same content as another, with
+    /// the copy kept instead: the oldest or newest by modification time.
+    /// Only files of the same size get read at all.
+    fn duplicates(
+        manager: &Manager,
+        config: &Config,
+        keep: &KeepCopy,
+    ) -> crate::Result<HashMap<PathBuf, PathBuf>> {
+        let matching = Config {
+            dedup_content: None,
+            ..config.clone()
+        };
+        let mut sizes: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        for action in self::plan(manager, &matching, ToFree::default())? {
+            if let Ok(metadata) = fs::symlink_metadata(&action.path)
+                && metadata.is_file()
+            {
+                sizes.entry(metadata.len()).or_default().push(action.path);
+            }
+        }
+
+        let mut duplicates = HashMap::new();
+        for files in sizes.into_values().filter(|files| files.len() > 1) {
+            let mut contents: HashMap<_, Vec<(SystemTime, PathBuf)>> = HashMap::new();
+            for file in files {
+                let modified = fs::metadata(&file).and_then(|m| m.modified());
+                if let (Ok(hash), Ok(modified)) = (self::hash(&file), modified) {
+                    contents.entry(hash).or_default().push((modified, file));
+                }
+            }
+            for mut copies in contents.into_values().filter(|copies| copies.len() > 1) {
+                copies.sort();
+                if *keep == KeepCopy::Newest {
+                    copies.reverse();
+                }
+                let (_, kept) = copies.remove(0);
+                for (_, copy) in copies {
+                    duplicates.insert(copy, kept.clone());
+                }
+            }
+        }
+        Ok(duplicates)
+    }
+
+    /// SHA-256 of the content of `file`, read in chunks.
+    fn hash(file: &Path) -> std::io::Result<Vec<u8>> {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut fs::File::open(file)?, &mut hasher)?;
+        Ok(hasher.finalize().to_vec())
+    }
+
+    /// Remove a single item (or only report it in dry-run) and account for it
+    /// in `summary`. A failure is recorded, and under `ErrorPolicy::FailFast`
+    /// returned as well, as is any once the run failed more than
+    /// `max_errors` times. With `device`, nothing on another device goes.
+    pub fn remove_one(
+        manager: &Manager,
+        path: &Path,
+        size: u64,
+        dryrun: bool,
+        device: Option<u64>,
+        summary: &mut ExecutionSummary,
+    ) -> crate::Result<()> {
+        let out = &manager.output;
+        // configs running in parallel stop too
+        self::too_many_errors(manager, summary)?;
+
+        if manager.done.contains(path) {
+            out.verbose(format_args!("\u{1b}[33mDone\u{1b}[0m {:?}...", path));
+            manager.emit(Event::Skipped {
+                path,
+                reason: String::from("done by the interrupted run"),
+            });
+            return Ok(());
+        }
+        if manager.skip_in_use && self::in_use(path) {
+            self::skip_in_use(manager, path);
+            return Ok(());
+        }
+
+        let removing = format_args!("\u{1b}[91mRemoving\u{1b}[0m {:?}...", path);
+        match dryrun && manager.collapse {
+            // summed up by `conclude` instead
+            true => out.verbose(removing),
+            false => out.line(removing),
+        }
+        let removed = Event::Removed { path, size, dryrun };
+        if dryrun {
+            manager.emit(removed);
+            summary.record(path, size);
+            manager.listener.item(path, size);
+            return self::audit(manager, summary, path, audit::Action::WouldRemove, Ok(()));
+        }
+        let action = match manager.quarantine {
+            Some(_) => audit::Action::Quarantine,
+            None => audit::Action::Remove,
+        };
+
+        let mut held = None;
+        let result = match &manager.quarantine {
+            Some(quarantine) => {
+                let policy = manager.on_permission_denied.clone();
+                let (quarantine, item, out) = (quarantine.clone(), path.to_path_buf(), out.clone());
+                self::timed(manager, move |fs| {
+                    self::quarantine(fs, &quarantine, &item, &policy, &out)
+                })
+                .map(|to| {
+                    held = Some(to);
+                    Left::new()
+                })
+            }
+            None => self::remove_timed(manager, path, device),
+        };
+        let left = match result {
+            Ok(left) if left.is_empty() => {
+                match held {
+                    Some((held, moved)) => {
+                        out.line(format_args!(
+                            "\u{1b}[31mQuarantined\u{1b}[0m {:?} in {:?}, {}...",
+                            path,
+                            held,
+                            moved.as_str()
+                        ));
+                        summary.quarantined.push((path.to_path_buf(), held));
+                    }
+                    None => out.line(format_args!("\u{1b}[31mRemoved\u{1b}[0m {:?}...", path)),
+                }
+                return self::gone(manager, summary, path, size, action);
+            }
+            Ok(left) => left,
+            // nothing left to do, and nothing freed by this run either
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                out.line(format_args!("\u{1b}[33mGone\u{1b}[0m {:?}...", path));
+                manager.emit(Event::Skipped {
+                    path,
+                    reason: String::from("already gone"),
+                });
+                return Ok(());
+            }
+            Err(e) => vec![(path.to_path_buf(), e)],
+        };
+
+        // what's only busy is left for a later run
+        let (busy, left): (Left, Left) = left
+            .into_iter()
+            .partition(|(_, e)| manager.skip_in_use && self::busy(e));
+        for (item, _) in &busy {
+            self::skip_in_use(manager, item);
+        }
+        if left.is_empty() {
+            return Ok(());
+        }
+
+        for (item, e) in &left {
+            out.line(format_args!("Error: {}", e));
+            manager.emit(Event::Failed {
+                path: item,
+                error: e.to_string(),
+            });
+            summary.fail(item, e.to_string());
+            self::audit(manager, summary, item, action, Err(e.to_string()))?;
+        }
+        manager.failures.add(left.len());
+        self::too_many_errors(manager, summary)?;
+        match manager.error_policy {
+            ErrorPolicy::FailFast => Err(AppError::new(
+                AppErrorKind::Functionality,
+                format!("couldn't remove {:?}: {}", left[0].0, left[0].1),
+            )),
+            ErrorPolicy::KeepGoing => Ok(()),
+        }
+    }
+
+    /// Account for `path`, of `size`, as gone for good from where it was.
+    fn gone(
+        manager: &Manager,
+        summary: &mut ExecutionSummary,
+        path: &Path,
+        size: u64,
+        action: audit::Action,
+    ) -> crate::Result<()> {
+        manager.emit(Event::Removed {
+            path,
+            size,
+            dryrun: false,
+        });
+        summary.record(path, size);
+        manager.listener.item(path, size);
+        if let Some(progress) = &manager.progress
+            && let Err(e) = manifest::append(progress, path, size)
+        {
+            self::unrecorded(manager, progress, e);
+        }
+        self::audit(manager, summary, path, action, Ok(()))
+    }
+
+    /// Warn that `progress` couldn't be written to, which only matters to
+    /// a later `--resume`; the run itself goes on.
+    fn unrecorded(manager: &Manager, progress: &Path, e: AppError) {
+        manager.output.line(format_args!(
+            "\u{1b}[33mWarning\u{1b}[0m couldn't record progress in {:?}: {}",
+            progress, e
+        ));
+    }
+
+    /// An error with the failures in `summary` once the run failed more
+    /// than `max_errors` times.
+    fn too_many_errors(manager: &Manager, summary: &ExecutionSummary) -> crate::Result<()> {
+        let max = match manager.max_errors {
+            Some(max) if manager.failures.count() > max => max,
+            _ => return Ok(()),
+        };
+        let mut message = format!("more than {} item(s) couldn't be removed, stopped", max);
+        for (path, error) in &summary.errors {
+            message.push_str(&format!("\n  {:?}: {}", path, error));
+        }
+        Err(AppError::new(AppErrorKind::Functionality, message))
+    }
+
+    /// Add what was done to `path` to the audit log, with `--audit-log` only.
+    fn audit(
+        manager: &Manager,
+        summary: &ExecutionSummary,
+        path: &Path,
+        action: audit::Action,
+        result: Result<(), String>,
+    ) -> crate::Result<()> {
+        match &manager.audit_log {
+            Some(log) => {
+                let hash = summary.config_hash.as_deref();
+                audit::append(log, &audit::Record::new(hash, path, action, result))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn skip_in_use(manager: &Manager, path: &Path) {
+        manager
+            .output
+            .line(format_args!("\u{1b}[33mIn use\u{1b}[0m {:?}...", path));
+        manager.emit(Event::Skipped {
+            path,
+            reason: String::from("in use by another process"),
+        });
+    }
+
+    /// Whether another process has the file at `path` open, found by trying
+    /// to open it exclusively.
+    #[cfg(windows)]
+    fn in_use(path: &Path) -> bool {
+        use std::os::windows::fs::OpenOptionsExt;
+
+        if !path.is_file() {
+            return false;
+        }
+        match fs::OpenOptions::new().read(true).share_mode(0).open(path) {
+            Ok(_) => false,
+            Err(e) => self::busy(&e),
+        }
+    }
+
+    // an open file can be unlinked, only removing it tells whether it's busy
+    #[cfg(not(windows))]
+    fn in_use(_: &Path) -> bool {
+        false
+    }
+
+    /// Whether `e` says the item is in use by another process.
+    fn busy(e: &std::io::Error) -> bool {
+        // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+        #[cfg(windows)]
+        if matches!(e.raw_os_error(), Some(32 | 33)) {
+            return true;
+        }
+        matches!(
+            e.kind(),
+            std::io::ErrorKind::ResourceBusy | std::io::ErrorKind::ExecutableFileBusy
+        )
+    }
+
+    /// How an item got into quarantine.
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub enum Moved {
+        /// hard-linked there, then unlinked: a file on the same device.
+        Linked,
+        /// a folder on the same device, or a file which can't be linked.
+        Renamed,
+        /// copied there, then removed: another device.
+        Copied,
+    }
+
+    impl Moved {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                Self::Linked => "hard-linked",
+                Self::Renamed => "renamed",
+                Self::Copied => "copied",
+            }
+        }
+    }
+
+    /// Move `path` into `quarantine`, under the path it had from its root,
+    /// with a numbered name when that's taken already. Returns where it went.
+    fn quarantine(
+        fs: &dyn FileSystem,
+        quarantine: &Path,
+        path: &Path,
+        policy: &PermissionPolicy,
+        out: &Output,
+    ) -> std::io::Result<(PathBuf, Moved)> {
+        let metadata = fs::symlink_metadata(path)?;
+        if metadata.is_dir() && self::holds_cwd(path) {
+            return Err(std::io::Error::other(format!(
+                "{:?} is the current directory or holds it",
+                path
+            )));
+        }
+
+        let relative: PathBuf = path
+            .components()
+            .filter(|c| matches!(c, path::Component::Normal(_)))
+            .collect();
+        let mut held = quarantine.join(&relative);
+        let mut count = 0;
+        while fs::symlink_metadata(&held).is_ok() {
+            count += 1;
+            held = quarantine.join(format!("{}.{}", relative.display(), count));
+        }
+        if let Some(parent) = held.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // under `PermissionPolicy::Fix` a refusal is retried as for a removal
+        let moved = match self::hold(fs, path, &held, metadata.is_dir(), policy, out) {
+            Err(e)
+                if e.kind() == std::io::ErrorKind::PermissionDenied
+                    && *policy == PermissionPolicy::Fix =>
+            {
+                path.parent().map(self::writable);
+                self::writable(path);
+                self::hold(fs, path, &held, metadata.is_dir(), policy, out)
+            }
+            result => result,
+        }?;
+        Ok((held, moved))
+    }
+
+    /// Put `path` at `held` without copying anything when both are on the
+    /// same device: a file is hard-linked then unlinked, a folder renamed.
+    /// Across devices the item is copied, then removed.
+    pub fn hold(
+        fs: &dyn FileSystem,
+        path: &Path,
+        held: &Path,
+        folder: bool,
+        policy: &PermissionPolicy,
+        out: &Output,
+    ) -> std::io::Result<Moved> {
+        use std::io::ErrorKind::CrossesDevices;
+
+        let moved = match folder {
+            true => match fs.rename(path, held) {
+                Err(e) if e.kind() == CrossesDevices => Moved::Copied,
+                result => return result.map(|()| Moved::Renamed),
+            },
+            false => match fs.hard_link(path, held) {
+                Ok(()) => Moved::Linked,
+                Err(e) if e.kind() == CrossesDevices => Moved::Copied,
+                // e.g. a filesystem without hard links
+                Err(_) => return fs.rename(path, held).map(|()| Moved::Renamed),
+            },
+        };
+
+        if moved == Moved::Copied
+            && let Err(e) = self::copy_tree(fs, path, held)
+        {
+            let _ = self::remove_item(fs, held, &PermissionPolicy::Abort, None, out);
+            return Err(e);
+        }
+        let removed = match folder {
+            true => self::remove_item(fs, path, policy, None, out)
+                .and_then(|left| left.into_iter().next().map_or(Ok(()), |(_, e)| Err(e))),
+            false => fs.remove_file(path),
+        };
+        match removed {
+            Ok(()) => Ok(moved),
+            // what's left of a folder is only safe in both places
+            Err(e) if folder => Err(e),
+            Err(e) => {
+                let _ = fs.remove_file(held);
+                Err(e)
+            }
+        }
+    }
+
+    /// Copy `from`, with all it holds for a folder, to `to`; symlinks are
+    /// copied as links on unix.
+    fn copy_tree(fs: &dyn FileSystem, from: &Path, to: &Path) -> std::io::Result<()> {
+        let metadata = fs::symlink_metadata(from)?;
+        if metadata.is_dir() {
+            fs::create_dir(to)?;
+            for entry in fs::read_dir(from)? {
+                let entry = entry?;
+                self::copy_tree(fs, &entry.path(), &to.join(entry.file_name()))?;
+            }
+            fs::set_permissions(to, metadata.permissions())
+        } else {
+            #[cfg(unix)]
+            if metadata.is_symlink() {
+                return std::os::unix::fs::symlink(fs::read_link(from)?, to);
+            }
+            fs.copy(from, to).map(|_| ())
+        }
+    }
+
+    /// Write the time, in seconds since the Unix epoch, to the file at
+    /// `path`, which also brings its modification time up to date.
+    pub fn touch(path: &Path) -> std::io::Result<()> {
+        let seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        fs::write(path, format!("{}\n", seconds))
+    }
+
+    /// Whether the destination of `config` holds the run's sentinel file,
+    /// which leaves it alone.
+    pub fn paused(manager: &Manager, config: &Config) -> bool {
+        !manager.sentinel.is_empty() && config.destination.join(&manager.sentinel).exists()
+    }
+
+    /// The user's home directory, if known.
+    pub fn home() -> Option<PathBuf> {
+        std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(PathBuf::from)
+    }
+
+    /// Paths never removed whatever asks for it: a filesystem root, the home
+    /// directory and the destination of any config but `own`, the one
+    /// matching its own destination.
+    pub fn protected(manager: &Manager, path: &Path, own: Option<&Config>) -> crate::Result<bool> {
+        let path = path::absolute(path)?;
+        if path.parent().is_none() {
+            return Ok(true);
+        }
+
+        let mut guarded = self::home().into_iter().collect::<Vec<_>>();
+        guarded.extend(
+            manager
+                .configs
+                .iter()
+                .filter(|c| !own.is_some_and(|own| std::ptr::eq(*c, own)))
+                .map(|c| c.destination.clone()),
+        );
+        for guarded in guarded {
+            if path::absolute(guarded)? == path {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// `remove_item` bounded by the run's `item_timeout`, if any.
+    fn remove_timed(manager: &Manager, path: &Path, device: Option<u64>) -> std::io::Result<Left> {
+        let policy = manager.on_permission_denied.clone();
+        let (item, out) = (path.to_path_buf(), manager.output.clone());
+        self::timed(manager, move |fs| {
+            self::remove_item(fs, item, &policy, device, &out)
+        })
+    }
+
+    /// Run `work` on the run's filesystem bounded by its `item_timeout`, if
+    /// any. It runs on a worker thread which is abandoned once the time is
+    /// up, so a hung mount doesn't hold up the rest of the run.
+    fn timed<T, F>(manager: &Manager, work: F) -> std::io::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn FileSystem) -> std::io::Result<T> + Send + 'static,
+    {
+        use std::{sync::mpsc, thread};
+
+        let timeout = match manager.item_timeout {
+            Some(timeout) => timeout,
+            None => return work(&*manager.fs),
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let fs = manager.fs.clone();
+        thread::spawn(move || {
+            // nobody listens anymore after a timeout
+            let _ = sender.send(work(&*fs));
+        });
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("timed out after {}s", timeout.as_secs_f64()),
+            )),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(std::io::Error::other("removal stopped unexpectedly"))
+            }
+        }
+    }
+
+    /// Total size in bytes of a file, or of every file below a directory,
+    /// as allocated on disk when `allocated`. Symlinks are counted as
+    /// themselves and never followed.
+    pub fn size<P: AsRef<Path>>(path: P, allocated: bool) -> u64 {
+        let path = path.as_ref();
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() => match fs::read_dir(path) {
+                Ok(entries) => entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| self::size(entry.path(), allocated))
+                    .sum(),
+                Err(_) => 0,
+            },
+            Ok(metadata) if allocated => self::allocated(path, &metadata),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        }
+    }
+
+    /// Number of files and folders in `path` (and below, when
+    /// `recursive`), 0 for anything else than a folder. With a `limit`,
+    /// counting stops there.
+    pub fn count(path: &Path, recursive: bool, limit: Option<u64>) -> u64 {
+        if !fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir()) {
+            return 0;
+        }
+        let limit = limit.unwrap_or(u64::MAX);
+        let mut count = 0;
+        let mut folders = vec![path.to_path_buf()];
+        while let Some(folder) = folders.pop() {
+            let Ok(entries) = fs::read_dir(&folder) else {
+                continue;
+            };
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                if count >= limit {
+                    return count;
+                }
+                count += 1;
+                if recursive && entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    folders.push(entry.path());
+                }
+            }
+        }
+        count
+    }
+
+    /// Space the blocks of a file take, less than its length when sparse.
+    #[cfg(unix)]
+    fn allocated(_: &Path, metadata: &fs::Metadata) -> u64 {
+        use std::os::unix::fs::MetadataExt;
+
+        // `st_blocks` counts 512-byte units whatever the block size
+        metadata.blocks() * 512
+    }
+
+    /// Space a file takes on disk, less than its length when sparse or
+    /// compressed.
+    #[cfg(windows)]
+    fn allocated(path: &Path, metadata: &fs::Metadata) -> u64 {
+        use std::os::windows::ffi::OsStrExt;
+        use windows_sys::Win32::Storage::FileSystem::{GetCompressedFileSizeW, INVALID_FILE_SIZE};
+
+        let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut high = 0u32;
+        // SAFETY: `wide` is NUL-terminated and `high` outlives the call.
+        let low = unsafe { GetCompressedFileSizeW(wide.as_ptr(), &mut high) };
+        if low == INVALID_FILE_SIZE && std::io::Error::last_os_error().raw_os_error() != Some(0) {
+            return metadata.len();
+        }
+        ((high as u64) << 32) | low as u64
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn allocated(_: &Path, metadata: &fs::Metadata) -> u64 {
+        metadata.len()
+    }
+
+    /// Every item directly inside `parent` not named in `exclude`, each entry
+    /// as its own result so one bad entry doesn't cost the rest. A name that
+    /// isn't valid UTF-8 can't be checked against `exclude` and is an error.
+    #[allow(dead_code)]
+    pub fn read_children<P: AsRef<Path>>(
+        parent: P,
+        exclude: &[String],
+    ) -> impl Iterator<Item = crate::Result<PathBuf>> {
+        let (entries, failed) = match fs::read_dir(self::long_path(parent)) {
+            Ok(entries) => (Some(entries), None),
+            Err(e) => (None, Some(Err(e.into()))),
+        };
+
+        failed
+            .into_iter()
+            .chain(
+                entries
+                    .into_iter()
+                    .flatten()
+                    .filter_map(move |entry| match entry {
+                        Ok(entry) => {
+                            let path = self::long_path(entry.path());
+                            match entry.file_name().to_str() {
+                                Some(name) if self::find(name, exclude, false).is_some() => None,
+                                Some(_) => Some(Ok(path)),
+                                None => Some(Err(AppError::new(
+                                    AppErrorKind::Functionality,
+                                    format!("name of {:?} isn't valid UTF-8", path),
+                                ))),
+                            }
+                        }
+                        Err(e) => Some(Err(e.into())),
+                    }),
+            )
+    }
+
+    // TODO: return Result<Vec<PathBuf>, AppError>, see `read_children`
+    pub fn childern<P: AsRef<Path>>(parent: P, exclude: &[String], out: &Output) -> Vec<PathBuf> {
+        // don't add path that exists in exclude list
+        self::entries(parent, out)
+            .into_iter()
+            .filter(
+                |path| match self::excluded(self::name(path), exclude, false) {
+                    Some(_) => {
+                        out.line(format_args!("\u{1b}[33mExclude\u{1b}[0m {:?}...", path));
+                        false
+                    }
+                    None => true,
+                },
+            )
+            .collect()
+    }
+
+    /// Every item directly inside `parent`, unreadable entries are reported
+    /// to `out` and left out.
+    pub fn entries<P: AsRef<Path>>(parent: P, out: &Output) -> Vec<PathBuf> {
+        let mut children = Vec::new();
+
+        match fs::read_dir(self::long_path(parent)) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok(entry) => children.push(self::long_path(entry.path())),
+                        Err(e) => {
+                            out.line(format_args!("Error reading directory entry: {}", e));
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                out.line(format_args!("Error reading directory: {}", e));
+            }
+        }
+
+        children
+    }
+
+    /// Name of `path`, none when it isn't valid UTF-8: no pattern can match
+    /// such a name, bar `*`.
+    fn name(path: &Path) -> Option<&str> {
+        path.file_name().unwrap_or_default().to_str()
+    }
+
+    /// Position in `exclude` of the pattern `name` is excluded by, see
+    /// `find`. A name that isn't valid UTF-8 is only excluded by `*`.
+    fn excluded(name: Option<&str>, exclude: &[String], unicode: bool) -> Option<usize> {
+        match name {
+            Some(name) => self::find(name, exclude, unicode),
+            None => exclude.iter().position(|n| n == "*"),
+        }
+    }
+
+    /// Patterns of the `.cleanupignore` in `dir`, one per line. Blank lines
+    /// and `#` comments are left out.
+    fn ignores(dir: &Path) -> Vec<String> {
+        fs::read_to_string(dir.join(self::IGNORE_FILE))
+            .unwrap_or_default()
+            .lines()
+            .map(|line| line.trim().trim_end_matches(['/', '\\']))
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Whether `name` matches `pattern`, ignoring case, and ignoring how
+    /// accented letters are encoded with `unicode`.
+    pub fn glob(pattern: &str, name: &str, unicode: bool) -> bool {
+        self::wildcard(&self::fold(pattern, unicode), &self::fold(name, unicode))
+    }
+
+    /// `text` in lower case, and composed (NFC) with `unicode`.
+    fn fold(text: &str, unicode: bool) -> String {
+        match unicode {
+            true => unicode::nfc(&text.to_lowercase()),
+            false => text.to_lowercase(),
+        }
+    }
+
+    /// Whether `text` matches `pattern` exactly; `*` stands for any run of
+    /// characters and `?` for a single one. On a mismatch it backs up to
+    /// the last `*`, which takes one more character, so no pattern costs
+    /// more than its length times the text's.
+    pub fn wildcard(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        let (mut p, mut t) = (0, 0);
+        // the last `*` seen, and where in the text it stopped taking
+        let mut star = None;
+        while t < text.len() {
+            match pattern.get(p) {
+                Some('*') => {
+                    star = Some((p, t));
+                    p += 1;
+                }
+                Some(&c) if c == '?' || c == text[t] => {
+                    p += 1;
+                    t += 1;
+                }
+                _ => match star {
+                    Some((at, taken)) => {
+                        star = Some((at, taken + 1));
+                        p = at + 1;
+                        t = taken + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+        pattern[p..].iter().all(|&c| c == '*')
+    }
+
+    /// Position of `item` in `list`, ignoring case (and encodings, with
+    /// `unicode`, as `glob`).
+    pub fn find<T: AsRef<str>>(item: T, list: &[String], unicode: bool) -> Option<usize> {
+        let item = self::fold(item.as_ref(), unicode);
+        list.iter().position(|n| self::fold(n, unicode) == item)
+    }
+
+    /// Match `path` against the patterns for its kind of item, those of
+    /// their own kind or of the config's `kind`.
+    pub fn decide<P: AsRef<Path>>(
+        path: P,
+        patterns: &[Pattern],
+        kind: &Kind,
+        unicode: bool,
+    ) -> Decision {
+        let path = path.as_ref();
+        let actual = if path.is_dir() {
+            Kind::Folder
+        } else if path.is_file() {
+            Kind::File
+        } else {
+            return Decision::Keep(Reason::Kind(kind.clone()));
+        };
+
+        let patterns: Vec<String> = patterns
+            .iter()
+            .filter(|p| *p.kind_or(kind) == actual)
+            .map(|p| p.pattern.clone())
+            .collect();
+        if patterns.is_empty() {
+            Decision::Keep(Reason::Kind(kind.clone()))
+        } else {
+            self::name_match(path, &patterns, &actual, unicode)
+        }
+    }
+
+    /// Match the name (folder) or the extension (file) of `path` against
+    /// `patterns`, whatever `path` actually is. A file pattern can match the
+    /// stem or the whole name instead, see `file_part`.
+    /// A part that isn't valid UTF-8 matches no pattern.
+    fn name_match(path: &Path, patterns: &[String], kind: &Kind, unicode: bool) -> Decision {
+        let not_unicode = || {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            Decision::Keep(Reason::NotUnicode(name.into_owned()))
+        };
+        match kind {
+            Kind::Folder => {
+                let Some(name) = self::name(path) else {
+                    return not_unicode();
+                };
+                match patterns
+                    .iter()
+                    .position(|pattern| self::glob(pattern, name, unicode))
+                {
+                    Some(index) => Decision::Remove(Reason::Pattern(patterns[index].clone())),
+                    None => Decision::Keep(Reason::Name(name.to_string())),
+                }
+            }
+            Kind::File => {
+                let matched = patterns.iter().find(|pattern| {
+                    let (part, pattern) = self::file_part(path, pattern);
+                    part.is_some_and(|part| self::glob(pattern, part, unicode))
+                });
+                let Some(name) = self::name(path) else {
+                    return matched.map_or_else(not_unicode, |pattern| {
+                        Decision::Remove(Reason::Pattern(pattern.clone()))
+                    });
+                };
+                match matched {
+                    Some(pattern) => Decision::Remove(Reason::Pattern(pattern.clone())),
+                    None if patterns
+                        .iter()
+                        .any(|p| p.starts_with("stem:") || p.starts_with("name:")) =>
+                    {
+                        Decision::Keep(Reason::Name(name.to_string()))
+                    }
+                    None => {
+                        let extn = path.extension().unwrap_or_default();
+                        Decision::Keep(Reason::Extension(extn.to_str().unwrap_or_default().into()))
+                    }
+                }
+            }
+        }
+    }
+
+    /// The part of the file `path` a file `pattern` is about, with the rest
+    /// of the pattern: `stem:cache`, `ext:log` or `name:Dockerfile`, and the
+    /// extension when there's no prefix. The part is none when it isn't
+    /// valid UTF-8.
+    fn file_part<'a>(path: &'a Path, pattern: &'a str) -> (Option<&'a str>, &'a str) {
+        let part =
+            |part: Option<&'a std::ffi::OsStr>| part.map_or(Some(""), std::ffi::OsStr::to_str);
+        if let Some(stem) = pattern.strip_prefix("stem:") {
+            (part(path.file_stem()), stem)
+        } else if let Some(name) = pattern.strip_prefix("name:") {
+            (self::name(path), name)
+        } else {
+            let extn = pattern.strip_prefix("ext:").unwrap_or(pattern);
+            (part(path.extension()), extn)
+        }
+    }
+
+    /// With `link_target_pattern` only symlinks pointing at (or below) a
+    /// path matching that pattern, as `glob` matches a name, are candidates, whether their target exists or not, and with
+    /// `broken_links` only those whose target is gone. The name is matched
+    /// as for `kind`, and only the link itself is ever removed.
+    fn link_match(path: &Path, config: &Config) -> Decision {
+        let link = match fs::read_link(path) {
+            Ok(link) => link,
+            Err(_) => return Decision::Keep(Reason::NotLink),
+        };
+        if let Some(target) = &config.link_target_pattern
+            && !link
+                .ancestors()
+                .any(|at| self::glob(target, &at.to_string_lossy(), config.unicode()))
+        {
+            return Decision::Keep(Reason::LinkTarget {
+                link,
+                target: target.to_string(),
+            });
+        }
+        if config.broken_links && fs::metadata(path).is_ok() {
+            return Decision::Keep(Reason::LinkAlive(link));
+        }
+        let patterns: Vec<String> = config.patterns.iter().map(|p| p.pattern.clone()).collect();
+        self::name_match(path, &patterns, &config.kind, config.unicode())
+    }
+
+    /// A folder pattern with a separator, e.g. `build/release`, is a path
+    /// from the destination rather than a name, each part of it matched as
+    /// a name, so `*` never spans a separator while a `**` part spans any
+    /// number of folders. `Some` once `path` is a folder such a pattern
+    /// matches.
+    fn path_match(path: &Path, config: &Config) -> Option<Decision> {
+        let is_separator = |c: char| c == '/' || c == '\\';
+        let relative = path.strip_prefix(&config.destination).ok()?;
+        // a folder on the way with a name that isn't valid UTF-8 can't match
+        let names: Vec<&str> = relative
+            .iter()
+            .map(|name| name.to_str())
+            .collect::<Option<_>>()?;
+        let pattern = config.patterns.iter().find(|pattern| {
+            *pattern.kind_or(&config.kind) == Kind::Folder
+                && pattern.pattern.contains(is_separator)
+                && {
+                    let parts: Vec<&str> = pattern
+                        .pattern
+                        .split(is_separator)
+                        .filter(|part| !part.is_empty())
+                        .collect();
+                    self::parts_match(&parts, &names, config.unicode())
+                }
+        })?;
+        path.is_dir()
+            .then(|| Decision::Remove(Reason::Pattern(pattern.pattern.clone())))
+    }
+
+    /// Whether `names` match `parts` one by one, where a `**` part stands for
+    /// any number of names, none included.
+    pub fn parts_match(parts: &[&str], names: &[&str], unicode: bool) -> bool {
+        match (parts.split_first(), names.split_first()) {
+            (None, None) => true,
+            (Some((&"**", rest)), _) => {
+                self::parts_match(rest, names, unicode)
+                    || !names.is_empty() && self::parts_match(parts, &names[1..], unicode)
+            }
+            (Some((part, rest)), Some((name, names))) => {
+                self::glob(part, name, unicode) && self::parts_match(rest, names, unicode)
+            }
+            _ => false,
+        }
+    }
+
+    /// With `match_all` every pattern of the config has to match `path` on
+    /// its own, the first one which doesn't keeps it.
+    fn all_match(path: &Path, config: &Config) -> Decision {
+        let mut matched = vec![];
+        for pattern in &config.patterns {
+            match self::decide(
+                path,
+                std::slice::from_ref(pattern),
+                &config.kind,
+                config.unicode(),
+            ) {
+                Decision::Remove(_) => matched.push(pattern.pattern.as_str()),
+                keep => return keep,
+            }
+        }
+        if matched.is_empty() {
+            return Decision::Keep(Reason::Kind(config.kind.clone()));
+        }
+        Decision::Remove(Reason::Pattern(matched.join(" and ")))
+    }
+
+    /// Full decision for `path` under `config`: the name match first, then
+    /// every optional predicate of the config has to hold as well.
+    pub fn judge(path: &Path, config: &Config) -> Decision {
+        let decision = if config.link_target_pattern.is_some() || config.broken_links {
+            self::link_match(path, config)
+        } else if config.match_all {
+            self::all_match(path, config)
+        } else {
+            self::path_match(path, config).unwrap_or_else(|| {
+                self::decide(path, &config.patterns, &config.kind, config.unicode())
+            })
+        };
+        match decision {
+            Decision::Remove(reason) => {
+                let failed = self::name_check(path, config)
+                    .or_else(|| self::parent_check(path, config))
+                    .or_else(|| self::next_to_check(path, config))
+                    .or_else(|| self::mode_check(path, config))
+                    .or_else(|| self::owner_check(path, config))
+                    .or_else(|| self::empty_check(path, config))
+                    .or_else(|| self::children_check(path, config))
+                    .or_else(|| self::age_check(path, config))
+                    .or_else(|| self::xattr_check(path, config))
+                    .or_else(|| self::first_line_check(path, config));
+                match failed {
+                    Some(failed) => Decision::Keep(failed),
+                    None => Decision::Remove(reason),
+                }
+            }
+            decision => decision,
+        }
+    }
+
+    /// `Some(reason)` when the item's name is too short or too long, or has
+    /// characters out of the config's `name_charset`.
+    fn name_check(path: &Path, config: &Config) -> Option<Reason> {
+        // a byte that isn't UTF-8 counts as a character out of any charset
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let length = name.chars().count();
+        if config.name_len_min.is_some_and(|min| length < min)
+            || config.name_len_max.is_some_and(|max| length > max)
+        {
+            return Some(Reason::NameLength(length));
+        }
+        let charset = config.name_charset.as_ref()?;
+        match name.chars().all(|c| charset.contains(c)) {
+            true => None,
+            false => Some(Reason::Charset(charset.clone())),
+        }
+    }
+
+    /// `Some(reason)` when the name of the folder holding the item doesn't
+    /// match the config's `parent_name`.
+    fn parent_check(path: &Path, config: &Config) -> Option<Reason> {
+        let pattern = config.parent_name.as_ref()?;
+        let parent = path.parent().unwrap_or(path);
+        match self::name(parent) {
+            Some(name) if self::glob(pattern, name, config.unicode()) => None,
+            _ => Some(Reason::Parent {
+                name: parent
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                pattern: pattern.clone(),
+            }),
+        }
+    }
+
+    /// `Some(reason)` when no other entry of the folder holding the item
+    /// matches the config's `next_to`.
+    fn next_to_check(path: &Path, config: &Config) -> Option<Reason> {
+        let pattern = config.next_to.as_ref()?;
+        let folder = path.parent()?;
+        // the walk listing the folder reports it when it can't be read
+        let found = self::entries(folder, &Output::null()).iter().any(|entry| {
+            entry != path
+                && self::name(entry).is_some_and(|name| self::glob(pattern, name, config.unicode()))
+        });
+        match found {
+            true => None,
+            false => Some(Reason::Alone(pattern.clone())),
+        }
+    }
+
+    /// `Some(reason)` when the permission bits don't match the config's mask.
+    #[cfg(unix)]
+    fn mode_check(path: &Path, config: &Config) -> Option<Reason> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mask = config.mode_mask?;
+        let value = config.mode_value.unwrap_or(mask);
+        let mode = fs::symlink_metadata(path).ok()?.permissions().mode() & 0o7777;
+        if mode & mask == value {
+            None
+        } else {
+            Some(Reason::Mode { mode, mask, value })
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn mode_check(_: &Path, _: &Config) -> Option<Reason> {
+        None
+    }
+
+    /// `Some(reason)` when the item isn't owned by the config's owner.
+    #[cfg(unix)]
+    fn owner_check(path: &Path, config: &Config) -> Option<Reason> {
+        use std::os::unix::fs::MetadataExt;
+
+        let owner = config.owner.as_ref()?;
+        let uid = fs::symlink_metadata(path).ok()?.uid();
+        if self::uid_of(owner) == Some(uid) {
+            None
+        } else {
+            Some(Reason::Owner {
+                uid,
+                owner: owner.clone(),
+            })
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn owner_check(_: &Path, _: &Config) -> Option<Reason> {
+        None
+    }
+
+    /// `Some(reason)` when the config only wants empty items and this one
+    /// isn't, or can't be read to tell.
+    fn empty_check(path: &Path, config: &Config) -> Option<Reason> {
+        if !config.only_empty {
+            return None;
+        }
+        let metadata = fs::symlink_metadata(path).ok();
+        let empty = match metadata {
+            Some(metadata) if metadata.is_dir() => {
+                fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+            }
+            Some(metadata) => metadata.len() == 0,
+            None => false,
+        };
+        (!empty).then_some(Reason::NotEmpty)
+    }
+
+    /// `Some(reason)` when a folder has fewer entries than `min_children`
+    /// or more than `max_children`. Files aren't concerned.
+    fn children_check(path: &Path, config: &Config) -> Option<Reason> {
+        let (min, max) = (config.min_children, config.max_children);
+        if (min.is_none() && max.is_none()) || !fs::symlink_metadata(path).ok()?.is_dir() {
+            return None;
+        }
+        // no need to count past what tells both bounds apart
+        let limit = max.map_or(min.unwrap_or(0), |max| max + 1);
+        let count = self::count(path, config.children_recursive, Some(limit));
+        match (min, max) {
+            (Some(min), _) if count < min => Some(Reason::FewChildren(min)),
+            (_, Some(max)) if count > max => Some(Reason::ManyChildren(max)),
+            _ => None,
+        }
+    }
+
+    /// `Some(reason)` when the item is younger than the config's
+    /// `older_than`, by its `time_basis`.
+    fn age_check(path: &Path, config: &Config) -> Option<Reason> {
+        let older_than = config.older_than()?;
+        let time = self::time(path, &config.time_basis)?;
+        // a time in the future is as young as it gets
+        let age = time.elapsed().unwrap_or_default();
+        if age >= older_than {
+            None
+        } else {
+            Some(Reason::Recent {
+                basis: config.time_basis.clone(),
+                older_than: config.older_than.clone().unwrap_or_default(),
+            })
+        }
+    }
+
+    /// The `basis` timestamp of `path`, itself rather than what it links to.
+    fn time(path: &Path, basis: &TimeBasis) -> Option<SystemTime> {
+        let metadata = fs::symlink_metadata(path).ok()?;
+        let time = match basis {
+            TimeBasis::Mtime => metadata.modified(),
+            TimeBasis::Atime => metadata.accessed().or_else(|_| metadata.modified()),
+            #[cfg(unix)]
+            TimeBasis::Ctime => {
+                use std::os::unix::fs::MetadataExt;
+                let seconds = u64::try_from(metadata.ctime()).unwrap_or_default();
+                let since = Duration::new(seconds, metadata.ctime_nsec() as u32);
+                Ok(std::time::UNIX_EPOCH + since)
+            }
+            #[cfg(not(unix))]
+            TimeBasis::Ctime => metadata.modified(),
+        };
+        time.ok()
+    }
+
+    /// `Some(reason)` when the item doesn't carry the config's extended
+    /// attribute (with its value, when set). Nothing does where extended
+    /// attributes aren't supported.
+    fn xattr_check(path: &Path, config: &Config) -> Option<Reason> {
+        let name = config.xattr_name.as_ref()?;
+        let carries = match (self::xattr(path, name), &config.xattr_value) {
+            (Some(actual), Some(value)) => actual == value.as_bytes(),
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if carries {
+            None
+        } else {
+            Some(Reason::Xattr {
+                name: name.clone(),
+                value: config.xattr_value.clone(),
+            })
+        }
+    }
+
+    /// Value of the extended attribute `name` of `path` itself, never of
+    /// what a symlink points at.
+    #[cfg(unix)]
+    fn xattr(path: &Path, name: &str) -> Option<Vec<u8>> {
+        xattr::get(path, name).ok().flatten()
+    }
+
+    #[cfg(not(unix))]
+    fn xattr(_: &Path, _: &str) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// `Some(reason)` when the first line of the file doesn't match the
+    /// config's glob. Only that line is read, and never more than 4 KiB.
+    fn first_line_check(path: &Path, config: &Config) -> Option<Reason> {
+        use std::io::{BufRead, BufReader, Read};
+
+        let pattern = config.first_line_matches.as_ref()?;
+        let mut line = Vec::new();
+        if path.is_file()
+            && let Ok(file) = fs::File::open(path)
+        {
+            let _ = BufReader::new(file.take(4096)).read_until(b'\n', &mut line);
+        }
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        if self::wildcard(pattern, line) {
+            None
+        } else {
+            Some(Reason::FirstLine {
+                line: line.to_string(),
+                pattern: pattern.clone(),
+            })
+        }
+    }
+
+    /// uid of `owner`, given either as a number or as a user name.
+    #[cfg(unix)]
+    pub fn uid_of(owner: &str) -> Option<u32> {
+        match owner.parse::<u32>() {
+            Ok(uid) => Some(uid),
+            Err(_) => nix::unistd::User::from_name(owner)
+                .ok()
+                .flatten()
+                .map(|user| user.uid.as_raw()),
+        }
+    }
+
+    /// Entries left in place by a removal, with the reason each was.
+    pub type Left = Vec<(PathBuf, std::io::Error)>;
+
+    /// Count of the failed removals of a run, which configs running in
+    /// parallel share.
+    #[derive(Clone, Default)]
+    pub struct Failures(Arc<AtomicUsize>);
+
+    impl Failures {
+        pub fn add(&self, count: usize) {
+            self.0.fetch_add(count, Ordering::SeqCst);
+        }
+
+        pub fn count(&self) -> usize {
+            self.0.load(Ordering::SeqCst)
+        }
+
+        pub fn reset(&self) {
+            self.0.store(0, Ordering::SeqCst);
+        }
+    }
+
+    impl std::fmt::Debug for Failures {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Failures({})", self.count())
+        }
+    }
+
+    impl PartialEq for Failures {
+        fn eq(&self, other: &Self) -> bool {
+            self.count() == other.count()
+        }
+    }
+
+    /// Remove a file or a whole folder. Under `PermissionPolicy::Abort` the
+    /// folder goes in one go, falling back once to removing it entry by entry
+    /// when that fails; otherwise it's entry by entry right away. Either way
+    /// the entries which can't be removed are returned, the rest gone.
+    ///
+    /// What `path` is gets looked up right before, as it may have changed
+    /// since it was matched; an item gone meanwhile is a `NotFound` error.
+    /// The current directory, or a folder holding it, is never removed. With
+    /// `device`, a folder goes entry by entry too, leaving the mount points
+    /// of other devices it holds alone.
+    pub fn remove_item<P: AsRef<Path>>(
+        fs: &dyn FileSystem,
+        path: P,
+        policy: &PermissionPolicy,
+        device: Option<u64>,
+        out: &Output,
+    ) -> std::io::Result<Left> {
+        let path = self::long_path(path);
+        let metadata = fs::symlink_metadata(&path)?;
+        if metadata.is_dir() && self::holds_cwd(&path) {
+            return Err(std::io::Error::other(format!(
+                "{:?} is the current directory or holds it",
+                path
+            )));
+        }
+        // a link to a folder goes as a folder, which only removes the link
+        let folder = metadata.is_dir() || (metadata.is_symlink() && path.is_dir());
+
+        let mut left = Left::new();
+        match policy {
+            PermissionPolicy::Abort if folder && device.is_none() => {
+                match fs.remove_dir_all(&path) {
+                    Ok(_) => {}
+                    // that's for the policy to handle, giving up here
+                    Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => return Err(e),
+                    // some mounts only manage children first, one at a time
+                    Err(e) => {
+                        out.line(format_args!(
+                            "\u{1b}[33mRetrying\u{1b}[0m {:?} entry by entry after: {}",
+                            path, e
+                        ));
+                        self::remove_tree(fs, &path, policy, None, &mut left);
+                    }
+                }
+            }
+            PermissionPolicy::Abort if !folder => fs.remove_file(&path)?,
+            _ => self::remove_tree(fs, &path, policy, device, &mut left),
+        }
+        Ok(left)
+    }
+
+    /// Whether the current directory is `folder` or somewhere below it.
+    fn holds_cwd(folder: &Path) -> bool {
+        let cwd = std::env::current_dir().and_then(fs::canonicalize);
+        match (cwd, fs::canonicalize(folder)) {
+            (Ok(cwd), Ok(folder)) => cwd.starts_with(folder),
+            _ => false,
+        }
+    }
+
+    /// Depth-first removal of `path`, collecting what couldn't be removed.
+    /// Symlinks are removed as themselves and never followed, and folders
+    /// on another device than `device` kept.
+    fn remove_tree(
+        fs: &dyn FileSystem,
+        path: &Path,
+        policy: &PermissionPolicy,
+        device: Option<u64>,
+        left: &mut Left,
+    ) {
+        let is_dir = fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir());
+        if !is_dir {
+            self::attempt(path, policy, left, |path| fs.remove_file(path));
+            return;
+        }
+        if let Some(device) = device
+            && fs.device(path) != Some(device)
+        {
+            left.push((
+                path.to_path_buf(),
+                std::io::Error::other("on another filesystem, left alone"),
+            ));
+            return;
+        }
+
+        let before = left.len();
+        match fs::read_dir(path) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok(entry) => self::remove_tree(fs, &entry.path(), policy, device, left),
+                        Err(e) => left.push((path.to_path_buf(), e)),
+                    }
+                }
+            }
+            Err(e) => left.push((path.to_path_buf(), e)),
+        }
+        // a folder keeping some entry can't go, which is already told
+        if left.len() == before {
+            self::attempt(path, policy, left, |path| fs.remove_dir(path));
+        }
+    }
+
+    /// Run `remove` on `path`; under `PermissionPolicy::Fix` a refusal is
+    /// retried once after making `path` and its folder writable.
+    fn attempt<F>(path: &Path, policy: &PermissionPolicy, left: &mut Left, remove: F)
+    where
+        F: Fn(&Path) -> std::io::Result<()>,
+    {
+        let result = match remove(path) {
+            Err(e)
+                if e.kind() == std::io::ErrorKind::PermissionDenied
+                    && *policy == PermissionPolicy::Fix =>
+            {
+                path.parent().map(self::writable);
+                self::writable(path);
+                remove(path)
+            }
+            result => result,
+        };
+        // gone meanwhile is as good as removed
+        if let Err(e) = result
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            left.push((path.to_path_buf(), e));
+        }
+    }
+
+    /// Give the owner write permission on `path`, quietly as it's only
+    /// worth a retry.
+    #[cfg(unix)]
+    fn writable(path: &Path) {
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Ok(metadata) = fs::symlink_metadata(path)
+            && !metadata.is_symlink()
+        {
+            let mode = metadata.permissions().mode() | 0o200;
+            let _ = fs::set_permissions(path, fs::Permissions::from_mode(mode));
+        }
+    }
+
+    /// Clear the read-only attribute of `path`.
+    #[cfg(not(unix))]
+    #[allow(clippy::permissions_set_readonly_false)]
+    fn writable(path: &Path) {
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            let mut permissions = metadata.permissions();
+            permissions.set_readonly(false);
+            let _ = fs::set_permissions(path, permissions);
+        }
+    }
+
+    /// Windows rejects paths longer than `MAX_PATH` (260 chars) unless they
+    /// carry the extended-length `\\?\` prefix, which deep `node_modules`
+    /// trees easily exceed. Such paths are made absolute and prefixed, shorter
+    /// ones are returned untouched.
+    #[cfg(windows)]
+    pub fn long_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        use std::ffi::OsString;
+
+        const MAX_PATH: usize = 260;
+        const VERBATIM: &str = r"\\?\";
+
+        let path = path.as_ref();
+        if path.as_os_str().len() < MAX_PATH {
+            return path.to_path_buf();
+        }
+
+        // `\\?\` disables path normalization, so resolve `.`/`..` and
+        // separators first.
+        let absolute = match path::absolute(path) {
+            Ok(absolute) => absolute,
+            Err(_) => return path.to_path_buf(),
+        };
+
+        match absolute.to_str() {
+            Some(s) if s.starts_with(VERBATIM) => absolute,
+            Some(s) if s.starts_with(r"\\") => {
+                PathBuf::from(format!(r"{}UNC\{}", VERBATIM, &s[2..]))
+            }
+            _ => {
+                let mut prefixed = OsString::from(VERBATIM);
+                prefixed.push(absolute.as_os_str());
+                PathBuf::from(prefixed)
+            }
+        }
+    }
+
+    #[cfg(not(windows))]
+    pub fn long_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        path.as_ref().to_path_buf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Charset, Preset, manifest};
+    use clap::Parser;
+    use std::sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    #[test]
+    fn create_manager() {
+        let manager = Manager::new();
+        assert_eq!(
+            manager,
+            Manager {
+                configs: vec![],
+                dryrun: false,
+                ..Manager::new()
+            }
+        );
+    }
+
+    #[test]
+    fn add_config() {
+        let mut manager = Manager::new();
+        manager.add(Config::new(
+            "/Users/abhinath/productive/pool/Project",
+            Kind::Folder,
+            vec!["build", "debug", "release"],
+            None,
+        ));
+        assert_eq!(
+            manager,
+            Manager {
+                configs: vec![Config {
+                    destination: PathBuf::from("/Users/abhinath/productive/pool/Project"),
+                    kind: Kind::Folder,
+                    patterns: vec![
+                        Pattern::from("build"),
+                        Pattern::from("debug"),
+                        Pattern::from("release"),
+                    ],
+                    exclude: None,
+                    ..Default::default()
+                }],
+                dryrun: false,
+                ..Manager::new()
+            }
+        );
+    }
+
+    #[test]
+    fn path_patterns() {
+        let root = tempfile::tempdir().unwrap();
+        let nested = root.path().join("build").join("release");
+        let elsewhere = root.path().join("app").join("release");
+        let deeper = root.path().join("app").join("build").join("release");
+        for folder in [&nested, &elsewhere, &deeper] {
+            fs::create_dir_all(folder).unwrap();
+        }
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager
+            .format(
+                root.path(),
+                Kind::Folder,
+                vec![String::from("./build/release/")],
+                None,
+            )
+            .unwrap();
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 1);
+        assert!(!nested.exists());
+        assert!(root.path().join("build").exists());
+        assert!(elsewhere.exists());
+        assert!(deeper.exists());
+    }
+
+    #[test]
+    fn path_globs() {
+        let root = tempfile::tempdir().unwrap();
+        let run = |pattern: &str| {
+            let mut folders = vec![];
+            for path in ["target", "app/target", "app/crates/core/target"] {
+                let folder = root.path().join(path);
+                fs::create_dir_all(&folder).unwrap();
+                folders.push(folder);
+            }
+            let mut manager = Manager::new();
+            manager.set_output(Output::buffer().0);
+            manager
+                .format(root.path(), Kind::Folder, vec![pattern.to_string()], None)
+                .unwrap();
+            manager.execute().unwrap();
+            folders.iter().map(|f| !f.exists()).collect::<Vec<bool>>()
+        };
+
+        // a single level
+        assert_eq!(run("*/target"), [false, true, false]);
+        // any depth, the destination itself included
+        assert_eq!(run("**/target"), [true, true, true]);
+        assert_eq!(run("app/**/target"), [false, true, true]);
+        assert_eq!(run("app/*/*/target"), [false, false, true]);
+
+        assert!(helper::parts_match(&["**"], &["a", "b"], false));
+        assert!(!helper::parts_match(&["a", "**", "c"], &["a", "b"], false));
+    }
+
+    #[test]
+    fn match_all() {
+        let root = tempfile::tempdir().unwrap();
+        let run = |match_all: bool| {
+            for name in ["temp_1.dat", "temp_1.txt", "other.dat"] {
+                fs::write(root.path().join(name), "").unwrap();
+            }
+            let mut config = Config::new(root.path(), Kind::File, vec!["name:*temp*", "dat"], None);
+            config.match_all = match_all;
+            let mut manager = Manager::new();
+            manager.set_output(Output::buffer().0);
+            manager.add(config);
+            manager.execute().unwrap().removed
+        };
+
+        // any pattern: all three go
+        assert_eq!(run(false), 3);
+
+        // every pattern: only the temp file ending in `.dat`
+        assert_eq!(run(true), 1);
+        assert!(!root.path().join("temp_1.dat").exists());
+        assert!(root.path().join("temp_1.txt").exists());
+        assert!(root.path().join("other.dat").exists());
+
+        let mut config = Config::new(root.path(), Kind::File, vec!["name:*temp*", "dat"], None);
+        config.match_all = true;
+        fs::write(root.path().join("temp_1.dat"), "").unwrap();
+        assert_eq!(
+            helper::judge(&root.path().join("temp_1.dat"), &config),
+            Decision::Remove(Reason::Pattern("name:*temp* and dat".to_string()))
+        );
+    }
+
+    #[test]
+    fn normalize_unicode() {
+        let root = tempfile::tempdir().unwrap();
+        // the same name, composed (NFC) and decomposed (NFD)
+        let (composed, decomposed) = ("caf\u{e9}", "cafe\u{301}");
+        let run = |pattern: &str, normalize: bool| {
+            for name in [composed, decomposed] {
+                fs::create_dir_all(root.path().join(name)).unwrap();
+            }
+            let mut config = Config::new(root.path(), Kind::Folder, vec![pattern], None);
+            config.normalize_unicode = Some(normalize);
+            let mut manager = Manager::new();
+            manager.set_output(Output::buffer().0);
+            manager.add(config);
+            manager.execute().unwrap().removed
+        };
+
+        assert_eq!(run(composed, true), 2);
+        assert_eq!(run(decomposed, true), 2);
+        assert_eq!(run(composed, false), 1);
+        assert!(root.path().join(decomposed).exists());
+
+        assert!(helper::glob("CAF\u{c9}*", "cafe\u{301}-old", true));
+        assert!(!helper::glob("CAF\u{c9}*", "cafe\u{301}-old", false));
+        assert_eq!(
+            helper::find(decomposed, &[composed.to_string()], true),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn pattern_variants() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("build")).unwrap();
+        let config = root.path().join("config.json");
+
+        for pattern in ["build", "build/", "./build", "./build/"] {
+            let mut manager = Manager::new();
+            manager.dryrun = true;
+            manager.set_output(Output::buffer().0);
+            manager
+                .format(root.path(), Kind::Folder, vec![pattern.to_string()], None)
+                .unwrap();
+            assert_eq!(manager.execute().unwrap().removed, 1, "{}", pattern);
+
+            let json = serde_json::json!([{
+                "destination": root.path(),
+                "kind": "folder",
+                "patterns": [pattern],
+            }]);
+            fs::write(&config, json.to_string()).unwrap();
+            let mut manager = Manager::new();
+            manager.dryrun = true;
+            manager.set_output(Output::buffer().0);
+            manager.parse(&config).unwrap();
+            assert_eq!(manager.execute().unwrap().removed, 1, "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn rollup() {
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("src");
+        fs::create_dir_all(src.join("nested")).unwrap();
+        fs::create_dir(root.path().join("docs")).unwrap();
+        for file in ["a.log", "b.rs", "c.rs", "nested/d.log", "nested/e.rs"] {
+            fs::write(src.join(file), "").unwrap();
+        }
+        fs::write(root.path().join("docs").join("f.md"), "").unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.rollup = true;
+        manager.set_output(output);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+        assert_eq!(manager.execute().unwrap().removed, 2);
+
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let mut scanned: Vec<&str> = text.lines().filter(|l| l.contains("Scanned")).collect();
+        scanned.sort();
+        assert_eq!(
+            scanned,
+            vec![
+                format!(
+                    "\u{1b}[1mScanned\u{1b}[0m {:?}, 0 removed, 1 kept...",
+                    root.path().join("docs")
+                ),
+                format!("\u{1b}[1mScanned\u{1b}[0m {:?}, 2 removed, 4 kept...", src),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_format() {
+        let mut manager = Manager::new();
+        manager
+            .format(
+                "/Users/abhinath/productive/pool/Project",
+                Kind::Folder,
+                vec![
+                    String::from("build"),
+                    String::from("debug"),
+                    String::from("release"),
+                ],
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(
+            manager,
+            Manager {
+                configs: vec![Config {
+                    destination: PathBuf::from("/Users/abhinath/productive/pool/Project"),
+                    kind: Kind::Folder,
+                    patterns: vec![
+                        Pattern::from("build"),
+                        Pattern::from("debug"),
+                        Pattern::from("release"),
+                    ],
+                    exclude: None,
+                    ..Default::default()
+                }],
+                dryrun: false,
+                ..Manager::new()
+            }
+        );
+    }
+
+    #[test]
+    // #[should_panic]
+    fn check_remove_as_mut() {
+        let mut item = helper::Remove {
+            destination: PathBuf::from("/Users/abhinath/productive/pool"),
+            kind: Kind::Folder,
+            patterns: vec![
+                String::from("packages"),
+                String::from("bin"),
+                String::from("obj"),
+                String::from("Debug"),
+                String::from("Release"),
+            ],
+            exclude: vec![],
+            dryrun: true,
+            output: Output::null(),
+        };
+        helper::remove_as_mut(&mut item);
+    }
+
+    #[test]
+    fn largest_and_deepest() {
+        let root = tempfile::tempdir().unwrap();
+        let small = root.path().join("a").join("build");
+        let large = root.path().join("b").join("build");
+        let deep = root.path().join("c").join("d").join("e");
+        fs::create_dir_all(&small).unwrap();
+        fs::create_dir_all(&large).unwrap();
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(small.join("out.o"), vec![0u8; 10]).unwrap();
+        fs::write(large.join("out.o"), vec![0u8; 4096]).unwrap();
+        fs::write(large.join("lib.a"), vec![0u8; 1024]).unwrap();
+        fs::write(deep.join("main.rs"), "fn main() {}").unwrap();
+
+        let mut manager = Manager::new();
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["build"], None));
+        let summary = manager.execute().unwrap();
+
+        assert_eq!(summary.removed, 2);
+        assert_eq!(summary.bytes, 10 + 4096 + 1024);
+        assert_eq!(summary.largest_item, Some((large.clone(), 4096 + 1024)));
+        // root/c/d/e/main.rs
+        assert_eq!(summary.max_depth_reached, 4);
+        assert!(!small.exists());
+        assert!(!large.exists());
+    }
+
+    #[test]
+    fn timing() {
+        let root = tempfile::tempdir().unwrap();
+        let build = root.path().join("build");
+        fs::create_dir_all(&build).unwrap();
+        fs::write(build.join("out.o"), vec![0u8; 2048]).unwrap();
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["build"], None));
+        let summary = manager.execute().unwrap();
+
+        assert!(summary.duration > Duration::ZERO);
+        let seconds = summary.duration.as_secs_f64();
+        assert!(summary.items_per_sec > 0.0);
+        assert!((summary.items_per_sec * seconds - 1.0).abs() < 1e-6);
+        assert!((summary.bytes_per_sec * seconds - 2048.0).abs() < 1e-3);
+        assert!(summary.to_string().contains("Took"));
+        let json = serde_json::to_value(&summary).unwrap();
+        assert!(json["duration"].as_f64().unwrap() > 0.0);
+    }
+
+    #[test]
+    fn assert_clean() {
+        let root = tempfile::tempdir().unwrap();
+        let config = root.path().join("config.json");
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "folder", "patterns": ["build"] }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+        fs::create_dir(root.path().join("build")).unwrap();
+
+        let args = vec!["neaten", "-c", config.to_str().unwrap(), "--assert-clean"];
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+
+        // found, but never removed
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 1);
+        assert!(root.path().join("build").exists());
+        assert_eq!(manager.exit_code(&summary), 1);
+
+        fs::remove_dir(root.path().join("build")).unwrap();
+        let summary = manager.execute().unwrap();
+        assert_eq!(manager.exit_code(&summary), 0);
+
+        // a broken config never passes
+        fs::write(&config, "[{").unwrap();
+        let args = vec!["neaten", "-c", config.to_str().unwrap(), "--assert-clean"];
+        let error = Manager::new()
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap_err();
+        assert_eq!(error.exit_code(), AppError::EXIT_CODE);
+        assert_ne!(error.exit_code(), 0);
+    }
+
+    #[test]
+    fn pending_exit_code() {
+        let root = tempfile::tempdir().unwrap();
+        let config = root.path().join("config.json");
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "folder", "patterns": ["build"] }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+        fs::create_dir(root.path().join("build")).unwrap();
+        let manager = |flags: &[&str]| {
+            let mut args = vec!["neaten", "-c", config.to_str().unwrap()];
+            args.extend(flags);
+            let mut manager = Manager::new();
+            manager.set_output(Output::buffer().0);
+            manager
+                .validate(Engine::try_parse_from(args).unwrap())
+                .unwrap();
+            manager
+        };
+
+        // a plain dry-run doesn't fail whatever it finds
+        let plain = manager(&["--dryrun"]);
+        let summary = plain.execute().unwrap();
+        assert_eq!(plain.exit_code(&summary), 0);
+
+        // would change things
+        let pending = manager(&["--dryrun", "--exit-code"]);
+        let summary = pending.execute().unwrap();
+        assert_eq!(summary.removed, 1);
+        assert_eq!(pending.exit_code(&summary), Manager::PENDING);
+
+        // clean
+        fs::remove_dir(root.path().join("build")).unwrap();
+        let summary = pending.execute().unwrap();
+        assert_eq!(pending.exit_code(&summary), 0);
+
+        // an error is neither clean nor pending
+        let json = serde_json::json!([
+            { "destination": root.path().join("gone"), "kind": "folder", "patterns": ["build"] }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+        let mut args = vec!["neaten", "-c", config.to_str().unwrap()];
+        args.extend(["--dryrun", "--exit-code"]);
+        let error = Manager::new()
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap_err();
+        assert!(![0, 1, Manager::PENDING].contains(&error.exit_code()));
+    }
+
+    #[test]
+    fn summary_in_dryrun() {
+        let root = tempfile::tempdir().unwrap();
+        let build = root.path().join("build");
+        fs::create_dir_all(&build).unwrap();
+        fs::write(build.join("out.o"), vec![0u8; 64]).unwrap();
+
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["build"], None));
+        let summary = manager.execute().unwrap();
+
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.largest_item, Some((build.clone(), 64)));
+        assert_eq!(summary.max_depth_reached, 1);
+        assert!(build.exists());
+    }
+
+    #[test]
+    fn explain_decisions() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join(".git")).unwrap();
+        fs::create_dir_all(root.path().join("src")).unwrap();
+        fs::write(root.path().join("foo.txt"), "").unwrap();
+        fs::write(root.path().join("bar.log"), "").unwrap();
+        fs::write(root.path().join("src").join("main.log"), "").unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.explain = true;
+        manager.set_output(output);
+        manager.add(Config::new(
+            root.path(),
+            Kind::File,
+            vec!["log"],
+            Some(vec![".git"]),
+        ));
+        manager.execute().unwrap();
+
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines.contains(&"kept foo.txt: extension 'txt' not in patterns"));
+        assert!(lines.contains(&"removed bar.log: matched pattern 'log'"));
+        assert!(lines.contains(&"removed main.log: matched pattern 'log'"));
+        assert!(lines.contains(&"skipped .git: excluded by '.git'"));
+        assert!(lines.contains(&"kept src: not a file"));
+        // dry-run leaves everything in place
+        assert!(root.path().join("bar.log").exists());
+    }
+
+    #[test]
+    fn no_explain_by_default() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("foo.txt"), "").unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.set_output(output);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+        manager.execute().unwrap();
+
+        assert!(buffer.lock().unwrap().is_empty());
+    }
+
+    fn missing_destination_manager(policy: &str) -> (crate::Result<()>, Manager, String) {
+        let root = tempfile::tempdir().unwrap();
+        let present = root.path().join("present");
+        fs::create_dir_all(&present).unwrap();
+
+        let config = root.path().join("config.json");
+        let json = serde_json::json!([
+            { "destination": present, "kind": "folder", "patterns": ["build"] },
+            { "destination": root.path().join("absent"), "kind": "folder", "patterns": ["build"] },
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+
+        let engine = Engine::try_parse_from(vec![
+            "neaten",
+            "--config",
+            config.to_str().unwrap(),
+            "--on-missing-destination",
+            policy,
+        ])
+        .unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        let result = manager.validate(engine);
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        (result, manager, text)
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn linked_destination() {
+        let root = tempfile::tempdir().unwrap();
+        let real = root.path().join("real");
+        let link = root.path().join("link");
+        fs::create_dir_all(real.join("build")).unwrap();
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let config = root.path().join("config.json");
+        let json = serde_json::json!([
+            { "destination": link, "kind": "folder", "patterns": ["build"] }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+        let validated = |policy: &str| {
+            let args = vec![
+                "neaten",
+                "--config",
+                config.to_str().unwrap(),
+                "--on-linked-destination",
+                policy,
+            ];
+            let (output, buffer) = Output::buffer();
+            let mut manager = Manager::new();
+            manager.set_output(output);
+            let result = manager.validate(Engine::try_parse_from(args).unwrap());
+            (result, manager, buffer)
+        };
+
+        let (result, _, _) = validated("refuse");
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains(&format!(
+            "destination {:?} is a symlink to {:?}",
+            link, real
+        )));
+        assert!(real.join("build").exists());
+
+        // the target is walked, and said so
+        let (result, manager, buffer) = validated("follow");
+        result.unwrap();
+        assert_eq!(manager.execute().unwrap().removed, 1);
+        assert!(!real.join("build").exists());
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains(&format!(
+            "Linked\u{1b}[0m destination {:?} walks {:?}",
+            link, real
+        )));
+
+        // a config can refuse on its own
+        let json = serde_json::json!([
+            { "destination": link, "kind": "folder", "patterns": ["build"], "on_linked_destination": "refuse" }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+        let (result, _, _) = validated("follow");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn missing_destination_error() {
+        let (result, _, _) = missing_destination_manager("error");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn missing_destination_warn() {
+        let (result, manager, text) = missing_destination_manager("warn");
+        assert!(result.is_ok());
+        assert_eq!(manager.configs.len(), 1);
+        assert!(manager.configs[0].destination.ends_with("present"));
+        assert!(text.contains("absent"));
+    }
+
+    #[test]
+    fn missing_destination_skip() {
+        let (result, manager, text) = missing_destination_manager("skip");
+        assert!(result.is_ok());
+        assert_eq!(manager.configs.len(), 1);
+        assert!(manager.configs[0].destination.ends_with("present"));
+        assert!(text.is_empty());
+    }
+
+    #[test]
+    fn missing_destination_config_override() {
+        let root = tempfile::tempdir().unwrap();
+        let config = root.path().join("config.json");
+        let json = serde_json::json!([{
+            "destination": root.path().join("absent"),
+            "kind": "folder",
+            "patterns": ["build"],
+            "on_missing_destination": "skip"
+        }]);
+        fs::write(&config, json.to_string()).unwrap();
+
+        let engine =
+            Engine::try_parse_from(vec!["neaten", "-c", config.to_str().unwrap()]).unwrap();
+        let mut manager = Manager::new();
+        assert!(manager.validate(engine).is_ok());
+        assert!(manager.configs.is_empty());
+    }
+
+    #[test]
+    fn config_dir() {
+        let root = tempfile::tempdir().unwrap();
+        let configs = root.path().join("configs");
+        fs::create_dir(&configs).unwrap();
+        for (file, project, pattern) in [("a.json", "web", "dist"), ("b.json", "app", "target")] {
+            fs::create_dir_all(root.path().join(project).join(pattern)).unwrap();
+            let json = serde_json::json!([{
+                "destination": root.path().join(project),
+                "kind": "folder",
+                "patterns": [pattern],
+            }]);
+            fs::write(configs.join(file), json.to_string()).unwrap();
+        }
+        fs::write(configs.join("notes.txt"), "not a config").unwrap();
+        let dir = configs.to_str().unwrap();
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager
+            .validate(Engine::try_parse_from(vec!["neaten", "--config-dir", dir]).unwrap())
+            .unwrap();
+        assert_eq!(manager.configs.len(), 2);
+        assert!(manager.configs[0].destination.ends_with("web"));
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 2);
+        assert!(!root.path().join("web").join("dist").exists());
+        assert!(!root.path().join("app").join("target").exists());
+
+        // a broken file fails the run, or is left out
+        fs::write(configs.join("c.json"), "[{").unwrap();
+        let mut manager = Manager::new();
+        let args = vec!["neaten", "--config-dir", dir];
+        let error = manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap_err();
+        assert!(error.to_string().contains("c.json"));
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        let args = vec!["neaten", "--config-dir", dir, "--on-invalid-config", "skip"];
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+        assert_eq!(manager.configs.len(), 2);
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("c.json"));
+
+        // an invalid entry is named by its place among all the files' configs
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "file", "patterns": ["log"] },
+            {
+                "label": "broken",
+                "destination": root.path(),
+                "kind": "file",
+                "patterns": ["tmp"],
+                "interval": "soon",
+            },
+        ]);
+        fs::write(configs.join("c.json"), json.to_string()).unwrap();
+        let mut manager = Manager::new();
+        let args = vec!["neaten", "--config-dir", dir];
+        let error = manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap_err();
+        assert_eq!(error.config_index(), Some(3));
+        assert_eq!(error.label(), Some("broken"));
+        assert!(error.message().contains("c.json"), "{}", error);
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        let args = vec!["neaten", "--config-dir", dir, "--on-invalid-config", "skip"];
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+        assert_eq!(manager.configs.len(), 2);
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("config #3 'broken'"), "{}", text);
+    }
+
+    #[test]
+    fn relative_destinations() {
+        let root = tempfile::tempdir().unwrap();
+        let configs = root.path().join("configs");
+        for folder in ["configs", "project/build", "work/cache/tmp"] {
+            fs::create_dir_all(root.path().join(folder)).unwrap();
+        }
+        let json = serde_json::json!([
+            { "destination": "../project", "kind": "folder", "patterns": ["build"] },
+            {
+                "base_dir": "../work",
+                "destination": "cache",
+                "kind": "folder",
+                "patterns": ["tmp"],
+            },
+        ]);
+        let config = configs.join("config.json");
+        fs::write(&config, json.to_string()).unwrap();
+
+        // from the config file's folder, wherever the run starts
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        let args = vec!["neaten", "-c", config.to_str().unwrap()];
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+        assert_eq!(manager.configs[0].destination, root.path().join("project"));
+        assert_eq!(manager.execute().unwrap().removed, 2);
+        assert!(!root.path().join("project/build").exists());
+        assert!(!root.path().join("work/cache/tmp").exists());
+
+        // an absolute one stays as it is
+        let mut absolute = Config::new(root.path(), Kind::Folder, vec!["build"], None);
+        absolute.base_dir = Some(PathBuf::from("elsewhere"));
+        absolute.resolve(&configs).unwrap();
+        assert_eq!(absolute.destination, root.path());
+
+        // `..` is folded in, for the config's own folder to be guarded
+        let json = serde_json::json!([
+            { "destination": "..", "kind": "folder", "patterns": ["configs"] },
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        let args = vec!["neaten", "-c", config.to_str().unwrap()];
+        let error = manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("holds the config file"),
+            "{}",
+            error
+        );
+        assert!(configs.exists());
+    }
+
+    #[test]
+    fn skip_invalid() {
+        let root = tempfile::tempdir().unwrap();
+        let mut entries = vec![];
+        for (project, pattern) in [("web", "dist"), ("app", "target"), ("lib", "build")] {
+            fs::create_dir_all(root.path().join(project).join(pattern)).unwrap();
+            entries.push(serde_json::json!({
+                "destination": root.path().join(project),
+                "kind": "folder",
+                "patterns": [pattern],
+            }));
+        }
+        entries[1]["interval"] = serde_json::json!("soon");
+        // no config at all, and one whose destination is gone
+        entries.push(serde_json::json!({
+            "destination": root.path(),
+            "kind": "gadget",
+            "patterns": ["dist"],
+        }));
+        entries.push(serde_json::json!({
+            "destination": root.path().join("gone"),
+            "kind": "folder",
+            "patterns": ["dist"],
+        }));
+        let config = root.path().join("config.json");
+        fs::write(&config, serde_json::Value::from(entries).to_string()).unwrap();
+        let config = config.to_str().unwrap();
+
+        // strict by default: nothing runs
+        let mut manager = Manager::new();
+        let args = vec!["neaten", "-c", config];
+        let error = manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap_err();
+        assert_eq!(error.config_index(), Some(1));
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        let args = vec!["neaten", "-c", config, "--skip-invalid"];
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+        assert_eq!(manager.execute().unwrap().removed, 2);
+        assert!(!root.path().join("web").join("dist").exists());
+        assert!(root.path().join("app").join("target").exists());
+        assert!(!root.path().join("lib").join("build").exists());
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("config #1 is invalid"), "{}", text);
+        assert!(text.contains("soon"), "{}", text);
+        assert!(text.contains("config #3 is invalid"), "{}", text);
+        assert!(text.contains("gadget"), "{}", text);
+        assert!(text.contains("config #4 is invalid"), "{}", text);
+        assert!(text.contains("gone\" doesn't exists, skipping"), "{}", text);
+    }
+
+    #[test]
+    fn groups() {
+        let root = tempfile::tempdir().unwrap();
+        let mut entries = vec![];
+        let projects = [
+            ("web", "dist", serde_json::json!(["aggressive"])),
+            ("app", "target", serde_json::json!(["Conservative", "ci"])),
+            ("lib", "build", serde_json::Value::Null),
+        ];
+        for (project, pattern, tags) in projects {
+            fs::create_dir_all(root.path().join(project).join(pattern)).unwrap();
+            entries.push(serde_json::json!({
+                "destination": root.path().join(project),
+                "kind": "folder",
+                "patterns": [pattern],
+                "tags": tags,
+            }));
+        }
+        let config = root.path().join("config.json");
+        fs::write(&config, serde_json::Value::from(entries).to_string()).unwrap();
+        let config = config.to_str().unwrap();
+        let run = |args: &[&str]| {
+            let mut manager = Manager::new();
+            manager.set_output(Output::buffer().0);
+            let args = [&["neaten", "-c", config, "--dryrun"], args].concat();
+            manager.validate(Engine::try_parse_from(args)?)?;
+            let destinations: Vec<PathBuf> = manager
+                .execute()?
+                .destinations
+                .into_iter()
+                .map(|d| d.destination)
+                .collect();
+            crate::Result::Ok(destinations)
+        };
+
+        // no filter runs everything
+        assert_eq!(run(&[]).unwrap().len(), 3);
+        assert_eq!(
+            run(&["--group", "conservative"]).unwrap(),
+            [root.path().join("app")]
+        );
+        assert_eq!(
+            run(&["--group", "aggressive,ci"]).unwrap(),
+            [root.path().join("web"), root.path().join("app")]
+        );
+        let error = run(&["--group", "nightly"]).unwrap_err();
+        assert!(error.to_string().contains("no config is tagged nightly"));
+    }
+
+    #[test]
+    fn match_destination() {
+        let root = tempfile::tempdir().unwrap();
+        let build = root.path().join("build");
+        fs::create_dir_all(build.join("debug")).unwrap();
+        fs::write(build.join("debug").join("app.o"), "").unwrap();
+        let run = |match_destination: bool| {
+            let mut config = Config::new(&build, Kind::Folder, vec!["build"], None);
+            config.match_destination = match_destination;
+            let mut manager = Manager::new();
+            manager.set_output(Output::buffer().0);
+            manager.add(config);
+            manager.execute().unwrap()
+        };
+
+        // only what's in it is looked at
+        assert_eq!(run(false).removed, 0);
+        assert!(build.join("debug").join("app.o").exists());
+
+        let summary = run(true);
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.items[0].0, build);
+        assert!(!build.exists());
+        assert!(root.path().exists());
+
+        // never when it's the destination of another config too
+        fs::create_dir_all(build.join("debug")).unwrap();
+        let mut config = Config::new(&build, Kind::Folder, vec!["build", "debug"], None);
+        config.match_destination = true;
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.set_output(output);
+        manager.add(Config::new(&build, Kind::File, vec!["o"], None));
+        manager.add(config);
+        let mut summary = ExecutionSummary::new(true);
+        let config = &manager.configs[1];
+        helper::remove(&manager, config, &mut summary, helper::ToFree::default()).unwrap();
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.items[0].0, build.join("debug"));
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("Protected"), "{}", text);
+    }
+
+    #[test]
+    fn write_manifest() {
+        let root = tempfile::tempdir().unwrap();
+        let build = root.path().join("project").join("build");
+        fs::create_dir_all(&build).unwrap();
+        fs::write(build.join("out.o"), vec![0u8; 16]).unwrap();
+        let manifest = root.path().join("manifest.json");
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.manifest = Some(manifest.clone());
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["build"], None));
+        manager.execute().unwrap();
+
+        let written = Manifest::read(&manifest).unwrap();
+        assert_eq!(written.entries.len(), 1);
+        assert_eq!(written.entries[0].path, build);
+        assert_eq!(written.entries[0].size, 16);
+        assert_eq!(written.entries[0].status, manifest::Status::Removed);
+    }
+
+    #[test]
+    fn dryrun_manifest() {
+        let root = tempfile::tempdir().unwrap();
+        let build = root.path().join("project").join("build");
+        fs::create_dir_all(&build).unwrap();
+        fs::write(build.join("out.o"), vec![0u8; 16]).unwrap();
+        let manifest = root.path().join("manifest.json");
+
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.set_output(Output::buffer().0);
+        manager.manifest = Some(manifest.clone());
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["build"], None));
+        manager.execute().unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&manifest).unwrap()).unwrap();
+        assert_eq!(json["entries"][0]["path"], build.to_str().unwrap());
+        assert_eq!(json["entries"][0]["status"], "would_remove");
+        assert!(build.exists());
+
+        // reviewed, then replayed for real
+        let summary = manager.replay(&manifest, false).unwrap();
+        assert_eq!(summary.removed, 1);
+        assert!(!build.exists());
+    }
+
+    #[test]
+    fn resume_from_progress() {
+        let root = tempfile::tempdir().unwrap();
+        let data = root.path().join("data");
+        fs::create_dir(&data).unwrap();
+        let names = ["a.log", "b.log", "c.log", "d.log", "e.log"];
+        for name in names {
+            fs::write(data.join(name), "log").unwrap();
+        }
+        let progress = root.path().join("progress.jsonl");
+        let config = root.path().join("config.json");
+        let json =
+            serde_json::json!([{ "destination": data, "kind": "file", "patterns": ["log"] }]);
+        fs::write(&config, json.to_string()).unwrap();
+        let args = |flag: &'static str| {
+            let mut args = vec!["neaten", "-c", config.to_str().unwrap()];
+            args.extend([flag, progress.to_str().unwrap()]);
+            Engine::try_parse_from(args).unwrap()
+        };
+
+        // interrupted after the first removal, then killed in the middle of
+        // writing a line
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.validate(args("--progress")).unwrap();
+        manager.set_filesystem(Interrupting(manager.cancel_handle()));
+        let summary = manager.execute().unwrap();
+        assert!(summary.cancelled);
+        assert_eq!(summary.removed, 1);
+        let mut file = fs::OpenOptions::new().append(true).open(&progress).unwrap();
+        std::io::Write::write_all(&mut file, b"{\"path\":").unwrap();
+        let recorded = manifest::resume(&progress).unwrap();
+        assert_eq!((recorded.planned.len(), recorded.done.len()), (5, 1));
+
+        // the rest of the plan goes, without walking the tree again
+        fs::write(data.join("new.log"), "log").unwrap();
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.validate(args("--resume")).unwrap();
+        assert_eq!(manager.resumed.as_ref().map(Vec::len), Some(4));
+        assert_eq!(manager.execute().unwrap().removed, 4);
+        assert!(names.iter().all(|name| !data.join(name).exists()));
+        assert!(data.join("new.log").exists());
+        assert_eq!(manifest::resume(&progress).unwrap().done.len(), 5);
+
+        // a dry-run leaves the progress file alone
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        let mut engine = args("--progress");
+        engine.dryrun = true;
+        manager.validate(engine).unwrap();
+        manager.execute().unwrap();
+        assert_eq!(manifest::resume(&progress).unwrap().done.len(), 5);
+
+        // nor does failing to write it stop the run
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        manager.progress = Some(root.path().to_path_buf());
+        manager.add(Config::new(&data, Kind::File, vec!["log"], None));
+        assert_eq!(manager.execute().unwrap().removed, 1);
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("couldn't record progress"), "{}", text);
+    }
+
+    #[test]
+    fn replay_manifest() {
+        let root = tempfile::tempdir().unwrap();
+        let listed = root.path().join("a").join("build");
+        let unlisted = root.path().join("b").join("build");
+        let file = root.path().join("notes.log");
+        fs::create_dir_all(&listed).unwrap();
+        fs::create_dir_all(&unlisted).unwrap();
+        fs::write(&file, "log").unwrap();
+
+        let manifest = root.path().join("manifest.json");
+        let json = serde_json::json!({ "entries": [
+            { "path": listed, "size": 0 },
+            { "path": file, "size": 3 },
+            { "path": root.path().join("gone"), "size": 0 },
+        ]});
+        fs::write(&manifest, json.to_string()).unwrap();
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.allow_unsigned_manifest = true;
+
+        // dry-run keeps everything
+        let summary = manager.replay(&manifest, true).unwrap();
+        assert_eq!(summary.removed, 2);
+        assert!(listed.exists());
+        assert!(file.exists());
+
+        let summary = manager.replay(&manifest, false).unwrap();
+        assert_eq!(summary.removed, 2);
+        assert_eq!(summary.bytes, 3);
+        assert!(!listed.exists());
+        assert!(!file.exists());
+        assert!(unlisted.exists());
+    }
+
+    #[test]
+    fn manifest_checksum() {
+        let root = tempfile::tempdir().unwrap();
+        let build = root.path().join("build");
+        let kept = root.path().join("src");
+        fs::create_dir(&build).unwrap();
+        fs::create_dir(&kept).unwrap();
+        let manifest = root.path().join("manifest.json");
+
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.set_output(Output::buffer().0);
+        manager.manifest = Some(manifest.clone());
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["build"], None));
+        manager.execute().unwrap();
+
+        // a checksum of 64 hex digits, which the unchanged manifest passes
+        let text = fs::read_to_string(&manifest).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["checksum"].as_str().unwrap().len(), 64);
+        let read = Manifest::read(&manifest).unwrap();
+        assert_eq!(read.checksum.as_deref(), json["checksum"].as_str());
+
+        // pointed at something else, nothing gets replayed
+        let build_json = serde_json::to_string(&build).unwrap();
+        let kept_json = serde_json::to_string(&kept).unwrap();
+        fs::write(&manifest, text.replace(&build_json, &kept_json)).unwrap();
+        let error = manager.replay(&manifest, false).unwrap_err();
+        assert!(error.to_string().contains("its checksum doesn't match"));
+        assert!(build.exists());
+        assert!(kept.exists());
+
+        // and without its checksum either
+        let mut stripped = json.clone();
+        stripped.as_object_mut().unwrap().remove("checksum");
+        fs::write(&manifest, stripped.to_string()).unwrap();
+        let error = manager.replay(&manifest, false).unwrap_err();
+        assert!(error.to_string().contains("its checksum is missing"));
+        assert!(build.exists());
+
+        // nor without its version, which reads as an old manifest...
+        stripped.as_object_mut().unwrap().remove("version");
+        fs::write(&manifest, stripped.to_string()).unwrap();
+        assert_eq!(Manifest::read(&manifest).unwrap().version, 0);
+        assert_eq!(json["version"], Manifest::VERSION);
+        let error = manager.replay(&manifest, false).unwrap_err();
+        assert!(error.to_string().contains("has no checksum"), "{}", error);
+        assert!(build.exists());
+
+        // ...only replayed when asked to, with a warning
+        let (output, buffer) = Output::buffer();
+        manager.set_output(output);
+        manager.allow_unsigned_manifest = true;
+        assert_eq!(manager.replay(&manifest, false).unwrap().removed, 1);
+        assert!(!build.exists());
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(
+            text.contains("has no checksum, replayed unverified"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn config_hash() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("build")).unwrap();
+        let manifest = root.path().join("manifest.json");
+        let manager = |patterns: Vec<&str>| {
+            let mut manager = Manager::new();
+            manager.dryrun = true;
+            manager.set_output(Output::buffer().0);
+            manager.manifest = Some(manifest.clone());
+            manager.add(Config::new(root.path(), Kind::Folder, patterns, None));
+            manager
+        };
+
+        let hash = manager(vec!["build"]).config_hash().unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(manager(vec!["build"]).config_hash().unwrap(), hash);
+        assert_ne!(manager(vec!["build", "dist"]).config_hash().unwrap(), hash);
+
+        // carried by the summary and the manifest of a run
+        let summary = manager(vec!["build"]).execute().unwrap();
+        assert_eq!(summary.config_hash.as_ref(), Some(&hash));
+        let read = Manifest::read(&manifest).unwrap();
+        assert_eq!(read.config_hash, Some(hash));
+    }
+
+    #[test]
+    fn mixed_pattern_kinds() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("app").join("target")).unwrap();
+        fs::create_dir_all(root.path().join("app").join("tmp")).unwrap();
+        fs::write(root.path().join("app").join("scratch.tmp"), "").unwrap();
+        fs::write(root.path().join("app").join("target.rs"), "").unwrap();
+
+        let mut config = Config::new(root.path(), Kind::Folder, vec!["target"], None);
+        config.patterns.push(Pattern {
+            pattern: String::from("tmp"),
+            kind: Some(Kind::File),
+        });
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.add(config);
+        let summary = manager.execute().unwrap();
+
+        // a folder named `target` and a file ending in `.tmp`, nothing else
+        assert_eq!(summary.removed, 2);
+        assert!(!root.path().join("app").join("target").exists());
+        assert!(!root.path().join("app").join("scratch.tmp").exists());
+        assert!(root.path().join("app").join("tmp").exists());
+        assert!(root.path().join("app").join("target.rs").exists());
+    }
+
+    #[test]
+    fn file_pattern_prefixes() {
+        let root = tempfile::tempdir().unwrap();
+        for name in ["cache", "cache.db", "Dockerfile", "debug.log", "notes.txt"] {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+        fs::create_dir(root.path().join("cached")).unwrap();
+        let decide = |name: &str, patterns: &[&str]| {
+            let patterns: Vec<Pattern> = patterns.iter().map(|p| Pattern::from(*p)).collect();
+            helper::decide(root.path().join(name), &patterns, &Kind::File, false)
+        };
+        let removed =
+            |name: &str, patterns: &[&str]| matches!(decide(name, patterns), Decision::Remove(_));
+
+        // unprefixed and `ext:` match the extension
+        assert!(removed("debug.log", &["log"]));
+        assert!(removed("debug.log", &["ext:LOG"]));
+        assert!(!removed("notes.txt", &["ext:log"]));
+        assert!(!removed("cache", &["cache"]));
+        // `stem:` the name without its extension
+        assert!(removed("cache", &["stem:cache"]));
+        assert!(removed("cache.db", &["stem:cache"]));
+        assert!(!removed("cached", &["stem:cache"]));
+        // `name:` the whole name
+        assert!(removed("Dockerfile", &["name:dockerfile"]));
+        assert!(removed("cache", &["name:cache"]));
+        assert!(!removed("cache.db", &["name:cache"]));
+
+        assert_eq!(
+            decide("cache.db", &["name:cache"]),
+            Decision::Keep(Reason::Name("cache.db".to_string()))
+        );
+        assert_eq!(
+            decide("notes.txt", &["ext:log"]),
+            Decision::Keep(Reason::Extension("txt".to_string()))
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn match_xattr() {
+        let root = tempfile::tempdir().unwrap();
+        let set = |name: &str, value: &str| {
+            let path = root.path().join(name);
+            fs::write(&path, "").unwrap();
+            xattr::set(&path, "user.neaten", value.as_bytes()).is_ok()
+        };
+        // not every filesystem takes user attributes
+        if !set("tagged.tmp", "throwaway") || !set("other.tmp", "keep") {
+            return;
+        }
+        fs::write(root.path().join("plain.tmp"), "").unwrap();
+
+        let mut config = Config::new(root.path(), Kind::File, vec!["tmp"], None);
+        config.xattr_name = Some(String::from("user.neaten"));
+        let judge = |name: &str, config: &Config| helper::judge(&root.path().join(name), config);
+        assert!(matches!(judge("tagged.tmp", &config), Decision::Remove(_)));
+        assert!(matches!(judge("other.tmp", &config), Decision::Remove(_)));
+        assert_eq!(
+            judge("plain.tmp", &config),
+            Decision::Keep(Reason::Xattr {
+                name: String::from("user.neaten"),
+                value: None
+            })
+        );
+
+        config.xattr_value = Some(String::from("throwaway"));
+        assert!(matches!(judge("tagged.tmp", &config), Decision::Remove(_)));
+        assert!(matches!(judge("other.tmp", &config), Decision::Keep(_)));
+
+        // still only what the patterns match
+        config.patterns = vec![Pattern::from("log")];
+        assert!(matches!(judge("tagged.tmp", &config), Decision::Keep(_)));
+    }
+
+    #[test]
+    fn dedup_content() {
+        const HOUR: Duration = Duration::from_secs(60 * 60);
+        let root = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        let files = [
+            ("old.txt", "hello world", 3),
+            ("nested/new.txt", "hello world", 1),
+            // same size, one byte off, and another size
+            ("near.txt", "hello worle", 2),
+            ("longer.txt", "hello world!", 2),
+        ];
+        let create = || {
+            for (name, content, hours_ago) in files {
+                let path = root.path().join(name);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, content).unwrap();
+                let file = fs::File::options().write(true).open(&path).unwrap();
+                file.set_modified(now - hours_ago * HOUR).unwrap();
+            }
+        };
+
+        let mut config = Config::new(root.path(), Kind::File, vec!["txt"], None);
+        config.dedup_content = Some(KeepCopy::Oldest);
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.add(config);
+
+        create();
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.bytes, 11);
+        assert!(!root.path().join("nested").join("new.txt").exists());
+        for name in ["old.txt", "near.txt", "longer.txt"] {
+            assert!(root.path().join(name).exists());
+        }
+
+        manager.configs[0].dedup_content = Some(KeepCopy::Newest);
+        create();
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.items, vec![(root.path().join("old.txt"), 11)]);
+        assert!(root.path().join("nested").join("new.txt").exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn match_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let modes = [
+            ("writable.sh", 0o666),
+            ("executable.sh", 0o755),
+            ("private.sh", 0o600),
+        ];
+        for (name, mode) in modes {
+            let path = root.path().join(name);
+            fs::write(&path, "#!/bin/sh").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        }
+
+        // world-writable
+        let mut config = Config::new(root.path(), Kind::File, vec!["sh"], None);
+        config.mode_mask = Some(0o002);
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.add(config);
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 1);
+        assert!(!root.path().join("writable.sh").exists());
+        assert!(root.path().join("executable.sh").exists());
+        assert!(root.path().join("private.sh").exists());
+
+        // owner-executable but not group-writable
+        let mut config = Config::new(root.path(), Kind::File, vec!["sh"], None);
+        config.mode_mask = Some(0o120);
+        config.mode_value = Some(0o100);
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.add(config);
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 1);
+        assert!(!root.path().join("executable.sh").exists());
+        assert!(root.path().join("private.sh").exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn match_owner() {
+        use std::os::unix::fs::MetadataExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let file = root.path().join("cache.tmp");
+        fs::write(&file, "").unwrap();
+        let uid = fs::metadata(&file).unwrap().uid();
+
+        // somebody else's
+        let mut config = Config::new(root.path(), Kind::File, vec!["tmp"], None);
+        config.owner = Some((uid + 1).to_string());
+        assert!(matches!(
+            helper::judge(&file, &config),
+            Decision::Keep(Reason::Owner { .. })
+        ));
+
+        // ours
+        config.owner = Some(uid.to_string());
+        assert!(matches!(helper::judge(&file, &config), Decision::Remove(_)));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn resolve_owner() {
+        assert_eq!(helper::uid_of("0"), Some(0));
+        assert_eq!(helper::uid_of("root"), Some(0));
+        assert_eq!(helper::uid_of("no-such-user-for-neaten"), None);
+    }
+
+    /// Refuses to remove (or move) anything named `locked.log`.
+    struct Locked;
+
+    impl FileSystem for Locked {
+        fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+            if path.ends_with("locked.log") {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    "locked",
+                ))
+            } else {
+                fs::remove_file(path)
+            }
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+            if from.ends_with("locked.log") {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    "locked",
+                ))
+            } else {
+                fs::rename(from, to)
+            }
+        }
+    }
+
+    fn locked_tree() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        for name in ["a.log", "locked.log", "z.log"] {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn manifest_on_failure() {
+        let root = locked_tree();
+        let held = tempfile::tempdir().unwrap();
+        let manifest = held.path().join("manifest.json");
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Locked);
+        manager.error_policy = ErrorPolicy::FailFast;
+        manager.manifest = Some(manifest.clone());
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+
+        // stopped at `locked.log`, with whatever went before it on record
+        assert!(manager.execute().is_err());
+        let listed: Vec<PathBuf> = Manifest::read(&manifest)
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        let gone: Vec<PathBuf> = ["a.log", "z.log"]
+            .iter()
+            .map(|name| root.path().join(name))
+            .filter(|path| !path.exists())
+            .collect();
+        assert_eq!(listed.len(), gone.len());
+        assert!(gone.iter().all(|path| listed.contains(path)));
+
+        // and moved there before it, put back
+        for name in ["a.log", "z.log"] {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+        manager.quarantine = Some(held.path().join("quarantine"));
+        manager.rollback_on_error = true;
+        assert!(manager.execute().is_err());
+        for name in ["a.log", "locked.log", "z.log"] {
+            assert!(root.path().join(name).exists());
+        }
+        // and no longer on record as gone
+        assert!(Manifest::read(&manifest).unwrap().entries.is_empty());
+    }
+
+    /// Refuses the first hard link and rename asked for.
+    #[derive(Default)]
+    struct Refusing {
+        links: AtomicUsize,
+        renames: AtomicUsize,
+    }
+
+    impl FileSystem for Refusing {
+        fn hard_link(&self, original: &Path, link: &Path) -> std::io::Result<()> {
+            match self.links.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(std::io::ErrorKind::PermissionDenied.into()),
+                _ => fs::hard_link(original, link),
+            }
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+            match self.renames.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(std::io::ErrorKind::PermissionDenied.into()),
+                _ => fs::rename(from, to),
+            }
+        }
+    }
+
+    #[test]
+    fn quarantine_policies() {
+        let root = tempfile::tempdir().unwrap();
+        let held = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("a.log"), "").unwrap();
+
+        // a refusal fails the move, unless the policy fixes it
+        for (policy, moved) in [
+            (PermissionPolicy::Abort, false),
+            (PermissionPolicy::Fix, true),
+        ] {
+            let mut manager = Manager::new();
+            manager.set_output(Output::buffer().0);
+            manager.set_filesystem(Refusing::default());
+            manager.quarantine = Some(held.path().join("quarantine"));
+            manager.on_permission_denied = policy;
+            manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+            let summary = manager.execute().unwrap();
+            assert_eq!(summary.errors.is_empty(), moved);
+            assert_eq!(root.path().join("a.log").exists(), !moved);
+        }
+
+        // and a move taking too long is given up on like a removal
+        fs::write(root.path().join("slow.log"), "").unwrap();
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Slow);
+        manager.quarantine = Some(held.path().join("quarantine"));
+        manager.item_timeout = Some(Duration::from_millis(50));
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+        let summary = manager.execute().unwrap().to_string();
+        assert!(summary.contains("timed out"), "{}", summary);
+    }
+
+    #[test]
+    fn quarantine_and_rollback() {
+        let root = locked_tree();
+        let held = tempfile::tempdir().unwrap();
+        let quarantine = held.path().join("quarantine");
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Locked);
+        manager.quarantine = Some(quarantine.clone());
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+
+        // moved rather than removed, under their own path
+        let relative = |name: &str| -> PathBuf {
+            root.path()
+                .join(name)
+                .components()
+                .filter(|c| matches!(c, path::Component::Normal(_)))
+                .collect()
+        };
+        assert_eq!(manager.execute().unwrap().errors.len(), 1);
+        for name in ["a.log", "z.log"] {
+            assert!(!root.path().join(name).exists());
+            assert!(quarantine.join(relative(name)).exists());
+        }
+        assert!(root.path().join("locked.log").exists());
+
+        // a failed run puts back what it moved
+        for name in ["a.log", "z.log"] {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+        manager.rollback_on_error = true;
+        let (output, buffer) = Output::buffer();
+        manager.set_output(output);
+        assert_eq!(manager.execute().unwrap().errors.len(), 1);
+        for name in ["a.log", "locked.log", "z.log"] {
+            assert!(root.path().join(name).exists());
+        }
+        // next to the ones moved by the first run
+        assert!(
+            !quarantine
+                .join(format!("{}.1", relative("a.log").display()))
+                .exists()
+        );
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains(&format!(
+            "Restored\u{1b}[0m {:?}",
+            root.path().join("a.log")
+        )));
+
+        // and a run going well keeps them in quarantine
+        fs::remove_file(root.path().join("locked.log")).unwrap();
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.quarantined.len(), 2);
+        assert!(
+            quarantine
+                .join(format!("{}.1", relative("a.log").display()))
+                .exists()
+        );
+    }
+
+    /// Every quarantine lands on another device.
+    struct CrossDevice;
+
+    impl FileSystem for CrossDevice {
+        fn rename(&self, _: &Path, _: &Path) -> std::io::Result<()> {
+            Err(std::io::ErrorKind::CrossesDevices.into())
+        }
+
+        fn hard_link(&self, _: &Path, _: &Path) -> std::io::Result<()> {
+            Err(std::io::ErrorKind::CrossesDevices.into())
+        }
+    }
+
+    /// Anything under a `mnt` folder is mounted from another device.
+    struct Mounted;
+
+    impl FileSystem for Mounted {
+        fn device(&self, path: &Path) -> Option<u64> {
+            Some(match path.iter().any(|name| name == "mnt") {
+                true => 2,
+                false => 1,
+            })
+        }
+    }
+
+    #[test]
+    fn one_filesystem() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("sub")).unwrap();
+        fs::create_dir_all(root.path().join("mnt").join("cache")).unwrap();
+        for name in ["a.log", "sub/b.log", "mnt/c.log", "mnt/cache/d.log"] {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+        let run = |one_filesystem: bool| {
+            let (output, buffer) = Output::buffer();
+            let mut manager = Manager::new();
+            manager.dryrun = true;
+            manager.explain = true;
+            manager.set_output(output);
+            manager.set_filesystem(Mounted);
+            let mut files = Config::new(root.path(), Kind::File, vec!["log"], None);
+            files.one_filesystem = one_filesystem;
+            let mut folders = Config::new(root.path(), Kind::Folder, vec!["mnt"], None);
+            folders.one_filesystem = one_filesystem;
+            manager.add(files);
+            manager.add(folders);
+            let summary = manager.execute().unwrap();
+            let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+            (summary.removed, text)
+        };
+
+        let (removed, text) = run(true);
+        assert_eq!(removed, 2);
+        assert!(text.contains("mnt: on another filesystem"), "{}", text);
+        assert!(!text.contains("c.log"), "{}", text);
+
+        // the mount point goes too, with all it holds
+        let (removed, _) = run(false);
+        assert_eq!(removed, 5);
+
+        // a matched folder goes without what's mounted below it
+        let build = root.path().join("build");
+        fs::create_dir_all(build.join("mnt")).unwrap();
+        fs::write(build.join("a.o"), "").unwrap();
+        fs::write(build.join("mnt").join("data"), "").unwrap();
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Mounted);
+        let mut config = Config::new(root.path(), Kind::Folder, vec!["build"], None);
+        config.one_filesystem = true;
+        manager.add(config);
+        let summary = manager.execute().unwrap().to_string();
+        assert!(summary.contains("on another filesystem"), "{}", summary);
+        assert!(!build.join("a.o").exists());
+        assert!(build.join("mnt").join("data").exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn quarantine_links() {
+        use std::os::unix::fs::MetadataExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let quarantine = root.path().join("quarantine");
+        let project = root.path().join("project");
+        fs::create_dir_all(project.join("cache")).unwrap();
+        fs::write(project.join("debug.log"), "log").unwrap();
+        fs::write(project.join("cache").join("blob"), "blob").unwrap();
+        let inode = fs::metadata(project.join("debug.log")).unwrap().ino();
+        let cached = fs::metadata(project.join("cache").join("blob"))
+            .unwrap()
+            .ino();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        manager.quarantine = Some(quarantine.clone());
+        manager.add(Config::new(&project, Kind::File, vec!["log"], None));
+        manager.add(Config::new(&project, Kind::Folder, vec!["cache"], None));
+        let summary = manager.execute().unwrap();
+
+        // the same inodes, nothing copied
+        let held = |index: usize| summary.quarantined[index].1.clone();
+        assert_eq!(fs::metadata(held(0)).unwrap().ino(), inode);
+        assert_eq!(fs::metadata(held(1).join("blob")).unwrap().ino(), cached);
+        assert!(!project.join("debug.log").exists());
+        assert!(!project.join("cache").exists());
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("hard-linked"), "{}", text);
+        assert!(text.contains("renamed"), "{}", text);
+    }
+
+    #[test]
+    fn quarantine_across_devices() {
+        let root = tempfile::tempdir().unwrap();
+        let quarantine = root.path().join("quarantine");
+        let project = root.path().join("project");
+        fs::create_dir_all(project.join("cache").join("nested")).unwrap();
+        fs::write(project.join("debug.log"), "log").unwrap();
+        fs::write(project.join("cache").join("nested").join("blob"), "blob").unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        manager.set_filesystem(CrossDevice);
+        manager.quarantine = Some(quarantine.clone());
+        manager.add(Config::new(&project, Kind::File, vec!["log"], None));
+        manager.add(Config::new(&project, Kind::Folder, vec!["cache"], None));
+        let summary = manager.execute().unwrap();
+
+        // copied over, then removed
+        let held = |index: usize| summary.quarantined[index].1.clone();
+        assert_eq!(fs::read_to_string(held(0)).unwrap(), "log");
+        let blob = held(1).join("nested").join("blob");
+        assert_eq!(fs::read_to_string(blob).unwrap(), "blob");
+        assert!(!project.join("debug.log").exists());
+        assert!(!project.join("cache").exists());
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert_eq!(text.matches("copied").count(), 2, "{}", text);
+    }
+
+    /// Another device, where `locked.log` can't be removed.
+    struct LockedAcross;
+
+    impl FileSystem for LockedAcross {
+        fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+            Locked.remove_file(path)
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+            CrossDevice.rename(from, to)
+        }
+
+        fn hard_link(&self, original: &Path, link: &Path) -> std::io::Result<()> {
+            CrossDevice.hard_link(original, link)
+        }
+    }
+
+    #[test]
+    fn rollback_across_devices() {
+        let root = locked_tree();
+        fs::write(root.path().join("a.log"), "a").unwrap();
+        let held = tempfile::tempdir().unwrap();
+        let quarantine = held.path().join("quarantine");
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(LockedAcross);
+        manager.quarantine = Some(quarantine.clone());
+        manager.rollback_on_error = true;
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+        assert_eq!(manager.execute().unwrap().errors.len(), 1);
+
+        // copied back, as they can't be renamed either
+        assert_eq!(fs::read_to_string(root.path().join("a.log")).unwrap(), "a");
+        for name in ["locked.log", "z.log"] {
+            assert!(root.path().join(name).exists());
+        }
+        // and gone from quarantine
+        for name in ["a.log", "z.log"] {
+            let relative: PathBuf = root
+                .path()
+                .join(name)
+                .components()
+                .filter(|c| matches!(c, path::Component::Normal(_)))
+                .collect();
+            assert!(!quarantine.join(relative).exists());
+        }
+    }
+
+    /// Refuses to remove anything, counting the attempts.
+    #[derive(Default)]
+    struct Failing {
+        attempts: Arc<AtomicUsize>,
+    }
+
+    impl FileSystem for Failing {
+        fn remove_file(&self, _: &Path) -> std::io::Result<()> {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            Err(std::io::Error::other("failing"))
+        }
+    }
+
+    /// Hangs on `slow.log` far longer than any test timeout.
+    struct Slow;
+
+    impl FileSystem for Slow {
+        fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+            if path.ends_with("slow.log") {
+                std::thread::sleep(Duration::from_secs(5));
+                Ok(())
+            } else {
+                fs::remove_file(path)
+            }
+        }
+    }
+
+    /// Takes a while over each file, noting how many it had at once.
+    #[derive(Default)]
+    struct Overlapping {
+        running: Arc<AtomicUsize>,
+        most: Arc<AtomicUsize>,
+    }
+
+    impl FileSystem for Overlapping {
+        fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most.fetch_max(running, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(100));
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            fs::remove_file(path)
+        }
+    }
+
+    #[test]
+    fn parallel_configs() {
+        let root = tempfile::tempdir().unwrap();
+        let run = |parallel: usize| {
+            let (first, second) = (root.path().join("first"), root.path().join("second"));
+            for destination in [&first, &second] {
+                fs::create_dir_all(destination).unwrap();
+                fs::write(destination.join("a.log"), "abc").unwrap();
+                fs::write(destination.join("b.log"), "de").unwrap();
+            }
+            let fs = Overlapping::default();
+            let most = fs.most.clone();
+            let mut manager = Manager::new();
+            manager.set_output(Output::buffer().0);
+            manager.set_filesystem(fs);
+            manager.parallel = parallel;
+            manager.add(Config::new(&first, Kind::File, vec!["log"], None));
+            manager.add(Config::new(&second, Kind::File, vec!["log"], None));
+            (manager.execute().unwrap(), most.load(Ordering::SeqCst))
+        };
+
+        let (summary, most) = run(1);
+        assert_eq!((summary.removed, most), (4, 1));
+
+        // both destinations at once, with the same totals
+        let (parallel, most) = run(2);
+        assert_eq!(most, 2);
+        assert_eq!(parallel.removed, 4);
+        assert_eq!(parallel.bytes, 10);
+        assert_eq!(parallel.destinations, summary.destinations);
+        assert!(parallel.errors.is_empty());
+        assert!(!root.path().join("first").join("a.log").exists());
+        assert!(!root.path().join("second").join("b.log").exists());
+    }
+
+    /// A volume of `total` bytes holding only what's below `root`.
+    struct Usage {
+        root: PathBuf,
+        total: u64,
+    }
+
+    impl FileSystem for Usage {
+        fn space(&self, _: &Path) -> std::io::Result<(u64, u64)> {
+            Ok((self.total, self.total - helper::size(&self.root, false)))
+        }
+    }
+
+    #[test]
+    fn parallel_water_marks() {
+        let root = tempfile::tempdir().unwrap();
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.parallel = 2;
+        manager.set_filesystem(Usage {
+            root: root.path().to_path_buf(),
+            total: 1000,
+        });
+        for project in ["web", "app"] {
+            let destination = root.path().join(project);
+            fs::create_dir(&destination).unwrap();
+            for name in ["a.log", "b.log", "c.log", "d.log"] {
+                fs::write(destination.join(name), [0u8; 100]).unwrap();
+            }
+            let mut config = Config::new(destination, Kind::File, vec!["log"], None);
+            config.high_water = Some(75);
+            config.low_water = Some(60);
+            manager.add(config);
+        }
+
+        // 80% full: the first frees 200 bytes, which leaves nothing to the
+        // second
+        let summary = manager.execute().unwrap();
+        assert_eq!((summary.removed, summary.bytes), (2, 200));
+    }
+
+    #[test]
+    fn parallel_fail_fast() {
+        let root = tempfile::tempdir().unwrap();
+        let sub = root.path().join("a").join("sub");
+        fs::create_dir_all(&sub).unwrap();
+        fs::write(root.path().join("a").join("a.log"), "").unwrap();
+        fs::write(sub.join("b.log"), "").unwrap();
+        let failing = Failing::default();
+        let attempts = failing.attempts.clone();
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(failing);
+        manager.parallel = 2;
+        manager.error_policy = ErrorPolicy::FailFast;
+        manager.add(Config::new(
+            root.path().join("a"),
+            Kind::File,
+            vec!["log"],
+            None,
+        ));
+        manager.add(Config::new(&sub, Kind::File, vec!["log"], None));
+
+        // the nested one waits for the first, which stops the run
+        assert!(manager.execute().is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn parallel_groups() {
+        let config = |destination: &str| Config::new(destination, Kind::File, vec!["log"], None);
+        let configs = [
+            config("/pool/a"),
+            config("/pool/b"),
+            config("/pool/a/sub"),
+            config("/pool/c"),
+        ];
+        let jobs: Vec<helper::Job> = configs
+            .iter()
+            .map(|config| helper::Job {
+                config,
+                to_free: helper::ToFree::default(),
+                fingerprint: config.fingerprint(),
+                first_run: false,
+            })
+            .collect();
+
+        // a destination inside another runs after it
+        let mut manager = Manager::new();
+        let mut groups = helper::groups(&manager, &jobs);
+        groups.sort();
+        assert_eq!(groups, vec![vec![0, 2], vec![1], vec![3]]);
+
+        // none of those exist, so none can be told apart
+        manager.one_per_device = true;
+        assert_eq!(helper::groups(&manager, &jobs), vec![vec![0, 1, 2, 3]]);
+    }
+
+    #[test]
+    fn item_timeout() {
+        let root = tempfile::tempdir().unwrap();
+        for name in ["a.log", "slow.log", "z.log"] {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+
+        let mut manager = Manager::new();
+        manager.item_timeout = Some(Duration::from_millis(50));
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Slow);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.errors.len(), 1);
+        assert!(
+            summary
+                .to_string()
+                .contains("slow.log\": timed out after 0.05s")
+        );
+        // abandoned, the others removed all the same
+        assert!(root.path().join("slow.log").exists());
+        assert!(!root.path().join("a.log").exists());
+        assert!(!root.path().join("z.log").exists());
+    }
+
+    /// Every file "open.log" is held by some other process.
+    struct Busy;
+
+    impl FileSystem for Busy {
+        fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+            match path.ends_with("open.log") {
+                true => Err(std::io::ErrorKind::ResourceBusy.into()),
+                false => fs::remove_file(path),
+            }
+        }
+    }
+
+    #[test]
+    fn skip_in_use() {
+        let root = tempfile::tempdir().unwrap();
+        let run = |skip_in_use: bool| {
+            for name in ["open.log", "z.log"] {
+                fs::write(root.path().join(name), "").unwrap();
+            }
+            let (output, buffer) = Output::buffer();
+            let mut manager = Manager::new();
+            manager.skip_in_use = skip_in_use;
+            manager.set_output(output);
+            manager.set_filesystem(Busy);
+            manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+            let result = manager.execute();
+            let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+            (result, text)
+        };
+
+        let (result, _) = run(false);
+        assert!(result.unwrap().errors[0].0.ends_with("open.log"));
+
+        let (result, text) = run(true);
+        let summary = result.unwrap();
+        assert_eq!(summary.removed, 1);
+        assert!(summary.errors.is_empty());
+        assert!(text.contains("\u{1b}[33mIn use\u{1b}[0m"));
+        assert!(root.path().join("open.log").exists());
+        assert!(!root.path().join("z.log").exists());
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn skip_held_open() {
+        let root = tempfile::tempdir().unwrap();
+        let file = root.path().join("app.log");
+        fs::write(&file, "").unwrap();
+        let held = fs::File::open(&file).unwrap();
+
+        let mut manager = Manager::new();
+        manager.skip_in_use = true;
+        manager.set_output(Output::buffer().0);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 0);
+        assert!(summary.errors.is_empty());
+        assert!(file.exists());
+
+        drop(held);
+        assert_eq!(manager.execute().unwrap().removed, 1);
+    }
+
+    #[test]
+    fn error_policy_fail_fast() {
+        let root = locked_tree();
+        let failing = Failing::default();
+        let attempts = failing.attempts.clone();
+
+        let mut manager = Manager::new();
+        manager.error_policy = ErrorPolicy::FailFast;
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(failing);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+
+        let error = manager.execute().unwrap_err();
+        assert!(error.to_string().contains("couldn't remove"));
+        // stopped at the first failure
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn error_policy_keep_going() {
+        let root = locked_tree();
+        let mut manager = Manager::new();
+        manager.error_policy = ErrorPolicy::KeepGoing;
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Locked);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.errors.len(), 1);
+        assert!(summary.errors[0].0.ends_with("locked.log"));
+        assert_eq!(manager.exit_code(&summary), AppError::EXIT_CODE);
+        // everything else went
+        assert!(!root.path().join("a.log").exists());
+        assert!(!root.path().join("z.log").exists());
+        assert!(root.path().join("locked.log").exists());
+    }
+
+    #[test]
+    fn sample() {
+        let root = tempfile::tempdir().unwrap();
+        for i in 0..1000 {
+            fs::write(root.path().join(format!("{}.log", i)), "x").unwrap();
+        }
+        let run = |seed: u64, dryrun: bool| {
+            let mut manager = Manager::new();
+            manager.dryrun = dryrun;
+            manager.sample = Some(10.0);
+            manager.seed = seed;
+            manager.set_output(Output::buffer().0);
+            manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+            manager.execute().unwrap()
+        };
+
+        let first = run(42, true);
+        let sample = first.sample.clone().unwrap();
+        assert_eq!((sample.seed, sample.matched), (42, 1000));
+        assert_eq!(sample.sampled, first.removed);
+        assert!((50..150).contains(&first.removed), "{}", first.removed);
+        assert!(first.to_string().contains(&format!(
+            "Sampled\u{1b}[0m {} of 1000 match(es) (seed 42)",
+            first.removed
+        )));
+
+        // the same seed picks the same items, another seed others
+        assert_eq!(run(42, true).items, first.items);
+        assert_ne!(run(7, true).items, first.items);
+
+        let removed = run(42, false);
+        assert_eq!(removed.items, first.items);
+        assert!(first.items.iter().all(|(path, _)| !path.exists()));
+        assert_eq!(
+            fs::read_dir(root.path()).unwrap().count(),
+            1000 - first.removed
+        );
+
+        let args = vec!["neaten", "-c", "config.json", "--sample", "0"];
+        let engine = Engine::try_parse_from(args).unwrap();
+        assert!(Manager::new().validate(engine).is_err());
+    }
+
+    #[test]
+    fn sample_folders() {
+        let root = tempfile::tempdir().unwrap();
+        for i in 0..200 {
+            fs::create_dir_all(root.path().join(format!("p{}/build/build", i))).unwrap();
+        }
+        let mut manager = Manager::new();
+        manager.sample = Some(50.0);
+        manager.set_output(Output::buffer().0);
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["build"], None));
+        let summary = manager.execute().unwrap();
+
+        // a folder left out isn't looked into, its own matches not drawn
+        let sample = summary.sample.clone().unwrap();
+        assert_eq!(sample.matched, 200);
+        assert_eq!(sample.sampled, summary.removed);
+        assert!((50..150).contains(&summary.removed), "{}", summary.removed);
+        for i in 0..200 {
+            let build = root.path().join(format!("p{}/build", i));
+            let removed = summary.items.iter().any(|(item, _)| *item == build);
+            assert_eq!(build.exists(), !removed);
+            assert_eq!(build.join("build").exists(), !removed);
+        }
+    }
+
+    #[test]
+    fn failures_follow_verbosity() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("a.log"), "").unwrap();
+        let run = |verbosity: Verbosity| {
+            let (mut output, buffer) = Output::buffer();
+            output.set_verbosity(verbosity);
+            let mut manager = Manager::new();
+            manager.set_output(output);
+            manager.set_filesystem(Failing::default());
+            manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+            assert_eq!(manager.execute().unwrap().errors.len(), 1);
+            String::from_utf8(buffer.lock().unwrap().clone()).unwrap()
+        };
+
+        assert!(run(Verbosity::Normal).contains("Error: failing"));
+        assert_eq!(run(Verbosity::Quiet), "");
+    }
+
+    #[test]
+    fn max_errors() {
+        let root = tempfile::tempdir().unwrap();
+        for i in 0..10 {
+            fs::write(root.path().join(format!("{}.log", i)), "").unwrap();
+        }
+        let failing = Failing::default();
+        let attempts = failing.attempts.clone();
+
+        let mut manager = Manager::new();
+        manager.max_errors = Some(3);
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(failing);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+
+        let error = manager.execute().unwrap_err().to_string();
+        assert!(
+            error.contains("more than 3 item(s) couldn't be removed"),
+            "{}",
+            error
+        );
+        assert_eq!(error.matches(": failing").count(), 4, "{}", error);
+        // stopped once over the threshold
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+
+        // a later run starts counting over
+        manager.max_errors = Some(20);
+        assert_eq!(manager.execute().unwrap().errors.len(), 10);
+        assert_eq!(attempts.load(Ordering::SeqCst), 14);
+    }
+
+    #[test]
+    fn summary_per_destination() {
+        let root = tempfile::tempdir().unwrap();
+        let web = root.path().join("web");
+        let api = root.path().join("api");
+        fs::create_dir_all(web.join("node_modules")).unwrap();
+        fs::create_dir_all(web.join("dist")).unwrap();
+        fs::create_dir_all(api.join("target")).unwrap();
+        fs::write(web.join("node_modules").join("index.js"), vec![0u8; 300]).unwrap();
+        fs::write(web.join("dist").join("app.js"), vec![0u8; 20]).unwrap();
+        fs::write(api.join("target").join("api"), vec![0u8; 1000]).unwrap();
+
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.set_output(Output::buffer().0);
+        manager.add(Config::new(&web, Kind::Folder, vec!["node_modules"], None));
+        manager.add(Config::new(&api, Kind::Folder, vec!["target"], None));
+        // same destination again, accounted to the first entry
+        manager.add(Config::new(&web, Kind::Folder, vec!["dist"], None));
+        let summary = manager.execute().unwrap();
+
+        assert_eq!(summary.removed, 3);
+        assert_eq!(summary.bytes, 1320);
+        assert_eq!(summary.destinations.len(), 2);
+        assert_eq!(summary.destinations[0].destination, web);
+        assert_eq!(summary.destinations[0].removed, 2);
+        assert_eq!(summary.destinations[0].bytes, 320);
+        assert_eq!(summary.destinations[1].destination, api);
+        assert_eq!(summary.destinations[1].removed, 1);
+        assert_eq!(summary.destinations[1].bytes, 1000);
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["removed"], 3);
+        assert_eq!(json["destinations"][0]["removed"], 2);
+        assert_eq!(json["destinations"][0]["bytes"], 320);
+        assert_eq!(json["destinations"][1]["bytes"], 1000);
+
+        let text = summary.to_string();
+        assert!(text.contains("1 item(s), 1000 byte(s)"));
+        assert!(text.contains("2 item(s), 320 byte(s)"));
+    }
+
+    fn first_run_manager(state: &Path, answer: bool) -> (Manager, Arc<AtomicUsize>) {
+        let asked = Arc::new(AtomicUsize::new(0));
+        let counter = asked.clone();
+
+        let mut manager = Manager::new();
+        manager.confirm_first_run = true;
+        manager.set_state_dir(state);
+        manager.set_output(Output::buffer().0);
+        manager.set_prompt(Prompt::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            answer
+        }));
+        (manager, asked)
+    }
+
+    #[test]
+    fn due() {
+        const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+        let root = tempfile::tempdir().unwrap();
+        let state = tempfile::tempdir().unwrap();
+        let weekly = root.path().join("weekly");
+        let daily = root.path().join("daily");
+        let always = root.path().join("always");
+        for project in [&weekly, &daily, &always] {
+            fs::create_dir_all(project.join("build")).unwrap();
+        }
+
+        let mut configs = Vec::new();
+        for (project, interval) in [(&weekly, Some("7d")), (&daily, Some("1d")), (&always, None)] {
+            let mut config = Config::new(project, Kind::Folder, vec!["build"], None);
+            config.interval = interval.map(String::from);
+            configs.push(config);
+        }
+
+        // both ran two days ago
+        let two_days_ago = SystemTime::now() - 2 * DAY;
+        let mut last_runs = LastRuns::load(state.path()).unwrap();
+        for config in &configs[..2] {
+            last_runs.set(config.fingerprint(), two_days_ago).unwrap();
+        }
+
+        let mut manager = Manager::new();
+        manager.due = true;
+        manager.set_state_dir(state.path());
+        manager.set_output(Output::buffer().0);
+        manager.configs = configs;
+        let summary = manager.execute().unwrap();
+
+        assert_eq!(summary.removed, 2);
+        assert!(weekly.join("build").exists());
+        assert!(!daily.join("build").exists());
+        assert!(!always.join("build").exists());
+
+        // the run that happened moved the timestamp forward
+        let last_runs = LastRuns::load(state.path()).unwrap();
+        let weekly_run = last_runs.get(&manager.configs[0].fingerprint()).unwrap();
+        let daily_run = last_runs.get(&manager.configs[1].fingerprint()).unwrap();
+        assert!(weekly_run < SystemTime::now() - DAY);
+        assert!(daily_run > SystemTime::now() - DAY);
+
+        // not due again right away
+        fs::create_dir_all(daily.join("build")).unwrap();
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 0);
+        assert!(daily.join("build").exists());
+    }
+
+    #[test]
+    fn after() {
+        let root = tempfile::tempdir().unwrap();
+        let mut config = Config::new(root.path(), Kind::Folder, vec!["build"], None);
+        config.after = Some(String::from("echo cache emptied"));
+        let mut manager = Manager::new();
+        manager.add(config);
+        let run = |manager: &mut Manager| {
+            let (output, buffer) = Output::buffer();
+            manager.set_output(output);
+            let result = manager.execute();
+            let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+            (result, text)
+        };
+
+        // neither with nothing removed nor in dry-run
+        let (result, text) = run(&mut manager);
+        assert_eq!(result.unwrap().removed, 0);
+        assert!(!text.contains("After"));
+        fs::create_dir(root.path().join("build")).unwrap();
+        manager.dryrun = true;
+        let (result, text) = run(&mut manager);
+        assert_eq!(result.unwrap().removed, 1);
+        assert!(!text.contains("After"));
+
+        manager.dryrun = false;
+        let (result, text) = run(&mut manager);
+        assert_eq!(result.unwrap().removed, 1);
+        assert!(text.contains("\u{1b}[33mAfter\u{1b}[0m echo cache emptied..."));
+        assert!(text.contains("  cache emptied"));
+
+        // failing, it's a failed step of the run, not a failed item
+        fs::create_dir(root.path().join("build")).unwrap();
+        manager.configs[0].after = Some(String::from("exit 3"));
+        let (result, _) = run(&mut manager);
+        let summary = result.unwrap();
+        assert!(summary.errors.is_empty());
+        assert_eq!(
+            summary.failed_steps,
+            vec![(
+                root.path().to_path_buf(),
+                String::from("after command 'exit 3' exited with 3")
+            )]
+        );
+        assert!(summary.to_string().contains("Failed\u{1b}[0m 1 step(s)"));
+        assert_eq!(manager.exit_code(&summary), AppError::EXIT_CODE);
+        assert!(!root.path().join("build").exists());
+    }
+
+    #[test]
+    fn verbosity() {
+        let root = tempfile::tempdir().unwrap();
+        for name in ["build", "vendor", "src"] {
+            fs::create_dir(root.path().join(name)).unwrap();
+        }
+        let run = |verbosity: Verbosity| {
+            let (mut output, buffer) = Output::buffer();
+            output.set_verbosity(verbosity);
+            let mut manager = Manager::new();
+            manager.dryrun = true;
+            manager.set_output(output);
+            manager.add(Config::new(
+                root.path(),
+                Kind::Folder,
+                vec!["build"],
+                Some(vec!["vendor"]),
+            ));
+            manager.execute().unwrap();
+            String::from_utf8(buffer.lock().unwrap().clone()).unwrap()
+        };
+
+        assert!(run(Verbosity::Quiet).is_empty());
+
+        let text = run(Verbosity::Normal);
+        assert!(text.contains("Removing"));
+        assert!(!text.contains("Exclude"));
+        assert!(!text.contains("name 'src' not in patterns"));
+
+        let text = run(Verbosity::Verbose);
+        assert!(text.contains("Removing"));
+        assert!(text.contains("\u{1b}[33mExclude\u{1b}[0m"));
+        assert!(!text.contains("name 'src' not in patterns"));
+
+        let text = run(Verbosity::Debug);
+        assert!(text.contains("Removing"));
+        assert!(text.contains("\u{1b}[33mExclude\u{1b}[0m"));
+        assert!(text.contains("name 'src' not in patterns"));
+    }
+
+    #[test]
+    fn incremental() {
+        let root = tempfile::tempdir().unwrap();
+        let state = tempfile::tempdir().unwrap();
+        let a = root.path().join("a");
+        let b = root.path().join("b");
+        fs::create_dir_all(a.join("src")).unwrap();
+        fs::create_dir_all(b.join("build")).unwrap();
+
+        let mut manager = Manager::new();
+        manager.incremental = true;
+        manager.set_state_dir(state.path());
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["build"], None));
+        let run = |manager: &mut Manager| {
+            let (mut output, buffer) = Output::buffer();
+            output.set_verbosity(Verbosity::Verbose);
+            manager.set_output(output);
+            let summary = manager.execute().unwrap();
+            let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+            (summary, text)
+        };
+
+        let (summary, text) = run(&mut manager);
+        assert_eq!(summary.removed, 1);
+        assert!(!text.contains("Unchanged"));
+
+        // `a` didn't change, so nothing below it gets looked at
+        fs::create_dir(a.join("src").join("build")).unwrap();
+        let (summary, text) = run(&mut manager);
+        assert_eq!(summary.removed, 0);
+        assert!(text.contains(&format!("\u{1b}[33mUnchanged\u{1b}[0m {:?}", a)));
+        assert!(a.join("src").join("build").exists());
+
+        // touched, it's walked again
+        fs::create_dir(a.join("build")).unwrap();
+        let (summary, text) = run(&mut manager);
+        assert_eq!(summary.removed, 2);
+        assert!(!text.contains(&format!("\u{1b}[33mUnchanged\u{1b}[0m {:?}", a)));
+        assert!(!a.join("src").join("build").exists());
+
+        // and without the index everything is
+        fs::create_dir(b.join("build")).unwrap();
+        manager.incremental = false;
+        let (summary, _) = run(&mut manager);
+        assert_eq!(summary.removed, 1);
+
+        // a folder growing old changes nothing above it, so there's no
+        // index for rules on time
+        let c = root.path().join("c");
+        fs::create_dir_all(c.join("sub").join("build")).unwrap();
+        let mut config = Config::new(&c, Kind::Folder, vec!["build"], None);
+        config.older_than = Some(String::from("1d"));
+        manager.configs = vec![config];
+        manager.incremental = true;
+        let (summary, _) = run(&mut manager);
+        assert_eq!(summary.removed, 0);
+        let old = SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60);
+        fs::File::open(c.join("sub").join("build"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let (summary, text) = run(&mut manager);
+        assert_eq!(summary.removed, 1);
+        assert!(!text.contains("Unchanged"));
+    }
+
+    #[test]
+    fn incremental_deep_change() {
+        let root = tempfile::tempdir().unwrap();
+        let state = tempfile::tempdir().unwrap();
+        let t3 = root.path().join("t3");
+        let a = t3.join("a");
+        fs::create_dir_all(a.join("b")).unwrap();
+
+        let mut manager = Manager::new();
+        manager.incremental = true;
+        manager.set_state_dir(state.path());
+        manager.set_output(Output::buffer().0);
+        manager.add(Config::new(&t3, Kind::File, vec!["log"], None));
+        assert_eq!(manager.execute().unwrap().removed, 0);
+
+        // only `b` changed, and `a` above it didn't: as documented, the
+        // new file goes unnoticed
+        fs::write(a.join("b").join("new.log"), "").unwrap();
+        manager.dryrun = true;
+        assert_eq!(manager.execute().unwrap().removed, 0);
+
+        // until every folder above it changed as well
+        fs::write(a.join("notes.txt"), "").unwrap();
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.items[0].0, a.join("b").join("new.log"));
+
+        // an index is only ever the one of its config, the same destination
+        // with other patterns walks everything
+        fs::write(a.join("b").join("new.tmp"), "").unwrap();
+        manager.configs = vec![Config::new(&t3, Kind::File, vec!["tmp"], None)];
+        assert_eq!(manager.execute().unwrap().removed, 1);
+    }
+
+    #[test]
+    fn invalid_interval() {
+        let root = tempfile::tempdir().unwrap();
+        let config = root.path().join("config.json");
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "folder", "patterns": ["target"], "interval": "weekly" }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+
+        let args = vec!["neaten", "-c", config.to_str().unwrap()];
+        let mut manager = Manager::new();
+        let error = manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap_err();
+        assert!(error.to_string().contains("invalid interval 'weekly'"));
+
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "folder", "patterns": ["target"], "older_than": "old" }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+        let args = vec!["neaten", "-c", config.to_str().unwrap()];
+        let error = manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap_err();
+        assert!(error.to_string().contains("invalid older_than 'old'"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn empty_and_old() {
+        const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+        let root = tempfile::tempdir().unwrap();
+        let old = SystemTime::now() - 40 * DAY;
+        // (name, with something in it, old)
+        let folders = [
+            ("tmp_gone", false, true),
+            ("tmp_recent", false, false),
+            ("tmp_full", true, true),
+            ("scratch", false, true),
+        ];
+        for (name, full, aged) in folders {
+            let folder = root.path().join(name);
+            fs::create_dir(&folder).unwrap();
+            if full {
+                fs::write(folder.join("notes.txt"), "").unwrap();
+            }
+            if aged {
+                fs::File::open(&folder).unwrap().set_modified(old).unwrap();
+            }
+        }
+
+        let mut config = Config::new(root.path(), Kind::Folder, vec!["tmp_*"], None);
+        config.only_empty = true;
+        config.older_than = Some(String::from("30d"));
+        let tmp_full = root.path().join("tmp_full");
+        assert_eq!(
+            helper::judge(&tmp_full, &config),
+            Decision::Keep(Reason::NotEmpty)
+        );
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.add(config);
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 1);
+        assert!(!root.path().join("tmp_gone").exists());
+        for (name, _, _) in &folders[1..] {
+            assert!(root.path().join(name).exists());
+        }
+    }
+
+    #[test]
+    fn children_bounds() {
+        let root = tempfile::tempdir().unwrap();
+        // (name, entries right inside with the `sub` folder, entries in it)
+        let folders = [
+            ("cache_1", 1, 0),
+            ("cache_5", 5, 0),
+            ("cache_20", 20, 0),
+            ("cache_deep", 1, 8),
+        ];
+        let make = || {
+            for (name, inside, nested) in folders {
+                let folder = root.path().join(name);
+                fs::create_dir_all(folder.join("sub")).unwrap();
+                for i in 1..inside {
+                    fs::write(folder.join(format!("{}.bin", i)), "").unwrap();
+                }
+                for i in 0..nested {
+                    fs::write(folder.join("sub").join(format!("{}.bin", i)), "").unwrap();
+                }
+            }
+        };
+        let run = |min: Option<u64>, max: Option<u64>, recursive: bool| {
+            make();
+            let mut config = Config::new(root.path(), Kind::Folder, vec!["cache_*"], None);
+            config.min_children = min;
+            config.max_children = max;
+            config.children_recursive = recursive;
+            let mut manager = Manager::new();
+            manager.dryrun = true;
+            manager.set_output(Output::buffer().0);
+            manager.add(config);
+            let mut names: Vec<String> = manager
+                .plan()
+                .unwrap()
+                .iter()
+                .map(|a| a.path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(run(Some(5), None, false), vec!["cache_20", "cache_5"]);
+        assert_eq!(
+            run(None, Some(5), false),
+            vec!["cache_1", "cache_5", "cache_deep"]
+        );
+        assert_eq!(run(Some(2), Some(10), false), vec!["cache_5"]);
+        assert_eq!(run(Some(9), Some(10), true), vec!["cache_deep"]);
+
+        // counting stops once the bound is crossed
+        let big = root.path().join("cache_20");
+        assert_eq!(helper::count(&big, false, Some(7)), 7);
+        assert_eq!(helper::count(&big, false, Some(100)), 20);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn contents_only_preserves_times() {
+        let root = tempfile::tempdir().unwrap();
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let run = |preserve_times: bool| {
+            let cache = root.path().join("cache");
+            fs::create_dir_all(cache.join("nested")).unwrap();
+            fs::write(cache.join("a.bin"), "abc").unwrap();
+            fs::File::open(&cache).unwrap().set_modified(old).unwrap();
+
+            let mut config = Config::new(root.path(), Kind::Folder, vec!["cache"], None);
+            config.contents_only = true;
+            config.preserve_times = preserve_times;
+            let mut manager = Manager::new();
+            manager.set_output(Output::buffer().0);
+            manager.add(config);
+            let summary = manager.execute().unwrap();
+
+            // the folder stays, empty
+            assert_eq!(summary.removed, 2);
+            assert_eq!(fs::read_dir(&cache).unwrap().count(), 0);
+            fs::metadata(&cache).unwrap().modified().unwrap()
+        };
+
+        assert_eq!(run(true), old);
+        assert_ne!(run(false), old);
+    }
+
+    #[test]
+    fn prune_emptied() {
+        let root = tempfile::tempdir().unwrap();
+        let tree = |root: &Path| {
+            for (folder, files) in [
+                ("cache-1", &["a.bin", "b.bin"][..]),
+                ("keep-1", &["c.bin"]),
+                ("logs/2023", &["a.log", "b.log"]),
+                ("logs/2024", &["c.log", "notes.txt"]),
+                ("logs/old", &["d.log"]),
+            ] {
+                fs::create_dir_all(root.join(folder)).unwrap();
+                for file in files {
+                    fs::write(root.join(folder).join(file), "x").unwrap();
+                }
+            }
+        };
+        tree(root.path());
+        let configs = || {
+            let mut folders =
+                Config::new(root.path(), Kind::Folder, vec!["cache-*", "keep-*"], None);
+            folders.contents_only = true;
+            folders.prune_emptied = Some(String::from("cache-*"));
+            let mut files = Config::new(root.path().join("logs"), Kind::File, vec!["log"], None);
+            files.prune_emptied = Some(String::from("20*"));
+            [folders, files]
+        };
+
+        // a dry-run counts the emptied folders, freeing nothing more
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.set_output(Output::buffer().0);
+        configs().into_iter().for_each(|config| manager.add(config));
+        let summary = manager.execute().unwrap();
+        assert_eq!((summary.removed, summary.bytes), (9, 7));
+        assert!(root.path().join("cache-1").join("a.bin").exists());
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.explain = true;
+        manager.set_output(output);
+        configs().into_iter().for_each(|config| manager.add(config));
+        assert_eq!(manager.execute().unwrap().removed, 9);
+
+        // emptied and matching: gone
+        assert!(!root.path().join("cache-1").exists());
+        assert!(!root.path().join("logs").join("2023").exists());
+        // emptied but not matching, or not emptied: kept
+        assert_eq!(fs::read_dir(root.path().join("keep-1")).unwrap().count(), 0);
+        assert_eq!(
+            fs::read_dir(root.path().join("logs").join("old"))
+                .unwrap()
+                .count(),
+            0
+        );
+        assert!(
+            root.path()
+                .join("logs")
+                .join("2024")
+                .join("notes.txt")
+                .exists()
+        );
+        assert!(root.path().join("logs").exists());
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("left empty, matching '20*'"), "{}", text);
+
+        let mut config = Config::new(root.path(), Kind::Folder, vec!["cache"], None);
+        config.prune_emptied = Some(String::new());
+        assert!(Manager::check_entry(&config).is_err());
+    }
+
+    /// Notes how many entries `cache` has left when a folder starts going.
+    #[derive(Default)]
+    struct Watching {
+        left: Arc<Mutex<Vec<usize>>>,
+        /// whether removing fails, leaving everything in place.
+        stuck: bool,
+    }
+
+    impl FileSystem for Watching {
+        fn remove_dir_all(&self, path: &Path) -> std::io::Result<()> {
+            let cache = path.with_file_name("cache");
+            let left = fs::read_dir(cache)?.count();
+            self.left.lock().unwrap().push(left);
+            std::thread::sleep(Duration::from_millis(50));
+            if self.stuck {
+                return Err(std::io::Error::other("stuck"));
+            }
+            fs::remove_dir_all(path)
+        }
+    }
+
+    #[test]
+    fn move_aside() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = root.path().join("cache");
+        let fill = || {
+            fs::create_dir_all(cache.join("nested")).unwrap();
+            fs::write(cache.join("nested").join("b.bin"), "b").unwrap();
+            fs::write(cache.join("a.bin"), "abc").unwrap();
+        };
+        let config = || {
+            let mut config = Config::new(root.path(), Kind::Folder, vec!["cache"], None);
+            config.contents_only = true;
+            config.move_aside = true;
+            config
+        };
+
+        fill();
+        let watching = Watching::default();
+        let left = watching.left.clone();
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        manager.set_filesystem(watching);
+        manager.add(config());
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 2);
+        assert_eq!(summary.bytes, 4);
+        // emptied before anything got removed, then cleared by the end
+        assert_eq!(*left.lock().unwrap(), [0]);
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 0);
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 1);
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("Moved aside"), "{}", text);
+
+        // nothing moves across devices, it's all removed in place
+        fill();
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(CrossDevice);
+        manager.add(config());
+        assert_eq!(manager.execute().unwrap().removed, 2);
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 0);
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 1);
+
+        // nothing counts as removed until it is, only the folder is failed
+        fill();
+        let state = tempfile::tempdir().unwrap();
+        let mut manager = Manager::new();
+        manager.progress = Some(state.path().join("progress.jsonl"));
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Watching {
+            stuck: true,
+            ..Default::default()
+        });
+        manager.add(config());
+        let summary = manager.execute().unwrap();
+        let aside = root.path().join(".cache.aside-0");
+        assert_eq!(summary.errors, vec![(aside.clone(), String::from("stuck"))]);
+        assert!(aside.join("a.bin").exists());
+        let progress = manifest::resume(&manager.progress.take().unwrap()).unwrap();
+        assert_eq!(progress.planned.len(), 2);
+        assert!(progress.done.is_empty());
+
+        // which the next run sweeps up
+        fill();
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.add(config());
+        assert_eq!(manager.execute().unwrap().removed, 2);
+        assert!(!aside.exists());
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 1);
+
+        let mut config = config();
+        config.contents_only = false;
+        assert!(Manager::check_entry(&config).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn older_than_by_time_basis() {
+        use std::fs::FileTimes;
+
+        const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+        let root = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        // read lately but written long ago, and the other way round
+        let read = root.path().join("read.bin");
+        let written = root.path().join("written.bin");
+        let times = [
+            (&read, now, now - 10 * DAY),
+            (&written, now - 10 * DAY, now),
+        ];
+
+        for (basis, removed) in [(TimeBasis::Mtime, &read), (TimeBasis::Atime, &written)] {
+            for (path, accessed, modified) in times {
+                let file = fs::File::create(path).unwrap();
+                let file_times = FileTimes::new()
+                    .set_accessed(accessed)
+                    .set_modified(modified);
+                file.set_times(file_times).unwrap();
+            }
+            let mut config = Config::new(root.path(), Kind::File, vec!["bin"], None);
+            config.older_than = Some(String::from("7d"));
+            config.time_basis = basis.clone();
+
+            assert!(matches!(
+                helper::judge(removed, &config),
+                Decision::Remove(_)
+            ));
+            let kept = if removed == &read { &written } else { &read };
+            assert_eq!(
+                helper::judge(kept, &config),
+                Decision::Keep(Reason::Recent {
+                    basis,
+                    older_than: String::from("7d")
+                })
+            );
+        }
+
+        // both just had their status changed
+        let mut config = Config::new(root.path(), Kind::File, vec!["bin"], None);
+        config.older_than = Some(String::from("7d"));
+        config.time_basis = TimeBasis::Ctime;
+        assert!(matches!(helper::judge(&read, &config), Decision::Keep(_)));
+        assert!(matches!(
+            helper::judge(&written, &config),
+            Decision::Keep(_)
+        ));
+    }
+
+    #[test]
+    fn confirm_first_run() {
+        let root = tempfile::tempdir().unwrap();
+        let state = tempfile::tempdir().unwrap();
+        let project = root.path().join("project");
+
+        // declined: nothing removed, nothing remembered
+        fs::create_dir_all(project.join("build")).unwrap();
+        let (mut manager, asked) = first_run_manager(state.path(), false);
+        manager.add(Config::new(&project, Kind::Folder, vec!["build"], None));
+        let summary = manager.execute().unwrap();
+        assert_eq!(asked.load(Ordering::SeqCst), 1);
+        assert_eq!(summary.removed, 0);
+        assert!(project.join("build").exists());
+
+        // confirmed: removed and remembered
+        let (mut manager, asked) = first_run_manager(state.path(), true);
+        manager.add(Config::new(&project, Kind::Folder, vec!["build"], None));
+        let summary = manager.execute().unwrap();
+        assert_eq!(asked.load(Ordering::SeqCst), 1);
+        assert_eq!(summary.removed, 1);
+        assert!(!project.join("build").exists());
+
+        // subsequent run goes ahead without asking
+        fs::create_dir_all(project.join("build")).unwrap();
+        let (mut manager, asked) = first_run_manager(state.path(), false);
+        manager.add(Config::new(&project, Kind::Folder, vec!["build"], None));
+        let summary = manager.execute().unwrap();
+        assert_eq!(asked.load(Ordering::SeqCst), 0);
+        assert_eq!(summary.removed, 1);
+
+        // a changed config is a new one
+        let (mut manager, asked) = first_run_manager(state.path(), false);
+        manager.add(Config::new(&project, Kind::Folder, vec!["dist"], None));
+        manager.execute().unwrap();
+        assert_eq!(asked.load(Ordering::SeqCst), 1);
+
+        // the listing is out before the question, though held back otherwise
+        fs::create_dir_all(project.join("dist")).unwrap();
+        let (mut manager, _) = first_run_manager(state.path(), false);
+        let (output, buffer) = Output::buffer();
+        let shown = buffer.clone();
+        manager.set_output(output);
+        manager.set_prompt(Prompt::new(move |_| {
+            let text = String::from_utf8(shown.lock().unwrap().clone()).unwrap();
+            text.contains("Removing") && text.contains("dist")
+        }));
+        manager.summary_only_on_change = true;
+        manager.add(Config::new(&project, Kind::Folder, vec!["dist"], None));
+        assert_eq!(manager.execute().unwrap().removed, 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn dedup_hardlinks() {
+        let root = tempfile::tempdir().unwrap();
+        let original = root.path().join("a.o");
+        fs::write(&original, vec![0u8; 128]).unwrap();
+        fs::hard_link(&original, root.path().join("b.o")).unwrap();
+        fs::create_dir_all(root.path().join("sub")).unwrap();
+        fs::hard_link(&original, root.path().join("sub").join("c.o")).unwrap();
+        fs::write(root.path().join("d.o"), vec![0u8; 64]).unwrap();
+
+        let mut config = Config::new(root.path(), Kind::File, vec!["o"], None);
+        config.dedup_hardlinks = true;
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.add(config);
+        let summary = manager.execute().unwrap();
+
+        assert_eq!(summary.removed, 2);
+        assert_eq!(summary.bytes, 0);
+        let links = ["a.o", "b.o", "sub/c.o"]
+            .iter()
+            .filter(|name| root.path().join(name).exists())
+            .count();
+        assert_eq!(links, 1);
+        // not hard-linked at all
+        assert!(root.path().join("d.o").exists());
+    }
+
+    /// Cancels the run from within the first removal.
+    struct Interrupting(Cancel);
+
+    impl FileSystem for Interrupting {
+        fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+            self.0.cancel();
+            fs::remove_file(path)
+        }
+    }
+
+    #[test]
+    fn cancel_after_current_item() {
+        let root = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log", "c.log"] {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+        fs::create_dir_all(root.path().join("sub")).unwrap();
+        fs::write(root.path().join("sub").join("d.log"), "").unwrap();
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Interrupting(manager.cancel_handle()));
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+        let summary = manager.execute().unwrap();
+
+        // the removal in progress completed, nothing after it started
+        assert!(summary.cancelled);
+        assert_eq!(summary.removed, 1);
+        assert!(!summary.items[0].0.exists());
+        let left = ["a.log", "b.log", "c.log", "sub/d.log"]
+            .iter()
+            .filter(|name| root.path().join(name).exists())
+            .count();
+        assert_eq!(left, 3);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn match_link_target() {
+        use std::os::unix::fs::symlink;
+
+        let root = tempfile::tempdir().unwrap();
+        let old = root.path().join("opt").join("old");
+        let new = root.path().join("opt").join("new");
+        fs::create_dir_all(old.join("bin")).unwrap();
+        fs::create_dir_all(&new).unwrap();
+        fs::write(old.join("bin").join("tool"), "").unwrap();
+
+        let links = root.path().join("links");
+        fs::create_dir_all(&links).unwrap();
+        symlink(old.join("bin").join("tool"), links.join("tool")).unwrap();
+        symlink(&old, links.join("old")).unwrap();
+        symlink(old.join("gone"), links.join("dangling")).unwrap();
+        symlink(&new, links.join("new")).unwrap();
+        fs::write(links.join("regular"), "").unwrap();
+
+        let mut config = Config::new(&links, Kind::File, vec!["*"], None);
+        config.link_target_pattern = Some(old.to_str().unwrap().to_string());
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.add(config);
+        let summary = manager.execute().unwrap();
+
+        assert_eq!(summary.removed, 3);
+        for name in ["tool", "old", "dangling"] {
+            assert!(fs::symlink_metadata(links.join(name)).is_err());
+        }
+        assert!(fs::symlink_metadata(links.join("new")).is_ok());
+        assert!(links.join("regular").exists());
+        // only the links went, never what they point at
+        assert!(old.join("bin").join("tool").exists());
+
+        // the target is matched as a glob, at any level of it
+        symlink(old.join("bin").join("tool"), links.join("tool")).unwrap();
+        symlink(new.join("tool"), links.join("new-tool")).unwrap();
+        let mut config = Config::new(&links, Kind::File, vec!["*"], None);
+        let pattern = root.path().join("opt").join("o*").join("bin");
+        config.link_target_pattern = Some(pattern.to_str().unwrap().to_string());
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.add(config);
+        assert_eq!(manager.execute().unwrap().removed, 1);
+        assert!(fs::symlink_metadata(links.join("tool")).is_err());
+        assert!(fs::symlink_metadata(links.join("new")).is_ok());
+        assert!(fs::symlink_metadata(links.join("new-tool")).is_ok());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn broken_links() {
+        use std::os::unix::fs::symlink;
+
+        let root = tempfile::tempdir().unwrap();
+        let releases = root.path().join("releases");
+        let current = root.path().join("current");
+        let other = root.path().join("other");
+        for folder in [&releases, &current, &other] {
+            fs::create_dir_all(folder).unwrap();
+        }
+        fs::write(releases.join("app-2"), "").unwrap();
+        symlink(releases.join("app-2"), current.join("app.bin")).unwrap();
+        symlink(releases.join("app-1"), current.join("old.bin")).unwrap();
+        symlink("../releases/app-0", current.join("older.bin")).unwrap();
+        symlink("../releases/app-2", current.join("relative.bin")).unwrap();
+        symlink(releases.join("app-1"), current.join("old.txt")).unwrap();
+        symlink(releases.join("app-1"), other.join("old.bin")).unwrap();
+        fs::write(current.join("plain.bin"), "").unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.explain = true;
+        manager.set_output(output);
+        let mut config = Config::new(root.path(), Kind::File, vec!["bin"], None);
+        config.broken_links = true;
+        config.parent_name = Some(String::from("current"));
+        manager.add(config);
+        assert_eq!(manager.execute().unwrap().removed, 2);
+
+        let gone = |path: PathBuf| fs::symlink_metadata(path).is_err();
+        assert!(gone(current.join("old.bin")));
+        assert!(gone(current.join("older.bin")));
+        for kept in ["app.bin", "relative.bin", "old.txt", "plain.bin"] {
+            assert!(!gone(current.join(kept)), "{}", kept);
+        }
+        assert!(!gone(other.join("old.bin")));
+        assert!(releases.join("app-2").exists());
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("app.bin: links to"), "{}", text);
+        assert!(text.contains("plain.bin: not a symlink"), "{}", text);
+    }
+
+    /// `root/project/build/out.o` and `root/project/shared` linking to
+    /// `root/shared`, holding `build/out.o` too, plus a link back up.
+    #[cfg(unix)]
+    fn linked_tree() -> tempfile::TempDir {
+        use std::os::unix::fs::symlink;
+
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("project");
+        let shared = root.path().join("shared");
+        fs::create_dir_all(project.join("build")).unwrap();
+        fs::create_dir_all(shared.join("build")).unwrap();
+        fs::write(project.join("build").join("out.o"), "").unwrap();
+        fs::write(shared.join("build").join("out.o"), "").unwrap();
+        symlink(&shared, project.join("shared")).unwrap();
+        symlink(&shared, project.join("build-link")).unwrap();
+        symlink(root.path(), shared.join("up")).unwrap();
+        root
+    }
+
+    #[cfg(unix)]
+    fn symlink_run(root: &Path, policy: SymlinkPolicy, patterns: Vec<&str>) -> ExecutionSummary {
+        let mut config = Config::new(root.join("project"), Kind::Folder, patterns, None);
+        config.symlinks = policy;
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.add(config);
+        manager.execute().unwrap()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinks_skip() {
+        let root = linked_tree();
+        let project = root.path().join("project");
+        let summary = symlink_run(root.path(), SymlinkPolicy::Skip, vec!["build", "shared"]);
+
+        assert_eq!(summary.removed, 1);
+        assert!(!project.join("build").exists());
+        // neither the links nor anything behind them
+        assert!(fs::symlink_metadata(project.join("shared")).is_ok());
+        assert!(root.path().join("shared").join("build").exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinks_delete_link() {
+        let root = linked_tree();
+        let project = root.path().join("project");
+        let summary = symlink_run(root.path(), SymlinkPolicy::DeleteLink, vec!["shared"]);
+
+        assert_eq!(summary.removed, 1);
+        assert!(fs::symlink_metadata(project.join("shared")).is_err());
+        // the link went, not its target, and nothing behind other links
+        assert!(root.path().join("shared").join("build").exists());
+        assert!(fs::symlink_metadata(project.join("build-link")).is_ok());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinks_follow_and_delete() {
+        let root = linked_tree();
+        let project = root.path().join("project");
+        let summary = symlink_run(root.path(), SymlinkPolicy::FollowAndDelete, vec!["build"]);
+
+        // its own build, and the one behind the link; walked once and
+        // without looping through `up`
+        assert_eq!(summary.removed, 2);
+        assert!(!project.join("build").exists());
+        assert!(!root.path().join("shared").join("build").exists());
+        assert!(fs::symlink_metadata(project.join("shared")).is_ok());
+        assert!(root.path().join("shared").exists());
+    }
+
+    /// Records what it's asked to remove, and whether as a folder,
+    /// touching nothing.
+    #[derive(Default)]
+    struct Recording {
+        removed: Arc<Mutex<Vec<(PathBuf, bool)>>>,
+    }
+
+    impl FileSystem for Recording {
+        fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+            self.removed
+                .lock()
+                .unwrap()
+                .push((path.to_path_buf(), false));
+            Ok(())
+        }
+
+        fn remove_dir_all(&self, path: &Path) -> std::io::Result<()> {
+            self.removed
+                .lock()
+                .unwrap()
+                .push((path.to_path_buf(), true));
+            Ok(())
+        }
+    }
+
+    /// Refuses to remove `denied.o`, as if it were someone else's.
+    struct Denying;
+
+    impl FileSystem for Denying {
+        fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+            if path.ends_with("denied.o") {
+                Err(std::io::ErrorKind::PermissionDenied.into())
+            } else {
+                fs::remove_file(path)
+            }
+        }
+
+        fn remove_dir_all(&self, path: &Path) -> std::io::Result<()> {
+            // bails on the first entry refused, as std does
+            for entry in fs::read_dir(path)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    self.remove_dir_all(&path)?;
+                } else {
+                    self.remove_file(&path)?;
+                }
+            }
+            fs::remove_dir(path)
+        }
+    }
+
+    fn denied_tree() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        let nested = root.path().join("target").join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("denied.o"), "").unwrap();
+        fs::write(nested.join("other.o"), "").unwrap();
+        fs::create_dir_all(root.path().join("target").join("release")).unwrap();
+        fs::write(
+            root.path().join("target").join("release").join("main.o"),
+            "",
+        )
+        .unwrap();
+        fs::write(root.path().join("target").join("main.o"), "").unwrap();
+        root
+    }
+
+    /// Fails at removing a folder in one go, fine with one entry at a time
+    /// but for `stuck.o`.
+    struct Quirky;
+
+    impl FileSystem for Quirky {
+        fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+            if path.ends_with("stuck.o") {
+                Err(std::io::Error::other("in use"))
+            } else {
+                fs::remove_file(path)
+            }
+        }
+
+        fn remove_dir_all(&self, _: &Path) -> std::io::Result<()> {
+            Err(std::io::Error::other("directory busy"))
+        }
+    }
+
+    #[test]
+    fn remove_dir_all_fallback() {
+        let root = denied_tree();
+        let target = root.path().join("target");
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Quirky);
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["target"], None));
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 1);
+        assert!(!target.exists());
+
+        // still failing entry by entry, those entries are the errors
+        let root = denied_tree();
+        let target = root.path().join("target");
+        fs::write(target.join("nested").join("stuck.o"), "").unwrap();
+        manager.configs = vec![Config::new(root.path(), Kind::Folder, vec!["target"], None)];
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.errors.len(), 1);
+        assert!(summary.to_string().contains("stuck.o\": in use"));
+        assert!(!target.join("release").exists());
+    }
+
+    #[test]
+    fn on_permission_denied_skip() {
+        let root = denied_tree();
+        let target = root.path().join("target");
+
+        let mut manager = Manager::new();
+        manager.on_permission_denied = PermissionPolicy::Skip;
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Denying);
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["target"], None));
+
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.errors.len(), 1);
+        assert!(summary.errors[0].0.ends_with("denied.o"));
+        // siblings and other folders gone, the folders holding it left
+        assert!(target.join("nested").join("denied.o").exists());
+        assert!(!target.join("nested").join("other.o").exists());
+        assert!(!target.join("release").exists());
+        assert!(!target.join("main.o").exists());
+    }
+
+    #[test]
+    fn on_permission_denied_abort() {
+        let root = denied_tree();
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Denying);
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["target"], None));
+
+        let summary = manager.execute().unwrap().to_string();
+        assert!(
+            summary.contains("target\": permission denied"),
+            "{}",
+            summary
+        );
+    }
+
+    /// Refuses to remove an entry of a folder its owner can't write to, as
+    /// the system does for anyone but root.
+    #[cfg(unix)]
+    struct Unprivileged;
+
+    #[cfg(unix)]
+    impl Unprivileged {
+        fn check(path: &Path) -> std::io::Result<()> {
+            use std::os::unix::fs::PermissionsExt;
+
+            let parent = path.parent().unwrap_or(path);
+            match fs::metadata(parent)?.permissions().mode() & 0o200 {
+                0 => Err(std::io::ErrorKind::PermissionDenied.into()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    impl FileSystem for Unprivileged {
+        fn remove_file(&self, path: &Path) -> std::io::Result<()> {
+            Self::check(path)?;
+            fs::remove_file(path)
+        }
+
+        fn remove_dir(&self, path: &Path) -> std::io::Result<()> {
+            Self::check(path)?;
+            fs::remove_dir(path)
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn on_permission_denied_fix() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let run = |policy: PermissionPolicy| {
+            let root = denied_tree();
+            let nested = root.path().join("target").join("nested");
+            fs::set_permissions(&nested, fs::Permissions::from_mode(0o555)).unwrap();
+
+            let mut manager = Manager::new();
+            manager.on_permission_denied = policy;
+            manager.set_output(Output::buffer().0);
+            manager.set_filesystem(Unprivileged);
+            manager.add(Config::new(root.path(), Kind::Folder, vec!["target"], None));
+            (manager.execute(), root)
+        };
+
+        // refused without fixing, whoever runs the test
+        let (result, root) = run(PermissionPolicy::Skip);
+        let summary = result.unwrap().to_string();
+        assert!(summary.contains("permission denied"), "{}", summary);
+        assert!(root.path().join("target/nested/denied.o").exists());
+
+        let (result, root) = run(PermissionPolicy::Fix);
+        assert_eq!(result.unwrap().removed, 1);
+        assert!(!root.path().join("target").exists());
+    }
+
+    #[test]
+    fn remove_path() {
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join("target");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("main.o"), "").unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+
+        // dry-run only reports it
+        manager.remove_path(&target, true).unwrap();
+        assert!(target.exists());
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("Removing"));
+        assert!(!text.contains("Removed"));
+
+        manager.remove_path(&target, false).unwrap();
+        assert!(!target.exists());
+
+        // gone already
+        assert!(manager.remove_path(&target, false).is_err());
+    }
+
+    #[test]
+    fn remove_protected_path() {
+        let root = tempfile::tempdir().unwrap();
+        let recording = Recording::default();
+        let removed = recording.removed.clone();
+
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(recording);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+
+        let filesystem_root = path::absolute(root.path())
+            .unwrap()
+            .ancestors()
+            .last()
+            .unwrap()
+            .to_path_buf();
+        for path in [filesystem_root, root.path().to_path_buf()] {
+            let error = manager.remove_path(&path, false).unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains("refusing to remove protected path")
+            );
+        }
+        assert!(removed.lock().unwrap().is_empty());
+        assert!(root.path().exists());
+    }
+
+    fn planned_tree() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("src")).unwrap();
+        fs::write(root.path().join("a.log"), "abc").unwrap();
+        fs::write(root.path().join("src").join("b.log"), "").unwrap();
+        fs::write(root.path().join("main.rs"), "").unwrap();
+        root
+    }
+
+    #[test]
+    fn plan_matched_folder_once() {
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join("app").join("target");
+        fs::create_dir_all(target.join("debug").join("deps")).unwrap();
+        fs::write(target.join("debug").join("app"), [0u8; 100]).unwrap();
+        fs::write(
+            target.join("debug").join("deps").join("lib.rlib"),
+            [0u8; 20],
+        )
+        .unwrap();
+        // matching names inside a matched folder aren't looked at either
+        fs::create_dir(target.join("target")).unwrap();
+        fs::write(target.join("target").join("out"), [0u8; 3]).unwrap();
+
+        let mut manager = Manager::new();
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["target"], None));
+        let actions = manager.plan().unwrap();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].path, target);
+        assert_eq!(actions[0].size, 123);
+    }
+
+    #[test]
+    fn plan_drops_nested_matches() {
+        let root = tempfile::tempdir().unwrap();
+        let build = root.path().join("app").join("build");
+        fs::create_dir_all(&build).unwrap();
+        fs::write(build.join("main.o"), "").unwrap();
+        fs::write(root.path().join("app").join("util.o"), "").unwrap();
+
+        let mut manager = Manager::new();
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["build"], None));
+        manager.add(Config::new(root.path(), Kind::File, vec!["o"], None));
+        let mut paths: Vec<PathBuf> = manager
+            .plan()
+            .unwrap()
+            .into_iter()
+            .map(|a| a.path)
+            .collect();
+        paths.sort();
+        assert_eq!(paths, vec![build, root.path().join("app").join("util.o")]);
+    }
+
+    #[test]
+    fn plan_inspects_archives() {
+        let root = tempfile::tempdir().unwrap();
+        // a gzip header, then CRC and the size of what it holds
+        let mut bytes = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3, 3, 0];
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend(1_000_000u32.to_le_bytes());
+        fs::write(root.path().join("logs.gz"), &bytes).unwrap();
+
+        let mut manager = Manager::new();
+        manager.add(Config::new(root.path(), Kind::File, vec!["gz"], None));
+        let action = &manager.plan().unwrap()[0];
+        assert_eq!(action.size, 20);
+        assert_eq!(action.uncompressed, None);
+
+        manager.inspect_archives = true;
+        let action = &manager.plan().unwrap()[0];
+        assert_eq!(action.uncompressed, Some(1_000_000));
+        let json = serde_json::to_value(action).unwrap();
+        assert_eq!(json["uncompressed"], 1_000_000);
+
+        // still a single file to remove
+        manager.set_output(Output::buffer().0);
+        assert_eq!(manager.execute().unwrap().removed, 1);
+    }
+
+    #[test]
+    fn collapsed_dryrun() {
+        let root = tempfile::tempdir().unwrap();
+        for project in ["a", "b/c", "d"] {
+            let target = root.path().join("src").join(project).join("target");
+            fs::create_dir_all(&target).unwrap();
+            fs::write(target.join("out.bin"), "0123456789").unwrap();
+        }
+        fs::write(root.path().join("notes.tmp"), "12345").unwrap();
+
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.collapse = true;
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["target"], None));
+        manager.add(Config::new(root.path(), Kind::File, vec!["tmp"], None));
+        let plan = manager.plan().unwrap();
+
+        let (output, buffer) = Output::buffer();
+        manager.set_output(output);
+        let summary = manager.execute().unwrap();
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+
+        // one line per group, which together account for the whole plan
+        assert!(
+            text.contains("src/**/target: 3 item(s) (30 bytes)"),
+            "{}",
+            text
+        );
+        assert!(text.contains("notes.tmp: 1 item(s) (5 bytes)"), "{}", text);
+        assert!(!text.contains("out.bin"), "{}", text);
+        let groups = plan::collapse(&plan);
+        assert_eq!(groups.iter().map(|g| g.count).sum::<usize>(), plan.len());
+        assert_eq!(groups.iter().map(|g| g.size).sum::<u64>(), summary.bytes);
+        assert_eq!(summary.removed, plan.len());
+    }
+
+    #[test]
+    fn dryrun_flags_high_risk() {
+        let root = tempfile::tempdir().unwrap();
+        // a couple of gigabytes over a thousand files, sparse to stay cheap
+        let cache = root.path().join("cache");
+        fs::create_dir(&cache).unwrap();
+        for i in 0..1_000 {
+            fs::write(cache.join(format!("{}.bin", i)), "").unwrap();
+        }
+        fs::File::create(cache.join("blob"))
+            .unwrap()
+            .set_len(2 * 1024 * 1024 * 1024)
+            .unwrap();
+        fs::create_dir(root.path().join("tmp")).unwrap();
+        fs::write(root.path().join("tmp").join("a.bin"), "abc").unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.set_output(output);
+        manager.add(Config::new(
+            root.path(),
+            Kind::Folder,
+            vec!["cache", "tmp"],
+            None,
+        ));
+        assert_eq!(manager.execute().unwrap().removed, 2);
+
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let flagged: Vec<&str> = text.lines().filter(|l| l.contains("high-risk")).collect();
+        assert_eq!(flagged.len(), 1);
+        assert!(flagged[0].contains(&format!("{:?}, ", cache)));
+        assert!(flagged[0].ends_with("in 1001 entries"));
+
+        // the plan carries what the score is made of
+        let actions = manager.plan().unwrap();
+        let action = actions.iter().find(|a| a.path == cache).unwrap();
+        assert_eq!(action.entries, 1_001);
+        assert!(action.high_risk(None));
+    }
+
+    #[test]
+    fn plan_and_execute_plan() {
+        let root = planned_tree();
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+
+        let mut actions = manager.plan().unwrap();
+        actions.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            actions[0],
+            PlannedAction {
+                destination: root.path().to_path_buf(),
+                path: root.path().join("a.log"),
+                size: 3,
+                reason: String::from("matched pattern 'log'"),
+                entries: 0,
+                uncompressed: None,
+            }
+        );
+        assert_eq!(actions[1].path, root.path().join("src").join("b.log"));
+        // planning is silent and touches nothing
+        assert!(buffer.lock().unwrap().is_empty());
+        assert!(root.path().join("a.log").exists());
+
+        let summary = manager.execute_plan(&actions[1..]).unwrap();
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.destinations[0].destination, root.path());
+        assert!(root.path().join("a.log").exists());
+        assert!(!root.path().join("src").join("b.log").exists());
+    }
+
+    #[test]
+    fn execute_plan_like_execute() {
+        let root = planned_tree();
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.set_output(Output::buffer().0);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+
+        let fresh = manager.execute().unwrap();
+        let planned = manager.execute_plan(&manager.plan().unwrap()).unwrap();
+        assert_eq!(planned.removed, fresh.removed);
+        assert_eq!(planned.bytes, fresh.bytes);
+        assert_eq!(planned.items, fresh.items);
+        assert_eq!(planned.destinations, fresh.destinations);
+        assert!(planned.duration > Duration::ZERO);
+
+        // and for real, leaving the same tree behind
+        manager.dryrun = false;
+        let summary = manager.execute_plan(&manager.plan().unwrap()).unwrap();
+        assert_eq!(summary.items, fresh.items);
+        assert_eq!(helper::entries(root.path(), &Output::null()).len(), 2);
+        assert!(root.path().join("main.rs").exists());
+    }
+
+    #[test]
+    fn refuse_cwd() {
+        let recording = Recording::default();
+        let removed = recording.removed.clone();
+        let cwd = std::env::current_dir().unwrap();
+
+        for path in [cwd.clone(), cwd.parent().unwrap().to_path_buf()] {
+            let error = helper::remove_item(
+                &recording,
+                &path,
+                &PermissionPolicy::Abort,
+                None,
+                &Output::null(),
+            )
+            .unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains("is the current directory or holds it")
+            );
+        }
+        assert!(removed.lock().unwrap().is_empty());
+
+        // anything else in there is fine
+        let root = tempfile::tempdir_in(&cwd).unwrap();
+        helper::remove_item(
+            &recording,
+            root.path(),
+            &PermissionPolicy::Abort,
+            None,
+            &Output::null(),
+        )
+        .unwrap();
+        assert_eq!(removed.lock().unwrap().len(), 1);
+    }
+
+    /// A volume reporting fixed usage.
+    struct Volume {
+        total: u64,
+        available: u64,
+    }
+
+    impl FileSystem for Volume {
+        fn space(&self, _: &Path) -> std::io::Result<(u64, u64)> {
+            Ok((self.total, self.available))
+        }
+    }
+
+    #[test]
+    fn projected_free() {
+        let root = tempfile::tempdir().unwrap();
+        for (project, size) in [("web", 300), ("app", 200)] {
+            fs::create_dir_all(root.path().join(project)).unwrap();
+            fs::write(root.path().join(project).join("out.tmp"), vec![0; size]).unwrap();
+        }
+
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Volume {
+            total: 10_000,
+            available: 1_000,
+        });
+        for project in ["web", "app"] {
+            manager.add(Config::new(
+                root.path().join(project),
+                Kind::File,
+                vec!["tmp"],
+                None,
+            ));
+        }
+        let summary = manager.execute().unwrap();
+
+        // both on the same volume
+        assert_eq!(summary.volumes.len(), 1);
+        let volume = &summary.volumes[0];
+        assert_eq!(volume.destinations.len(), 2);
+        assert_eq!((volume.free, volume.freed), (1_000, 500));
+        assert_eq!(volume.projected_free, 1_500);
+        assert!(
+            summary
+                .to_string()
+                .contains("Projected free\u{1b}[0m 1500 byte(s)")
+        );
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["volumes"][0]["projected_free"], 1_500);
+
+        // a real run has nothing left to project
+        manager.dryrun = false;
+        assert!(manager.execute().unwrap().volumes.is_empty());
+    }
+
+    /// A volume reporting fixed inode counts.
+    #[cfg(unix)]
+    struct Inodes {
+        total: u64,
+        free: u64,
+    }
+
+    #[cfg(unix)]
+    impl FileSystem for Inodes {
+        fn inodes(&self, _: &Path) -> std::io::Result<(u64, u64)> {
+            Ok((self.total, self.free))
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn inode_marks() {
+        let root = tempfile::tempdir().unwrap();
+        let mut config = Config::new(root.path(), Kind::File, vec!["log"], None);
+        config.inode_high_water = Some(90);
+        config.inode_low_water = Some(88);
+        let run = |total: u64, free: u64| {
+            for name in ["a.log", "b.log", "c.log", "d.log", "e.log"] {
+                fs::write(root.path().join(name), "log").unwrap();
+            }
+            let (output, buffer) = Output::buffer();
+            let mut manager = Manager::new();
+            manager.set_output(output);
+            manager.set_filesystem(Inodes { total, free });
+            manager.add(config.clone());
+            let summary = manager.execute().unwrap();
+            let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+            (summary.removed, text)
+        };
+
+        // 80% of the inodes in use, nothing to do
+        let (removed, text) = run(100, 20);
+        assert_eq!(removed, 0);
+        assert!(text.contains("\u{1b}[33mNot full\u{1b}[0m"));
+
+        // 91% in use, 3 inodes to get under 88%
+        assert_eq!(run(100, 9).0, 3);
+
+        // a volume without inode counts: the marks are ignored
+        let (removed, text) = run(0, 0);
+        assert_eq!(removed, 5);
+        assert!(text.contains("ignoring its inode marks"));
+
+        // low mark past the high one
+        let mut manager = Manager::new();
+        config.inode_low_water = Some(95);
+        manager.add(config);
+        let error = manager.check().unwrap_err();
+        assert!(error.to_string().contains("invalid inode marks 90%/95%"));
+    }
+
+    #[test]
+    fn sentinel() {
+        let root = tempfile::tempdir().unwrap();
+        let paused = root.path().join("paused");
+        let active = root.path().join("active");
+        for destination in [&paused, &active] {
+            fs::create_dir(destination).unwrap();
+            fs::write(destination.join("debug.log"), "log").unwrap();
+        }
+        fs::write(paused.join(".no-cleanup"), "").unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        manager.add(Config::new(&paused, Kind::File, vec!["log"], None));
+        manager.add(Config::new(&active, Kind::File, vec!["log"], None));
+        assert_eq!(manager.plan().unwrap().len(), 1);
+        let summary = manager.execute().unwrap();
+
+        // the paused destination is untouched, sentinel included
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.skipped, vec![paused.clone()]);
+        assert!(paused.join("debug.log").exists());
+        assert!(paused.join(".no-cleanup").exists());
+        assert!(!active.join("debug.log").exists());
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains(&format!("\u{1b}[33mPaused\u{1b}[0m {:?}", paused)));
+        assert!(summary.to_string().contains("paused by its sentinel file"));
+
+        // an empty name turns the check off
+        manager.sentinel = String::new();
+        assert_eq!(manager.execute().unwrap().removed, 1);
+        assert!(!paused.join("debug.log").exists());
+    }
+
+    #[test]
+    fn water_marks() {
+        let root = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log", "c.log", "d.log"] {
+            fs::write(root.path().join(name), [0u8; 100]).unwrap();
+        }
+        let mut config = Config::new(root.path(), Kind::File, vec!["log"], None);
+        config.high_water = Some(90);
+        config.low_water = Some(80);
+        let run = |available: u64| {
+            let (output, buffer) = Output::buffer();
+            let mut manager = Manager::new();
+            manager.set_output(output);
+            manager.set_filesystem(Volume {
+                total: 1000,
+                available,
+            });
+            manager.dryrun = true;
+            manager.add(config.clone());
+            let summary = manager.execute().unwrap();
+            let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+            (summary, text)
+        };
+
+        // 80% full, under the high water mark
+        let (summary, text) = run(200);
+        assert_eq!(summary.removed, 0);
+        assert!(text.contains("\u{1b}[33mNot full\u{1b}[0m"));
+
+        // 95% full, 150 bytes to get under 80%
+        let (summary, _) = run(50);
+        assert_eq!(summary.removed, 2);
+        assert_eq!(summary.bytes, 200);
+
+        // low water past high water
+        let mut manager = Manager::new();
+        config.low_water = Some(95);
+        manager.add(config);
+        let error = manager.check().unwrap_err();
+        assert!(error.to_string().contains("invalid water marks 90%/95%"));
+    }
+
+    #[test]
+    fn labeled_errors() {
+        let root = tempfile::tempdir().unwrap();
+        let json = format!(
+            r#"[
+                {{"destination": {0:?}, "kind": "file", "patterns": ["log"]}},
+                {{"label": "nightly", "destination": {0:?}, "kind": "folder",
+                  "patterns": ["build"], "interval": "soon"}}
+            ]"#,
+            root.path()
+        );
+        let configs: Vec<Config> = serde_json::from_str(&json).unwrap();
+        assert_eq!(configs[1].label.as_deref(), Some("nightly"));
+
+        // while validating
+        let mut manager = Manager::new();
+        configs.into_iter().for_each(|config| manager.add(config));
+        let error = manager.check().unwrap_err();
+        assert_eq!(error.config_index(), Some(1));
+        assert_eq!(error.label(), Some("nightly"));
+        assert!(
+            error
+                .to_string()
+                .contains("config #1 'nightly': invalid interval 'soon'")
+        );
+
+        // and while running
+        let mut config = Config::new(root.path(), Kind::File, vec!["log"], None);
+        config.label = Some(String::from("scratch"));
+        config.high_water = Some(90);
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_filesystem(Unmounted);
+        manager.add(config);
+        let error = manager.execute().unwrap_err();
+        assert_eq!(error.config_index(), Some(0));
+        assert!(error.to_string().contains("config #0 'scratch': "));
+
+        // by where it was read, whatever was left out or merged before it
+        let config = root.path().join("config.json");
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "folder", "patterns": ["build"], "tags": ["ci"] },
+            { "destination": root.path(), "kind": "folder", "patterns": ["dist"], "tags": ["ci"] },
+            { "destination": root.path(), "kind": "file", "patterns": ["log"] },
+            {
+                "label": "gone",
+                "destination": root.path().join("gone"),
+                "kind": "folder",
+                "patterns": ["out"],
+                "tags": ["ci"]
+            }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+        let args = vec!["neaten", "-c", config.to_str().unwrap(), "--group", "ci"];
+        let error = Manager::new()
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap_err();
+        assert_eq!(error.config_index(), Some(3));
+        assert_eq!(error.label(), Some("gone"));
+    }
+
+    /// A volume whose usage can't be read.
+    struct Unmounted;
+
+    impl FileSystem for Unmounted {
+        fn space(&self, _: &Path) -> std::io::Result<(u64, u64)> {
+            Err(std::io::Error::other("not mounted"))
+        }
+    }
+
+    #[test]
+    fn print_then_execute_plan() {
+        let root = tempfile::tempdir().unwrap();
+        let plan = tempfile::tempdir().unwrap();
+        for name in ["a", "b"] {
+            fs::create_dir_all(root.path().join(name).join("target")).unwrap();
+        }
+
+        let (report, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager.set_report_output(report);
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["target"], None));
+        manager.print_plan().unwrap();
+        let json = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let actions: Vec<PlannedAction> = serde_json::from_str(&json).unwrap();
+        assert_eq!(actions, manager.plan().unwrap());
+        let file = plan.path().join("plan.json");
+        fs::write(&file, &json).unwrap();
+
+        // approved meanwhile; a new match isn't in it, a vanished one is gone
+        fs::create_dir_all(root.path().join("c").join("target")).unwrap();
+        fs::remove_dir(root.path().join("b").join("target")).unwrap();
+
+        let args = vec!["neaten", "--execute-plan", file.to_str().unwrap()];
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+        assert!(manager.configs.is_empty());
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 1);
+        assert!(!root.path().join("a").join("target").exists());
+        assert!(root.path().join("c").join("target").exists());
+
+        let args = vec!["neaten", "--execute-plan", "missing-plan.json"];
+        let error = Manager::new()
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap_err();
+        assert!(error.to_string().contains("plan file doesn't exists"));
+    }
+
+    #[test]
+    fn print_plan_ndjson() {
+        let root = tempfile::tempdir().unwrap();
+        let plan = tempfile::tempdir().unwrap();
+        for name in ["a", "b", "c"] {
+            fs::create_dir_all(root.path().join(name).join("target")).unwrap();
+        }
+
+        let (report, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.plan_lines = true;
+        manager.set_output(Output::buffer().0);
+        manager.set_report_output(report);
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["target"], None));
+        manager.print_plan().unwrap();
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let mut actions: Vec<PlannedAction> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let mut planned = manager.plan().unwrap();
+        actions.sort_by(|a, b| a.path.cmp(&b.path));
+        planned.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(actions, planned);
+        assert_eq!(text.lines().count(), 3);
+
+        // approve all but one line, as a `jq` filter would
+        let approved: Vec<&str> = text.lines().filter(|line| !line.contains("/b/")).collect();
+        let file = plan.path().join("plan.ndjson");
+        fs::write(&file, approved.join("\n")).unwrap();
+        let args = vec!["neaten", "--execute-plan", file.to_str().unwrap()];
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+        assert_eq!(manager.execute().unwrap().removed, 2);
+        assert!(!root.path().join("a").join("target").exists());
+        assert!(root.path().join("b").join("target").exists());
+        assert!(!root.path().join("c").join("target").exists());
+    }
+
+    #[test]
+    fn execute_plan_after_changes() {
+        let root = tempfile::tempdir().unwrap();
+        for name in ["changed", "vanished", "kept"] {
+            fs::create_dir_all(root.path().join(name).join("target")).unwrap();
+        }
+
+        let recording = Recording::default();
+        let removed = recording.removed.clone();
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        manager.set_filesystem(recording);
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["target"], None));
+        let actions = manager.plan().unwrap();
+        assert_eq!(actions.len(), 3);
+
+        // a folder when planned, a file now; the other gone altogether
+        let changed = root.path().join("changed").join("target");
+        fs::remove_dir(&changed).unwrap();
+        fs::write(&changed, "").unwrap();
+        let vanished = root.path().join("vanished").join("target");
+        fs::remove_dir(&vanished).unwrap();
+
+        let summary = manager.execute_plan(&actions).unwrap();
+        assert_eq!(summary.removed, 2);
+        assert!(summary.errors.is_empty());
+
+        // removed as the file it became
+        let mut removed = removed.lock().unwrap().clone();
+        removed.sort();
+        let kept = root.path().join("kept").join("target");
+        assert_eq!(removed, vec![(changed.clone(), false), (kept, true)]);
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains(&format!("\u{1b}[33mGone\u{1b}[0m {:?}", vanished)));
+
+        // and the same on the real disk
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        let summary = manager.execute_plan(&actions).unwrap();
+        assert_eq!(summary.removed, 2);
+        assert!(!changed.exists());
+    }
+
+    #[test]
+    fn review_without_terminal() {
+        for answer in [false, true] {
+            let root = planned_tree();
+            let mut manager = Manager::new();
+            manager.terminal = false;
+            manager.set_output(Output::buffer().0);
+            manager.set_prompt(Prompt::new(move |question| {
+                assert_eq!(question, "Remove these 2 item(s)?");
+                answer
+            }));
+            manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+
+            let summary = manager.review().unwrap();
+            assert_eq!(summary.removed, if answer { 2 } else { 0 });
+            assert_eq!(root.path().join("a.log").exists(), !answer);
+        }
+    }
+
+    #[test]
+    fn protect_control_files() {
+        let root = tempfile::tempdir().unwrap();
+        let settings = root.path().join("settings");
+        fs::create_dir_all(&settings).unwrap();
+        let config = settings.join("cleanup.json");
+        fs::write(root.path().join(".cleanupignore"), "# nothing\n").unwrap();
+        fs::write(root.path().join("other.json"), "{}").unwrap();
+
+        // every file, the config included
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "file", "patterns": ["*"] },
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let args = vec!["neaten", "-c", config.to_str().unwrap(), "--explain"];
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+        let summary = manager.execute().unwrap();
+
+        assert_eq!(summary.removed, 1);
+        assert!(!root.path().join("other.json").exists());
+        assert!(config.exists());
+        assert!(root.path().join(".cleanupignore").exists());
+
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("skipped .cleanupignore: controls the run"));
+        assert!(text.contains("skipped settings: controls the run"));
+    }
+
+    #[test]
+    fn refuse_removing_config_home() {
+        let root = tempfile::tempdir().unwrap();
+        let settings = root.path().join("project").join("settings");
+        fs::create_dir_all(&settings).unwrap();
+        let config = settings.join("cleanup.json");
+        let validate = |pattern: &str| {
+            let json = serde_json::json!([
+                { "destination": root.path(), "kind": "file", "patterns": ["log"] },
+                { "destination": root.path(), "kind": "folder", "patterns": [pattern] },
+            ]);
+            fs::write(&config, json.to_string()).unwrap();
+            let args = vec!["neaten", "-c", config.to_str().unwrap()];
+            let mut manager = Manager::new();
+            manager.set_output(Output::buffer().0);
+            manager.validate(Engine::try_parse_from(args).unwrap())
+        };
+
+        // the config's own folder, or one further up
+        for pattern in ["settings", "proj*"] {
+            let error = validate(pattern).unwrap_err();
+            assert_eq!(error.config_index(), Some(1));
+            assert!(
+                error.to_string().contains("holds the config file"),
+                "{}",
+                error
+            );
+        }
+        assert!(validate("dist").is_ok());
+        assert!(settings.exists());
+
+        // a config read from the config directory, or included by one
+        let configs = root.path().join("configs");
+        fs::create_dir(&configs).unwrap();
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "folder", "patterns": ["project"] },
+        ]);
+        fs::write(configs.join("a.json"), json.to_string()).unwrap();
+        fs::write(
+            configs.join("b.json"),
+            r#"[{ "include": "../project/settings/cleanup.json" }]"#,
+        )
+        .unwrap();
+        fs::write(&config, "[]").unwrap();
+        let validate = |args: Vec<&str>| {
+            let mut manager = Manager::new();
+            manager.set_output(Output::buffer().0);
+            manager.validate(Engine::try_parse_from(args).unwrap())
+        };
+        let dir = configs.to_str().unwrap();
+        let error = validate(vec!["neaten", "--config-dir", dir]).unwrap_err();
+        assert!(error.to_string().contains("cleanup.json"), "{}", error);
+
+        fs::remove_file(configs.join("b.json")).unwrap();
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "folder", "patterns": ["configs"] },
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+        fs::write(
+            configs.join("a.json"),
+            r#"[{ "include": "../project/settings/cleanup.json" }]"#,
+        )
+        .unwrap();
+        let error = validate(vec!["neaten", "--config-dir", dir]).unwrap_err();
+        assert!(error.to_string().contains("a.json"), "{}", error);
+        assert!(configs.exists() && settings.exists());
+    }
+
+    #[test]
+    fn parse_large_config() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("p1999")).unwrap();
+        let config = root.path().join("config.json");
+        let entries: Vec<serde_json::Value> = (0..2000)
+            .map(|i| {
+                serde_json::json!({
+                    "destination": root.path(),
+                    "kind": "folder",
+                    "patterns": [format!("p{}/", i)],
+                })
+            })
+            .collect();
+        fs::write(&config, serde_json::to_string(&entries).unwrap()).unwrap();
+
+        let args = vec!["neaten", "-c", config.to_str().unwrap()];
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+        assert_eq!(manager.configs.len(), 1);
+        assert_eq!(manager.configs[0].patterns.len(), 2000);
+        assert_eq!(manager.configs[0].patterns[1999], "p1999");
+        assert_eq!(manager.execute().unwrap().removed, 1);
+
+        // a bad entry stops the reading, named by its place in the file
+        let mut entries = entries;
+        entries[1500]["interval"] = serde_json::json!("soon");
+        fs::write(&config, serde_json::to_string(&entries).unwrap()).unwrap();
+        let error = manager.parse(&config).unwrap_err();
+        assert_eq!(error.config_index(), Some(1500));
+
+        // and so does anything but a list
+        fs::write(&config, "{}").unwrap();
+        assert!(manager.parse(&config).is_err());
+    }
+
+    #[test]
+    fn merge_configs() {
+        let root = tempfile::tempdir().unwrap();
+        let config = root.path().join("config.json");
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "folder", "patterns": ["target"] },
+            { "destination": root.path(), "kind": "folder", "patterns": ["dist", "Target"] },
+            { "destination": root.path(), "kind": "file", "patterns": ["log"] },
+            {
+                "destination": root.path(),
+                "kind": "folder",
+                "patterns": ["out"],
+                "exclude": [".git"]
+            },
+            { "destination": root.path(), "kind": "file", "patterns": ["tmp"] }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+
+        let args = vec!["neaten", "-c", config.to_str().unwrap()];
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+
+        let patterns: Vec<&Vec<Pattern>> = manager.configs.iter().map(|c| &c.patterns).collect();
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0], &vec!["target", "dist", "out"]);
+        assert_eq!(patterns[1], &vec!["log", "tmp"]);
+        // with the excludes of both
+        assert_eq!(manager.configs[0].exclude, Some(vec![String::from(".git")]));
+    }
+
+    #[test]
+    fn duplicate_patterns() {
+        let root = tempfile::tempdir().unwrap();
+        let args = vec![
+            "neaten",
+            "-d",
+            root.path().to_str().unwrap(),
+            "-k",
+            "folder",
+            "-p",
+            "build,Build,dist,BUILD",
+            "-e",
+            ".git,.GIT,src",
+        ];
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_output(output);
+        // only a warning, validation goes on
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let warnings: Vec<&str> = text.lines().collect();
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].contains("pattern 'Build' repeats 'build'"));
+        assert!(warnings[1].contains("pattern 'BUILD' repeats 'build'"));
+        assert!(warnings[2].contains("exclude '.GIT' repeats '.git'"));
+    }
+
+    #[test]
+    fn show_resolved_configs() {
+        let root = tempfile::tempdir().unwrap();
+        let config = root.path().join("config.json");
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "folder", "patterns": ["target"] },
+            {
+                "destination": root.path(),
+                "kind": "file",
+                "patterns": ["log", "tmp"],
+                "exclude": [".git"],
+                "on_missing_destination": "warn",
+                "mode_mask": 2
+            }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+
+        let args = vec![
+            "neaten",
+            "-c",
+            config.to_str().unwrap(),
+            "--on-missing-destination",
+            "skip",
+            "show",
+        ];
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_report_output(output);
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+        manager.show().unwrap();
+
+        let destination = root.path().display();
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            text,
+            format!(
+                "{destination}\n  kind: folder\n  patterns: target\n  exclude: none\n  \
+                 on missing destination: skip\n\n\
+                 {destination}\n  kind: file\n  patterns: log, tmp\n  exclude: .git\n  \
+                 on missing destination: warn\n  mode: 2 masked with 2\n\n"
+            )
+        );
+
+        // same rules as JSON, which reads back as they would run
+        let (output, buffer) = Output::buffer();
+        manager.json = true;
+        manager.set_report_output(output);
+        manager.show().unwrap();
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let configs: Vec<Config> = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            serde_json::to_value(&configs).unwrap(),
+            serde_json::to_value(manager.resolved().unwrap()).unwrap()
+        );
+        assert_eq!(configs[0].on_missing_destination, Some(MissingPolicy::Skip));
+        assert_eq!(configs[0].exclude, Some(vec![]));
+        assert_eq!(configs[1].mode_value, Some(2));
+    }
+
+    #[test]
+    fn show_includes() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("shared")).unwrap();
+        let config = root.path().join("config.json");
+        let json = serde_json::json!([
+            { "destination": ".", "kind": "folder", "patterns": ["target"] },
+            { "include": "shared/common.json" }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+        // an included file resolves its destinations from its own folder
+        let json = serde_json::json!([
+            { "destination": "..", "kind": "file", "patterns": ["${CARGO_PKG_NAME}.log"] }
+        ]);
+        fs::write(root.path().join("shared/common.json"), json.to_string()).unwrap();
+
+        let args = vec!["neaten", "-c", config.to_str().unwrap(), "--json", "show"];
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.set_report_output(output);
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+        manager.show().unwrap();
+
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let configs: Vec<Config> = serde_json::from_str(&text).unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0].patterns, vec!["target"]);
+        assert_eq!(configs[1].destination, root.path());
+        assert_eq!(
+            configs[1].patterns,
+            vec![format!("{}.log", env!("CARGO_PKG_NAME")).as_str()]
+        );
+
+        // a file including itself fails rather than loops
+        let json = serde_json::json!([{ "include": "../config.json" }]);
+        fs::write(root.path().join("shared/common.json"), json.to_string()).unwrap();
+        let args = vec!["neaten", "-c", config.to_str().unwrap(), "show"];
+        let error = Manager::new()
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap_err();
+        assert!(error.message().contains("includes itself"), "{}", error);
     }
 
     #[test]
-    fn add_config() {
+    fn match_first_line() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("gen.sh"),
+            "#!/usr/bin/env python3\nprint()\n",
+        )
+        .unwrap();
+        fs::write(root.path().join("tool"), "#!/usr/bin/python\r\nprint()\n").unwrap();
+        fs::write(root.path().join("hand.sh"), "#!/bin/sh\necho #!python\n").unwrap();
+        fs::write(root.path().join("Python.txt"), "#!PYTHON").unwrap();
+        fs::write(root.path().join("empty"), "").unwrap();
+        fs::create_dir_all(root.path().join("python")).unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.explain = true;
+        manager.set_output(output);
+        let mut config = Config::new(root.path(), Kind::File, vec!["*"], None);
+        config.first_line_matches = Some(String::from("#!*python*"));
+        manager.add(config);
+        let summary = manager.execute().unwrap();
+
+        assert_eq!(summary.removed, 2);
+        assert!(!root.path().join("gen.sh").exists());
+        assert!(!root.path().join("tool").exists());
+        assert!(root.path().join("hand.sh").exists());
+        assert!(root.path().join("Python.txt").exists());
+        assert!(root.path().join("empty").exists());
+
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("kept hand.sh: first line '#!/bin/sh' doesn't match '#!*python*'"));
+        assert!(text.contains("kept empty: first line '' doesn't match '#!*python*'"));
+    }
+
+    #[test]
+    fn presets() {
+        let root = tempfile::tempdir().unwrap();
+        let folders = [
+            "crate/target",
+            "crate/src",
+            "web/node_modules",
+            "web/target",
+            "notes/target",
+            "notes/node_modules",
+            "tool/__pycache__",
+        ];
+        for folder in folders {
+            fs::create_dir_all(root.path().join(folder)).unwrap();
+        }
+        fs::write(root.path().join("crate/Cargo.toml"), "").unwrap();
+        fs::write(root.path().join("web/package.json"), "").unwrap();
+        fs::write(root.path().join("tool/main.py"), "").unwrap();
+
+        let dir = root.path().to_str().unwrap();
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        let args = vec!["neaten", "-d", dir, "--presets", "rust,node"];
+        manager
+            .validate(Engine::try_parse_from(args).unwrap())
+            .unwrap();
+        assert_eq!(manager.execute().unwrap().removed, 2);
+
+        // only next to the manifest of the project
+        assert!(!root.path().join("crate/target").exists());
+        assert!(!root.path().join("web/node_modules").exists());
+        assert!(root.path().join("crate/src").exists());
+        assert!(root.path().join("web/target").exists());
+        assert!(root.path().join("notes/target").exists());
+        assert!(root.path().join("notes/node_modules").exists());
+        assert!(root.path().join("tool/__pycache__").exists());
+
+        let configs = Preset::Python.configs(root.path());
+        let pycache = root.path().join("tool/__pycache__");
+        assert!(matches!(
+            helper::judge(&pycache, &configs[0]),
+            Decision::Remove(_)
+        ));
+    }
+
+    #[test]
+    fn touch_after() {
+        let root = tempfile::tempdir().unwrap();
+        let stamp = root.path().join(".cleaned");
+        let run = |dryrun: bool| {
+            fs::write(root.path().join("a.log"), "").unwrap();
+            let mut manager = Manager::new();
+            manager.dryrun = dryrun;
+            manager.set_output(Output::buffer().0);
+            let mut config = Config::new(root.path(), Kind::File, vec!["*"], None);
+            config.touch_after = Some(String::from(".cleaned"));
+            manager.add(config);
+            manager.execute().unwrap()
+        };
+
+        // one which can't be written is a failed step, not a failed item
+        fs::create_dir(&stamp).unwrap();
+        let summary = run(false);
+        assert_eq!(summary.removed, 1);
+        assert!(summary.errors.is_empty());
+        assert_eq!(summary.failed_steps.len(), 1);
+        assert!(
+            summary.failed_steps[0]
+                .1
+                .starts_with(&format!("couldn't touch sentinel {:?}", stamp)),
+            "{:?}",
+            summary.failed_steps
+        );
+        fs::remove_dir(&stamp).unwrap();
+
+        // not for a dry-run
+        run(true);
+        assert!(!stamp.exists());
+
+        // created by a real run, which leaves it alone once there
+        assert_eq!(run(false).removed, 1);
+        let seconds: u64 = fs::read_to_string(&stamp).unwrap().trim().parse().unwrap();
+        assert!(seconds > 1_500_000_000);
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&stamp)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        assert_eq!(run(false).removed, 1);
+        assert!(fs::metadata(&stamp).unwrap().modified().unwrap() > old);
+
+        let mut config = Config::new(root.path(), Kind::File, vec!["log"], None);
+        config.touch_after = Some(String::from("logs/.cleaned"));
+        assert!(Manager::check_entry(&config).is_err());
+    }
+
+    #[test]
+    fn min_matches() {
+        let root = tempfile::tempdir().unwrap();
+        let run = |policy: Option<ShortfallPolicy>| {
+            let (output, buffer) = Output::buffer();
+            let mut manager = Manager::new();
+            manager.set_output(output);
+            let mut config = Config::new(root.path(), Kind::File, vec!["log"], None);
+            config.min_matches = Some(2);
+            config.on_few_matches = policy;
+            manager.add(config);
+            let result = manager.execute();
+            let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+            (result, text)
+        };
+
+        // nothing to match: the run fails, or only warns
+        let (result, _) = run(None);
+        let summary = result.unwrap();
+        assert!(summary.errors.is_empty());
+        assert_eq!(
+            summary.failed_steps,
+            vec![(
+                root.path().to_path_buf(),
+                String::from("matched 0 of at least 2")
+            )]
+        );
+        assert!(summary.brief().ends_with(", 1 step(s) failed"));
+        let (result, text) = run(Some(ShortfallPolicy::Warn));
+        let summary = result.unwrap();
+        assert_eq!(summary.removed, 0);
+        assert!(summary.failed_steps.is_empty());
+        assert!(text.contains("matched 0 of at least 2"), "{}", text);
+
+        // enough of them
+        for name in ["a.log", "b.log"] {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+        let (result, text) = run(None);
+        assert_eq!(result.unwrap().removed, 2);
+        assert!(!text.contains("Warning"), "{}", text);
+    }
+
+    #[test]
+    fn name_shape() {
+        let root = tempfile::tempdir().unwrap();
+        let random = "3f2a9c0e7b1d4f6a8c2e0b9d7f1a3c5e";
+        let names = [
+            random,
+            "3F2A9C0E7B1D4F6A8C2E0B9D7F1A3C5E",
+            "3f2a9c0e7b1d4f6a8c2e0b9d7f1a3c5",
+            "report-2024-final-version-copy-1",
+            "zz2a9c0e7b1d4f6a8c2e0b9d7f1a3c5e",
+            "notes.txt",
+        ];
+        for name in names {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.explain = true;
+        manager.set_output(output);
+        let mut config = Config::new(root.path(), Kind::File, vec!["name:*"], None);
+        config.name_len_min = Some(32);
+        config.name_len_max = Some(32);
+        config.name_charset = Some(Charset::Hex);
+        manager.add(config);
+        assert_eq!(manager.execute().unwrap().removed, 2);
+
+        assert!(!root.path().join(random).exists());
+        assert!(!root.path().join(names[1]).exists());
+        for name in &names[2..] {
+            assert!(root.path().join(name).exists(), "{}", name);
+        }
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("name is 31 characters long"), "{}", text);
+        assert!(text.contains("name isn't all hex"), "{}", text);
+
+        let mut config = Config::new(root.path(), Kind::File, vec!["tmp"], None);
+        config.name_len_min = Some(8);
+        config.name_len_max = Some(4);
+        assert!(Manager::check_entry(&config).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn since_session_start() {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = tempfile::tempdir().unwrap();
+        for name in ["mine.tmp", "theirs.tmp"] {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+        // only root may give a file away
+        let chowned = nix::unistd::Uid::effective().is_root() && {
+            let path = CString::new(root.path().join("theirs.tmp").as_os_str().as_bytes()).unwrap();
+            // SAFETY: the path is a valid C string
+            unsafe { libc::chown(path.as_ptr(), 1, 1) == 0 }
+        };
+        let run = |start: SystemTime, user: Option<u32>| {
+            let (output, buffer) = Output::buffer();
+            let mut manager = Manager::new();
+            manager.explain = true;
+            manager.session_start = Some(start);
+            manager.session_user = user;
+            manager.set_output(output);
+            let mut config = Config::new(root.path(), Kind::File, vec!["tmp"], None);
+            config.since_session_start = true;
+            manager.add(config);
+            let removed = manager.execute().unwrap().removed;
+            (
+                removed,
+                String::from_utf8(buffer.lock().unwrap().clone()).unwrap(),
+            )
+        };
+
+        // a session started after the files changed leaves them all be
+        let (removed, text) = run(SystemTime::now() + Duration::from_secs(3600), None);
+        assert_eq!(removed, 0);
+        assert!(
+            text.contains("changed before the session started"),
+            "{}",
+            text
+        );
+
+        // nor does one of another user, whoever runs the test
+        let owner = nix::unistd::Uid::effective().as_raw();
+        let since = SystemTime::now() - Duration::from_secs(3600);
+        let (removed, text) = run(since, Some(owner + 1000));
+        assert_eq!(removed, 0);
+        assert!(
+            text.contains(&format!("owned by uid {}", owner)),
+            "{}",
+            text
+        );
+
+        let (removed, _) = run(since, None);
+        assert!(!root.path().join("mine.tmp").exists());
+        match chowned {
+            true => {
+                assert_eq!(removed, 1);
+                assert!(root.path().join("theirs.tmp").exists());
+            }
+            false => assert_eq!(removed, 2),
+        }
+    }
+
+    #[test]
+    fn parent_name() {
+        let root = tempfile::tempdir().unwrap();
+        for folder in ["app/debug", "app/release", "lib/Debug", "debug/obj"] {
+            let folder = root.path().join(folder);
+            fs::create_dir_all(&folder).unwrap();
+            fs::write(folder.join("main.o"), "").unwrap();
+        }
+        fs::write(root.path().join("app").join("debug").join("main.c"), "").unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.explain = true;
+        manager.set_output(output);
+        let mut config = Config::new(root.path(), Kind::File, vec!["o"], None);
+        config.parent_name = Some(String::from("debug"));
+        manager.add(config);
+        assert_eq!(manager.execute().unwrap().removed, 2);
+
+        assert!(!root.path().join("app/debug/main.o").exists());
+        assert!(!root.path().join("lib/Debug/main.o").exists());
+        assert!(root.path().join("app/debug/main.c").exists());
+        assert!(root.path().join("app/release/main.o").exists());
+        // only the immediate parent counts
+        assert!(root.path().join("debug/obj/main.o").exists());
+
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(
+            text.contains("parent 'release' doesn't match 'debug'"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn dir_quota() {
+        const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+        let root = tempfile::tempdir().unwrap();
+        let sub = root.path().join("sub");
+        fs::create_dir_all(&sub).unwrap();
+        let now = SystemTime::now();
+        let write = |path: PathBuf, days: u32| {
+            fs::write(&path, vec![0u8; 100]).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - DAY * days).unwrap();
+        };
+        // newest first: 1.bin is a day old, 5.bin five days
+        for days in 1..=5 {
+            write(root.path().join(format!("{}.bin", days)), days);
+        }
+        write(root.path().join("notes.txt"), 9);
+        write(sub.join("a.bin"), 30);
+        write(sub.join("b.bin"), 40);
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.explain = true;
+        manager.set_output(output);
+        let mut config = Config::new(root.path(), Kind::File, vec!["bin"], None);
+        config.dir_quota = Some(String::from("250B"));
+        manager.add(config);
+        let summary = manager.execute().unwrap();
+
+        // the two newest fit, the rest goes; each folder on its own
+        assert_eq!(summary.removed, 3);
+        assert_eq!(summary.bytes, 300);
+        for (days, kept) in [(1, true), (2, true), (3, false), (4, false), (5, false)] {
+            assert_eq!(root.path().join(format!("{}.bin", days)).exists(), kept);
+        }
+        assert!(root.path().join("notes.txt").exists());
+        assert!(sub.join("a.bin").exists() && sub.join("b.bin").exists());
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(
+            text.contains("kept 2.bin: newer, within the folder's quota of 250B"),
+            "{}",
+            text
+        );
+
+        let mut config = Config::new(root.path(), Kind::File, vec!["bin"], None);
+        config.dir_quota = Some(String::from("lots"));
+        assert!(Manager::check_entry(&config).is_err());
+    }
+
+    #[test]
+    fn rotation_keep_newest() {
+        let root = tempfile::tempdir().unwrap();
+        let names = [
+            "app-2023-01-01.log",
+            "app-2023-12-31.log",
+            "app-2023-02-15.log",
+            "db-2022-06-01.log",
+            "db-2021-06-01.log",
+            "web-9.log",
+            "web-10.log",
+            "notes.log",
+        ];
+        for name in names {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.explain = true;
+        manager.set_output(output);
+        let mut config = Config::new(root.path(), Kind::File, vec!["log"], None);
+        config.rotation_key = Some(String::from(r"-(?P<key>[\d-]+)\.log$"));
+        manager.add(config);
+        let summary = manager.execute().unwrap();
+
+        let left = |name: &str| root.path().join(name).exists();
+        assert_eq!(summary.removed, 4);
+        // newest by name, whatever the modification times say
+        assert!(left("app-2023-12-31.log"));
+        assert!(left("db-2022-06-01.log"));
+        assert!(!left("app-2023-01-01.log"));
+        assert!(!left("app-2023-02-15.log"));
+        assert!(!left("db-2021-06-01.log"));
+        // numbers compare as numbers
+        assert!(left("web-10.log"));
+        assert!(!left("web-9.log"));
+        assert!(left("notes.log"));
+
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("kept app-2023-12-31.log: newest by key '2023-12-31'"));
+        assert!(text.contains("removed web-9.log: older by key '9'"));
+        assert!(text.contains("kept notes.log: no rotation key in name"));
+
+        // keeping more of each series
+        for name in names {
+            fs::write(root.path().join(name), "").unwrap();
+        }
+        manager.configs[0].keep_newest = Some(2);
+        let summary = manager.execute().unwrap();
+        assert_eq!(summary.removed, 1);
+        assert!(!left("app-2023-01-01.log"));
+    }
+
+    #[test]
+    fn rotation_mixed_keys() {
+        let keys = ["2024-01-01", "7", "12", "007", "2023-12-31", "9"];
+        let sorted = |keys: &[&'static str]| {
+            let mut keys = keys.to_vec();
+            keys.sort_by(|a, b| helper::compare_keys(a, b));
+            keys
+        };
+        let expected = vec!["007", "7", "9", "12", "2023-12-31", "2024-01-01"];
+        assert_eq!(sorted(&keys), expected);
+        // whatever order they come in
+        let mut reversed = keys;
+        reversed.reverse();
+        assert_eq!(sorted(&reversed), expected);
+    }
+
+    #[test]
+    fn read_children() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("a.txt"), "").unwrap();
+        fs::write(root.path().join("b.txt"), "").unwrap();
+        fs::create_dir(root.path().join("node_modules")).unwrap();
+        // a name exclude can't be checked against
+        #[cfg(target_os = "linux")]
+        {
+            use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+            fs::write(root.path().join(OsStr::from_bytes(b"bad\xff")), "").unwrap();
+        }
+
+        let exclude = vec![String::from("node_modules")];
+        let (ok, failed): (Vec<_>, Vec<_>) =
+            helper::read_children(root.path(), &exclude).partition(|child| child.is_ok());
+        let mut names: Vec<_> = ok
+            .into_iter()
+            .map(|child| child.unwrap().file_name().unwrap().to_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["a.txt", "b.txt"]);
+        #[cfg(target_os = "linux")]
+        assert_eq!(failed.len(), 1);
+        #[cfg(not(target_os = "linux"))]
+        assert!(failed.is_empty());
+
+        // an unreadable folder is a single error rather than nothing
+        let missing: Vec<_> = helper::read_children(root.path().join("missing"), &[]).collect();
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].is_err());
+    }
+
+    // other unix file systems may refuse names that aren't UTF-8
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn non_unicode_names() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let root = tempfile::tempdir().unwrap();
+        let name = |bytes: &[u8]| root.path().join(OsStr::from_bytes(bytes));
+        fs::write(name(b"bad\xff.log"), "").unwrap();
+        fs::write(name(b"notes.\xfflog"), "").unwrap();
+        fs::write(name(b"\xfe.tmp"), "").unwrap();
+        fs::create_dir(name(b"cache\xff")).unwrap();
+
+        let (output, buffer) = Output::buffer();
         let mut manager = Manager::new();
+        manager.explain = true;
+        manager.set_output(output);
+        // the extension is valid UTF-8 even though the name isn't
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+        // none of these can match the names, an empty name would
         manager.add(Config::new(
-            "/Users/abhinath/productive/pool/Project",
-            Kind::Folder,
-            vec!["build", "debug", "release"],
+            root.path(),
+            Kind::File,
+            vec!["stem:", "name:.tmp"],
             None,
         ));
+        manager.add(Config::new(root.path(), Kind::Folder, vec!["cache*"], None));
+        assert_eq!(manager.execute().unwrap().removed, 1);
+
+        assert!(!name(b"bad\xff.log").exists());
+        assert!(name(b"notes.\xfflog").exists());
+        assert!(name(b"\xfe.tmp").exists());
+        assert!(name(b"cache\xff").exists());
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(
+            text.contains("notes.\u{fffd}log: name 'notes.\u{fffd}log' isn't valid UTF-8"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("name '\u{fffd}.tmp' isn't valid UTF-8"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("name 'cache\u{fffd}' isn't valid UTF-8"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn glob() {
+        assert!(helper::glob("keep.log", "KEEP.log", false));
+        assert!(helper::glob("*.log", "debug.log", false));
+        assert!(helper::glob("build-?", "build-1", false));
+        assert!(helper::glob("*", "", false));
+        assert!(!helper::glob("build-?", "build-10", false));
+        assert!(!helper::glob("*.log", "debug.txt", false));
+        assert!(helper::wildcard(
+            "// @generated*",
+            "// @generated by protoc"
+        ));
+        assert!(!helper::wildcard(
+            "// @generated*",
+            "// @Generated by protoc"
+        ));
+        for (pattern, text, matched) in [
+            ("", "", true),
+            ("", "a", false),
+            ("**", "", true),
+            ("a*b*c", "axxbyyc", true),
+            ("a*b*c", "axxbyy", false),
+            ("*a?", "bab", true),
+            ("*ab", "aab", true),
+            ("*.log", "a.log.log", true),
+            ("?", "д", true),
+        ] {
+            assert_eq!(helper::wildcard(pattern, text), matched, "{}", pattern);
+        }
+
+        // the whole first line of a generated file, many stars and no match
+        let line = "a".repeat(4096);
+        let pattern = format!("{}b", "*a".repeat(32));
+        let started = Instant::now();
+        assert!(!helper::wildcard(&pattern, &line));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn cleanupignore() {
+        let root = tempfile::tempdir().unwrap();
+        let app = root.path().join("app");
+        let data = app.join("data");
+        fs::create_dir_all(&data).unwrap();
+        fs::write(root.path().join("root.log"), "").unwrap();
+        fs::write(
+            app.join(".cleanupignore"),
+            "# kept on purpose\n\nkeep-*.log\ndata/\n",
+        )
+        .unwrap();
+        fs::write(app.join("keep-me.log"), "").unwrap();
+        fs::write(app.join("debug.log"), "").unwrap();
+        fs::write(data.join("other.log"), "").unwrap();
+        // patterns only apply below the file's own directory
+        fs::write(root.path().join("keep-root.log"), "").unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.explain = true;
+        manager.set_output(output);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+        let summary = manager.execute().unwrap();
+
+        assert_eq!(summary.removed, 3);
+        assert!(!root.path().join("root.log").exists());
+        assert!(!root.path().join("keep-root.log").exists());
+        assert!(!app.join("debug.log").exists());
+        assert!(app.join("keep-me.log").exists());
+        assert!(data.join("other.log").exists());
+
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("skipped keep-me.log: ignored by 'keep-*.log'"));
+        assert!(text.contains("skipped data: ignored by 'data'"));
+    }
+
+    fn json_events(buffer: &Mutex<Vec<u8>>) -> Vec<serde_json::Value> {
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        text.lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn allocated_size() {
+        const LENGTH: u64 = 16 * 1024 * 1024;
+
+        let root = tempfile::tempdir().unwrap();
+        // a hole, nothing allocated behind it
+        let sparse = fs::File::create(root.path().join("sparse.img")).unwrap();
+        sparse.set_len(LENGTH).unwrap();
+        drop(sparse);
+
+        let mut manager = Manager::new();
+        manager.dryrun = true;
+        manager.set_output(Output::buffer().0);
+        manager.add(Config::new(root.path(), Kind::File, vec!["img"], None));
+        assert_eq!(manager.execute().unwrap().bytes, LENGTH);
+
+        manager.allocated_size = true;
+        let bytes = manager.execute().unwrap().bytes;
+        assert!(bytes < LENGTH);
+        assert_eq!(bytes, helper::size(root.path().join("sparse.img"), true));
+    }
+
+    #[test]
+    fn json_lines() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join(".git")).unwrap();
+        fs::write(root.path().join("a.log"), "abc").unwrap();
+        fs::write(root.path().join("main.rs"), "").unwrap();
+
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.json_lines = true;
+        manager.set_output(Output::buffer().0);
+        manager.set_report_output(output);
+        manager.add(Config::new(
+            root.path(),
+            Kind::File,
+            vec!["log"],
+            Some(vec![".git"]),
+        ));
+        let summary = manager.execute().unwrap();
+        manager.report(&summary).unwrap();
+
+        let mut events = json_events(&buffer);
+        assert_eq!(events.len(), 3);
+        // the summary comes last, entries in directory order before it
+        let last = events.pop().unwrap();
+        assert_eq!(last["event"], "summary");
+        assert_eq!(last["removed"], 1);
+        events.sort_by_key(|event| event["event"].as_str().unwrap().to_string());
+        assert_eq!(events[0]["event"], "removed");
         assert_eq!(
-            manager,
-            Manager {
-                configs: vec![Config {
-                    destination: PathBuf::from("/Users/abhinath/productive/pool/Project"),
-                    kind: Kind::Folder,
-                    patterns: vec![
-                        String::from("build"),
-                        String::from("debug"),
-                        String::from("release"),
-                    ],
-                    exclude: None,
-                }],
-                dryrun: false
+            events[0]["path"],
+            root.path().join("a.log").to_str().unwrap()
+        );
+        assert_eq!(events[0]["size"], 3);
+        assert_eq!(events[0]["dryrun"], false);
+        assert_eq!(events[1]["event"], "skipped");
+        assert_eq!(
+            events[1]["path"],
+            root.path().join(".git").to_str().unwrap()
+        );
+        assert_eq!(events[1]["reason"], "excluded by '.git'");
+
+        // failures are streamed as they happen
+        let root = locked_tree();
+        let (output, buffer) = Output::buffer();
+        manager.set_report_output(output);
+        manager.set_filesystem(Locked);
+        manager.configs = vec![Config::new(root.path(), Kind::File, vec!["log"], None)];
+        assert_eq!(manager.execute().unwrap().errors.len(), 1);
+
+        let events = json_events(&buffer);
+        let failed: Vec<_> = events.iter().filter(|e| e["event"] == "failed").collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(
+            failed[0]["path"],
+            root.path().join("locked.log").to_str().unwrap()
+        );
+        assert_eq!(failed[0]["error"], "locked");
+        assert_eq!(events.iter().filter(|e| e["event"] == "removed").count(), 2);
+    }
+
+    fn on_change_manager(json: bool) -> (Manager, Arc<Mutex<Vec<u8>>>) {
+        let (output, buffer) = Output::buffer();
+        let mut manager = Manager::new();
+        manager.summary_only_on_change = true;
+        if json {
+            manager.summary_format = SummaryFormat::Json;
+        }
+        manager.set_output(output.clone());
+        manager.set_report_output(output);
+        (manager, buffer)
+    }
+
+    #[test]
+    fn summary_only_on_change() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join(".git")).unwrap();
+        fs::write(root.path().join("main.rs"), "").unwrap();
+
+        // nothing to remove, only an excluded item: silent
+        for json in [false, true] {
+            let (mut manager, buffer) = on_change_manager(json);
+            manager.add(Config::new(
+                root.path(),
+                Kind::File,
+                vec!["log"],
+                Some(vec![".git"]),
+            ));
+            let summary = manager.execute().unwrap();
+            manager.report(&summary).unwrap();
+            assert!(buffer.lock().unwrap().is_empty());
+        }
+
+        // something removed: progress and summary
+        fs::write(root.path().join("debug.log"), "").unwrap();
+        let (mut manager, buffer) = on_change_manager(false);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+        let summary = manager.execute().unwrap();
+        manager.report(&summary).unwrap();
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert!(text.contains("debug.log"));
+        assert!(text.contains("Removed\u{1b}[0m 1 item(s)"));
+
+        // and the same as JSON
+        fs::write(root.path().join("debug.log"), "").unwrap();
+        let (mut manager, buffer) = on_change_manager(true);
+        manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+        let summary = manager.execute().unwrap();
+        manager.report(&summary).unwrap();
+        let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let json = &text[text.find('{').unwrap()..];
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(json).unwrap()["removed"],
+            1
+        );
+
+        // nothing removed but a failure: reported all the same, and failed
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("locked.log"), "").unwrap();
+        for json in [false, true] {
+            let (mut manager, buffer) = on_change_manager(json);
+            manager.set_filesystem(Locked);
+            manager.add(Config::new(root.path(), Kind::File, vec!["log"], None));
+            let summary = manager.execute().unwrap();
+            manager.report(&summary).unwrap();
+            let text = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+            match json {
+                true => {
+                    let json = &text[text.find('{').unwrap()..];
+                    let json = serde_json::from_str::<serde_json::Value>(json).unwrap();
+                    assert_eq!(json["errors"].as_array().unwrap().len(), 1);
+                }
+                false => assert!(text.contains("Failed\u{1b}[0m 1 item(s)"), "{}", text),
+            }
+            assert_eq!(manager.exit_code(&summary), AppError::EXIT_CODE);
+        }
+    }
+
+    #[test]
+    fn progress_callback() {
+        let root = tempfile::tempdir().unwrap();
+        let (web, app) = (root.path().join("web"), root.path().join("app"));
+        for (folder, size) in [(&web, 3), (&app, 4)] {
+            fs::create_dir_all(folder).unwrap();
+            fs::write(folder.join("out.tmp"), vec![0; size]).unwrap();
+        }
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = Manager::new();
+        manager.set_output(Output::buffer().0);
+        let seen = events.clone();
+        manager.set_progress_callback(move |event| {
+            let event = match event {
+                ProgressEvent::Started { destination } => format!("start {:?}", destination),
+                ProgressEvent::Item {
+                    path,
+                    size,
+                    removed,
+                    bytes,
+                } => format!("item {:?} {} {} {}", path, size, removed, bytes),
+                ProgressEvent::Finished { removed, bytes } => format!("end {} {}", removed, bytes),
+            };
+            seen.lock().unwrap().push(event);
+        });
+        manager.add(Config::new(&web, Kind::File, vec!["tmp"], None));
+        manager.add(Config::new(&app, Kind::File, vec!["tmp"], None));
+        manager.dryrun = true;
+        manager.execute().unwrap();
+
+        let expected = vec![
+            format!("start {:?}", web),
+            format!("item {:?} 3 1 3", web.join("out.tmp")),
+            format!("start {:?}", app),
+            format!("item {:?} 4 2 7", app.join("out.tmp")),
+            String::from("end 2 7"),
+        ];
+        assert_eq!(*events.lock().unwrap(), expected);
+
+        // the next run counts from scratch
+        events.lock().unwrap().clear();
+        manager.execute().unwrap();
+        assert_eq!(*events.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn audit_log() {
+        let root = tempfile::tempdir().unwrap();
+        let log = root.path().join("logs").join("audit.log");
+        let run = |name: &str, dryrun: bool| {
+            let folder = root.path().join(name);
+            fs::create_dir_all(&folder).unwrap();
+            for i in 0..20 {
+                fs::write(folder.join(format!("{}.tmp", i)), "").unwrap();
             }
+            let mut manager = Manager::new();
+            manager.dryrun = dryrun;
+            manager.audit_log = Some(log.clone());
+            manager.set_output(Output::buffer().0);
+            manager.add(Config::new(&folder, Kind::File, vec!["tmp"], None));
+            manager.execute().unwrap().removed
+        };
+
+        // two runs at once, then one more later on
+        std::thread::scope(|scope| {
+            scope.spawn(|| run("a", false));
+            scope.spawn(|| run("b", false));
+        });
+        run("c", true);
+
+        let records: Vec<audit::Record> = fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 60);
+        for name in ["a", "b", "c"] {
+            let of_run = records
+                .iter()
+                .filter(|r| r.path.parent() == Some(&root.path().join(name)));
+            assert_eq!(of_run.count(), 20);
+        }
+        let record = records.last().unwrap();
+        assert_eq!(record.action, audit::Action::WouldRemove);
+        assert_eq!(record.result, "ok");
+        assert!(record.config_hash.is_some());
+        assert!(
+            records[..40]
+                .iter()
+                .all(|r| r.action == audit::Action::Remove)
         );
+
+        // each item goes with the hash of the config which matched it
+        fs::remove_file(&log).unwrap();
+        let configs = ["a", "b"].map(|name| {
+            fs::write(root.path().join(name).join("0.tmp"), "").unwrap();
+            Config::new(root.path().join(name), Kind::File, vec!["tmp"], None)
+        });
+        let mut manager = Manager::new();
+        manager.audit_log = Some(log.clone());
+        manager.set_output(Output::buffer().0);
+        for config in &configs {
+            manager.add(config.clone());
+        }
+        manager.execute().unwrap();
+        let records: Vec<audit::Record> = fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        for (record, config) in records.iter().zip(configs) {
+            let mut alone = Manager::new();
+            alone.add(config);
+            assert_eq!(record.config_hash, Some(alone.config_hash().unwrap()));
+        }
+        assert_ne!(records[0].config_hash, records[1].config_hash);
     }
 
     #[test]
-    fn check_format() {
+    fn report_file() {
+        let root = tempfile::tempdir().unwrap();
+        let report = root.path().join("reports/run.json");
+        let config = root.path().join("config.json");
+        let json = serde_json::json!([
+            { "destination": root.path(), "kind": "file", "patterns": ["log"] }
+        ]);
+        fs::write(&config, json.to_string()).unwrap();
+        fs::write(root.path().join("debug.log"), "").unwrap();
+
+        let args = vec![
+            "neaten",
+            "-c",
+            config.to_str().unwrap(),
+            "--summary-format",
+            "json",
+            "--report-file",
+            report.to_str().unwrap(),
+        ];
+        let (output, buffer) = Output::buffer();
+        let (stdout, printed) = Output::buffer();
         let mut manager = Manager::new();
+        manager.set_output(stdout);
+        manager.set_report_output(output);
         manager
-            .format(
-                "/Users/abhinath/productive/pool/Project",
-                Kind::Folder,
-                vec![
-                    String::from("build"),
-                    String::from("debug"),
-                    String::from("release"),
-                ],
-                None,
-            )
+            .validate(Engine::try_parse_from(args).unwrap())
             .unwrap();
+        let summary = manager.execute().unwrap();
+        manager.report(&summary).unwrap();
 
-        assert_eq!(
-            manager,
-            Manager {
-                configs: vec![Config {
-                    destination: PathBuf::from("/Users/abhinath/productive/pool/Project"),
-                    kind: Kind::Folder,
-                    patterns: vec![
-                        String::from("build"),
-                        String::from("debug"),
-                        String::from("release"),
-                    ],
-                    exclude: None,
-                }],
-                dryrun: false
-            }
-        );
+        // the JSON went to the file, in a folder created for it
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&report).unwrap()).unwrap();
+        assert_eq!(json["removed"], 1);
+        assert!(buffer.lock().unwrap().is_empty());
+        // and only the brief summary to stdout, without the item lines
+        let text = String::from_utf8(printed.lock().unwrap().clone()).unwrap();
+        assert_eq!(text.lines().count(), 1, "{}", text);
+        assert!(text.contains("Removed\u{1b}[0m 1 item(s)"));
+        assert!(!text.contains("debug.log"));
+        assert!(!text.contains("Took"));
+        assert!(!text.contains('{'));
+
+        // a file which can't be written is an error
+        manager.report_file = Some(root.path().join("debug.log/run.json"));
+        fs::write(root.path().join("debug.log"), "").unwrap();
+        assert!(manager.report(&summary).is_err());
     }
 
     #[test]
-    // #[should_panic]
-    fn check_remove_as_mut() {
-        let mut item = helper::Remove {
-            destination: PathBuf::from("/Users/abhinath/productive/pool"),
-            kind: Kind::Folder,
-            patterns: vec![
-                String::from("packages"),
-                String::from("bin"),
-                String::from("obj"),
-                String::from("Debug"),
-                String::from("Release"),
-            ],
-            exclude: vec![],
-            dryrun: true,
-        };
-        helper::remove_as_mut(&mut item);
+    #[cfg(windows)]
+    fn remove_long_path() {
+        let root = tempfile::tempdir().unwrap();
+
+        // build a chain of folders well past MAX_PATH
+        let mut deep = root.path().join("node_modules");
+        while deep.as_os_str().len() <= 300 {
+            deep = deep.join("a_rather_long_package_name");
+        }
+        fs::create_dir_all(helper::long_path(&deep)).unwrap();
+        fs::write(helper::long_path(deep.join("index.js")), "").unwrap();
+
+        let target = root.path().join("node_modules");
+        let left = helper::remove_item(
+            &RealFs,
+            &target,
+            &PermissionPolicy::Abort,
+            None,
+            &Output::null(),
+        )
+        .unwrap();
+        assert!(left.is_empty());
+        assert!(!target.exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn remove_long_path() {
+        use std::ffi::CString;
+
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join("node_modules");
+        fs::create_dir(&target).unwrap();
+
+        // a chain of folders past PATH_MAX can only be built one level at a
+        // time, relative to the folder above
+        let name = CString::new("a_rather_long_package_name".repeat(9)).unwrap();
+        let path = CString::new(target.as_os_str().as_encoded_bytes()).unwrap();
+        let mut length = target.as_os_str().len();
+        // SAFETY: every descriptor is opened here and closed once used
+        unsafe {
+            let mut folder = libc::open(path.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY);
+            while length <= libc::PATH_MAX as usize {
+                assert_eq!(libc::mkdirat(folder, name.as_ptr(), 0o755), 0);
+                let next = libc::openat(folder, name.as_ptr(), libc::O_RDONLY | libc::O_DIRECTORY);
+                libc::close(folder);
+                folder = next;
+                length += name.as_bytes().len() + 1;
+            }
+            let index = CString::new("index.js").unwrap();
+            let file = libc::openat(
+                folder,
+                index.as_ptr(),
+                libc::O_CREAT | libc::O_WRONLY,
+                0o644,
+            );
+            assert!(file >= 0);
+            libc::close(file);
+            libc::close(folder);
+        }
+
+        let left = helper::remove_item(
+            &RealFs,
+            &target,
+            &PermissionPolicy::Abort,
+            None,
+            &Output::null(),
+        )
+        .unwrap();
+        assert!(left.is_empty());
+        assert!(!target.exists());
     }
 }
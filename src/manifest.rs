@@ -0,0 +1,220 @@
+use crate::{
+    ExecutionSummary, PlannedAction, config,
+    error::{AppError, AppErrorKind},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Record of the items a run removed (or, from a dry-run, would remove),
+/// which `Manager::replay` can act on later without re-evaluating any rule.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Manifest {
+    /// format it was written in, 0 for manifests from before it was
+    /// recorded, set to `VERSION` on write.
+    #[serde(default)]
+    pub version: u32,
+    /// SHA-256 of the entries as compact JSON, set on write. Only a
+    /// manifest of version 0 may lack one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    /// `config_hash` of the run which wrote it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub size: u64,
+    /// what the run did with it, removed when not told.
+    #[serde(default)]
+    pub status: Status,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    #[default]
+    Removed,
+    /// listed by a dry-run, still in place.
+    WouldRemove,
+}
+
+impl Manifest {
+    /// Format of the manifests written now; from version 1 on they all
+    /// carry a checksum.
+    pub const VERSION: u32 = 1;
+
+    /// Read the manifest at `path`, failing when its entries don't match
+    /// its checksum any more, or when it lost the checksum its version has.
+    pub fn read<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let json_data = fs::read_to_string(path)?;
+        let manifest: Self = serde_json::from_str(&json_data)?;
+        let modified = |why: &str| {
+            Err(AppError::new(
+                AppErrorKind::Functionality,
+                format!("manifest {:?} was modified, {}", path, why),
+            ))
+        };
+        match &manifest.checksum {
+            Some(checksum) if *checksum != manifest.digest()? => {
+                modified("its checksum doesn't match")
+            }
+            None if manifest.version > 0 => modified("its checksum is missing"),
+            _ => Ok(manifest),
+        }
+    }
+
+    /// Write the manifest to `path`, with the checksum of its entries.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> crate::Result<()> {
+        let sealed = Sealed {
+            version: Self::VERSION,
+            checksum: self.digest()?,
+            config_hash: self.config_hash.as_deref(),
+            entries: &self.entries,
+        };
+        fs::write(path, serde_json::to_string_pretty(&sealed)?)?;
+        Ok(())
+    }
+
+    /// The checksum, over the config hash as well when there's one.
+    fn digest(&self) -> crate::Result<String> {
+        match &self.config_hash {
+            Some(config_hash) => config::digest(&(config_hash, &self.entries)),
+            None => config::digest(&self.entries),
+        }
+    }
+}
+
+/// Append `path`, just removed, to the progress file at `progress`: one
+/// `Entry` as JSON per line, written right away so a killed run keeps it.
+pub fn append(progress: &Path, path: &Path, size: u64) -> crate::Result<()> {
+    let entry = Entry {
+        path: path.to_path_buf(),
+        size,
+        status: Status::Removed,
+    };
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(progress)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+/// Append what a config is about to remove to the progress file at
+/// `progress`, one action as JSON per line, for `--resume` to carry on
+/// from without walking the tree again.
+pub fn plan(progress: &Path, actions: &[PlannedAction]) -> crate::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(progress)?;
+    for action in actions {
+        writeln!(file, "{}", serde_json::to_string(action)?)?;
+    }
+    Ok(())
+}
+
+/// What an interrupted run recorded in its progress file.
+#[derive(Debug, Default, PartialEq)]
+pub struct Progress {
+    /// actions the run planned, in order.
+    pub planned: Vec<PlannedAction>,
+    /// paths it removed.
+    pub done: HashSet<PathBuf>,
+}
+
+impl Progress {
+    /// The planned actions not done yet.
+    pub fn left(self) -> Vec<PlannedAction> {
+        let done = self.done;
+        self.planned
+            .into_iter()
+            .filter(|action| !done.contains(&action.path))
+            .collect()
+    }
+}
+
+/// A line of the progress file: an action planned, or an item done.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Line {
+    Planned(PlannedAction),
+    Done(Entry),
+}
+
+/// What the progress file at `progress` lists, nothing when it doesn't
+/// exist. A line cut short by the run being killed is dropped from the
+/// file, for the resumed run to append after the good ones.
+pub fn resume(progress: &Path) -> crate::Result<Progress> {
+    let text = match fs::read_to_string(progress) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Progress::default()),
+        Err(e) => return Err(e.into()),
+    };
+    let (entries, lines): (Vec<Line>, Vec<&str>) = text
+        .lines()
+        .filter_map(|line| Some((serde_json::from_str::<Line>(line).ok()?, line)))
+        .unzip();
+    if lines.len() != text.lines().count() {
+        fs::write(
+            progress,
+            lines
+                .iter()
+                .map(|line| format!("{}\n", line))
+                .collect::<String>(),
+        )?;
+    }
+    let mut read = Progress::default();
+    for entry in entries {
+        match entry {
+            Line::Planned(action) => read.planned.push(action),
+            Line::Done(entry) => {
+                read.done.insert(entry.path);
+            }
+        }
+    }
+    Ok(read)
+}
+
+/// `Manifest` as written, version and checksum first.
+#[derive(Serialize)]
+struct Sealed<'a> {
+    version: u32,
+    checksum: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config_hash: Option<&'a str>,
+    entries: &'a [Entry],
+}
+
+impl From<&ExecutionSummary> for Manifest {
+    fn from(summary: &ExecutionSummary) -> Self {
+        let status = if summary.dryrun {
+            Status::WouldRemove
+        } else {
+            Status::Removed
+        };
+        Self {
+            version: Self::VERSION,
+            checksum: None,
+            config_hash: summary.config_hash.clone(),
+            entries: summary
+                .items
+                .iter()
+                .map(|(path, size)| Entry {
+                    path: path.clone(),
+                    size: *size,
+                    status,
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    fmt::{Arguments, Debug, Formatter, Result as FmtResult},
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+/// How much of its progress a run shows, each level adding to the one
+/// before.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Verbosity {
+    /// nothing, only the summary.
+    Quiet,
+    /// every item removed, and anything going wrong.
+    #[default]
+    Normal,
+    /// items excluded, ignored, protected or left out as well.
+    Verbose,
+    /// why every item is removed or kept, too.
+    Debug,
+}
+
+/// Where a run writes its progress lines, stdout unless told otherwise.
+#[derive(Clone)]
+pub struct Output {
+    sink: Arc<Mutex<dyn Write + Send>>,
+    /// lines kept back while on hold.
+    held: Arc<Mutex<Option<Vec<u8>>>>,
+    /// lines above this level aren't written.
+    verbosity: Verbosity,
+}
+
+impl Output {
+    fn new(sink: Arc<Mutex<dyn Write + Send>>) -> Self {
+        Self {
+            sink,
+            held: Arc::new(Mutex::new(None)),
+            verbosity: Verbosity::Normal,
+        }
+    }
+
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    /// Whether lines of `level` get written.
+    pub fn shows(&self, level: Verbosity) -> bool {
+        level <= self.verbosity
+    }
+
+    pub fn stdout() -> Self {
+        Self::new(Arc::new(Mutex::new(io::stdout())))
+    }
+
+    pub fn stderr() -> Self {
+        Self::new(Arc::new(Mutex::new(io::stderr())))
+    }
+
+    /// An output throwing every line away.
+    pub fn null() -> Self {
+        Self::new(Arc::new(Mutex::new(io::sink())))
+    }
+
+    /// An in-memory output, returned together with the buffer it fills.
+    pub fn buffer() -> (Self, Arc<Mutex<Vec<u8>>>) {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        (Self::new(buffer.clone()), buffer)
+    }
+
+    /// Keep the following lines back until `release` or `discard`.
+    pub fn hold(&self) {
+        if let Ok(mut held) = self.held.lock() {
+            held.get_or_insert_with(Vec::new);
+        }
+    }
+
+    /// Write out the lines kept back, and stop holding.
+    pub fn release(&self) {
+        let held = self.held.lock().ok().and_then(|mut held| held.take());
+        if let (Some(held), Ok(mut sink)) = (held, self.sink.lock()) {
+            let _ = sink.write_all(&held);
+        }
+    }
+
+    /// Write out the lines kept back so far, holding the following ones.
+    pub fn flush(&self) {
+        if let (Ok(mut held), Ok(mut sink)) = (self.held.lock(), self.sink.lock())
+            && let Some(held) = held.as_mut()
+        {
+            let _ = sink.write_all(held);
+            held.clear();
+        }
+    }
+
+    /// Hand back the lines kept back, and stop holding.
+    pub fn take(&self) -> Vec<u8> {
+        self.held
+            .lock()
+            .ok()
+            .and_then(|mut held| held.take())
+            .unwrap_or_default()
+    }
+
+    /// Drop the lines kept back, and stop holding.
+    pub fn discard(&self) {
+        if let Ok(mut held) = self.held.lock() {
+            held.take();
+        }
+    }
+
+    /// Write a line of the `Normal` level.
+    pub fn line(&self, args: Arguments) {
+        self.line_at(Verbosity::Normal, args);
+    }
+
+    /// Write a line shown even at the `Quiet` level.
+    pub fn always(&self, args: Arguments) {
+        self.line_at(Verbosity::Quiet, args);
+    }
+
+    /// Write a line only shown from the `Verbose` level on.
+    pub fn verbose(&self, args: Arguments) {
+        self.line_at(Verbosity::Verbose, args);
+    }
+
+    fn line_at(&self, level: Verbosity, args: Arguments) {
+        if !self.shows(level) {
+            return;
+        }
+        if let Ok(mut held) = self.held.lock()
+            && let Some(held) = held.as_mut()
+        {
+            let _ = writeln!(held, "{}", args);
+            return;
+        }
+        if let Ok(mut sink) = self.sink.lock() {
+            // nothing sensible to do when stdout is gone
+            let _ = writeln!(sink, "{}", args);
+        }
+    }
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::stdout()
+    }
+}
+
+impl Debug for Output {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Output")
+    }
+}
+
+// where the lines go doesn't change what a run does
+impl PartialEq for Output {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
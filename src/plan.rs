@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// An item a run would remove, found without touching anything.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct PlannedAction {
+    /// destination of the config which found it.
+    pub destination: PathBuf,
+    pub path: PathBuf,
+    /// size in bytes removing it would free.
+    pub size: u64,
+    /// why it matched.
+    pub reason: String,
+    /// number of files and folders inside it, 0 for a file.
+    #[serde(default)]
+    pub entries: u64,
+    /// for an archive with `--inspect-archives`, the size of what it holds
+    /// once unpacked; `size` stays what removing it frees.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uncompressed: Option<u64>,
+}
+
+impl PlannedAction {
+    /// Score from which an action is worth a careful look before running.
+    pub const HIGH_RISK: u32 = 3;
+
+    /// How much removing the item could hurt if the rule is wrong: it
+    /// grows with the size, the number of entries inside, and how close
+    /// the item is to the filesystem root or the `home` directory.
+    pub fn risk(&self, home: Option<&Path>) -> u32 {
+        const MIB: u64 = 1024 * 1024;
+
+        let mut score = match self.size {
+            size if size >= 1024 * MIB => 2,
+            size if size >= 100 * MIB => 1,
+            _ => 0,
+        };
+        score += match self.entries {
+            entries if entries >= 10_000 => 2,
+            entries if entries >= 1_000 => 1,
+            _ => 0,
+        };
+
+        let names = self
+            .path
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .count();
+        if names <= 1 {
+            score += 3;
+        }
+        if let Some(home) = home {
+            if home.starts_with(&self.path) {
+                score += 3;
+            } else if self.path.parent() == Some(home) {
+                score += 2;
+            }
+        }
+        score
+    }
+
+    pub fn high_risk(&self, home: Option<&Path>) -> bool {
+        self.risk(home) >= Self::HIGH_RISK
+    }
+}
+
+/// The actions of a plan file, either a JSON array of them or one JSON
+/// object per line, as `jq -c` would print them.
+pub fn parse(text: &str) -> crate::Result<Vec<PlannedAction>> {
+    if text.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(text)?);
+    }
+    let mut actions = Vec::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        actions.push(serde_json::from_str(line)?);
+    }
+    Ok(actions)
+}
+
+/// Actions of one destination removed for the same reason, as a single
+/// line of a collapsed dry-run.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Group {
+    pub destination: PathBuf,
+    /// the paths as one pattern relative to the destination, e.g.
+    /// `src/**/target`, or the path itself for a group of one.
+    pub pattern: String,
+    pub reason: String,
+    pub count: usize,
+    /// size in bytes of all the actions.
+    pub size: u64,
+}
+
+/// `actions` grouped by destination and reason, in the order each group
+/// first shows up. Nothing is looked up on disk.
+pub fn collapse(actions: &[PlannedAction]) -> Vec<Group> {
+    let mut groups: Vec<(Group, Vec<&Path>)> = Vec::new();
+    for action in actions {
+        let found = groups.iter_mut().find(|(group, _)| {
+            group.destination == action.destination && group.reason == action.reason
+        });
+        let (group, paths) = match found {
+            Some(found) => found,
+            None => {
+                let group = Group {
+                    destination: action.destination.clone(),
+                    pattern: String::new(),
+                    reason: action.reason.clone(),
+                    count: 0,
+                    size: 0,
+                };
+                groups.push((group, Vec::new()));
+                groups.last_mut().unwrap()
+            }
+        };
+        group.count += 1;
+        group.size += action.size;
+        paths.push(
+            action
+                .path
+                .strip_prefix(&action.destination)
+                .unwrap_or(&action.path),
+        );
+    }
+
+    groups
+        .into_iter()
+        .map(|(mut group, paths)| {
+            group.pattern = self::pattern(&paths);
+            group
+        })
+        .collect()
+}
+
+/// `paths` as one pattern: the folder they share, `**` when they sit at
+/// different depths below it, then their name or `*` when names differ.
+fn pattern(paths: &[&Path]) -> String {
+    let parents: Vec<&Path> = paths
+        .iter()
+        .map(|path| path.parent().unwrap_or(Path::new("")))
+        .collect();
+    let mut common = parents[0].to_path_buf();
+    while !parents.iter().all(|parent| parent.starts_with(&common)) {
+        common.pop();
+    }
+
+    let name = paths[0].file_name().unwrap_or_default();
+    let mut pattern = common;
+    if parents.iter().any(|parent| *parent != pattern) {
+        pattern.push("**");
+    }
+    match paths
+        .iter()
+        .all(|path| path.file_name().unwrap_or_default() == name)
+    {
+        true => pattern.push(name),
+        false => pattern.push("*"),
+    }
+    pattern.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(path: &str, size: u64, entries: u64) -> PlannedAction {
+        PlannedAction {
+            destination: PathBuf::from("/home/me"),
+            path: PathBuf::from(path),
+            size,
+            reason: String::from("matched pattern 'x'"),
+            entries,
+            uncompressed: None,
+        }
+    }
+
+    #[test]
+    fn parse_lines() {
+        let actions = vec![
+            action("/home/me/a/target", 10, 2),
+            action("/home/me/b.log", 3, 0),
+        ];
+        let array = serde_json::to_string(&actions).unwrap();
+        assert_eq!(parse(&array).unwrap(), actions);
+
+        let lines: Vec<String> = actions
+            .iter()
+            .map(|a| serde_json::to_string(a).unwrap())
+            .collect();
+        let text = format!("{}\n\n{}\n", lines[0], lines[1]);
+        assert_eq!(parse(&text).unwrap(), actions);
+        assert!(parse("{\"path\": 1}").is_err());
+    }
+
+    #[test]
+    fn risk() {
+        let home = Some(Path::new("/home/me"));
+
+        // small and deep: nothing to worry about
+        let trivial = action("/home/me/app/target/debug.log", 10, 0);
+        assert_eq!(trivial.risk(home), 0);
+        assert!(!trivial.high_risk(home));
+
+        // large, right under home
+        let large = action("/home/me/Documents", 2 * 1024 * 1024 * 1024, 50);
+        assert!(large.high_risk(home));
+
+        // big and full of files, however deep
+        let crowded = action("/home/me/app/node_modules", 200 * 1024 * 1024, 20_000);
+        assert_eq!(crowded.risk(home), 3);
+        assert!(crowded.high_risk(home));
+
+        // home itself, or next to the root
+        assert!(action("/home", 0, 0).high_risk(home));
+        assert!(action("/tmp", 0, 0).high_risk(None));
+        assert!(!action("/tmp/cache", 0, 0).high_risk(None));
+    }
+
+    #[test]
+    fn collapse() {
+        let mut actions = vec![
+            action("/home/me/src/a/target", 300, 0),
+            action("/home/me/src/b/c/target", 200, 0),
+            action("/home/me/src/d/target", 100, 0),
+            action("/home/me/notes.txt", 5, 0),
+        ];
+        actions[3].reason = String::from("matched pattern 'txt'");
+        let groups = super::collapse(&actions);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].pattern, "src/**/target");
+        assert_eq!(groups[1].pattern, "notes.txt");
+
+        // the groups account for the plan exactly
+        let count: usize = groups.iter().map(|g| g.count).sum();
+        let size: u64 = groups.iter().map(|g| g.size).sum();
+        assert_eq!(count, actions.len());
+        assert_eq!(size, actions.iter().map(|a| a.size).sum::<u64>());
+        assert_eq!((groups[0].count, groups[0].size), (3, 600));
+
+        // same folder, different names
+        let logs = [
+            action("/home/me/a.log", 1, 0),
+            action("/home/me/b.log", 1, 0),
+        ];
+        assert_eq!(super::collapse(&logs)[0].pattern, "*");
+    }
+}
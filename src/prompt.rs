@@ -0,0 +1,53 @@
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    io::{self, BufRead, Write},
+    sync::Arc,
+};
+
+/// Asks the user a yes/no question.
+#[derive(Clone)]
+pub struct Prompt(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl Prompt {
+    pub fn new(ask: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(ask))
+    }
+
+    /// Asks on the terminal, anything but `y`/`yes` is a no. The question
+    /// goes to stderr, clear of output piped from stdout.
+    pub fn stdin() -> Self {
+        Self::new(|question| {
+            eprint!("{} [y/N] ", question);
+            let _ = io::stderr().flush();
+
+            let mut answer = String::new();
+            if io::stdin().lock().read_line(&mut answer).is_err() {
+                return false;
+            }
+            matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+        })
+    }
+
+    pub fn ask(&self, question: &str) -> bool {
+        (self.0)(question)
+    }
+}
+
+impl Default for Prompt {
+    fn default() -> Self {
+        Self::stdin()
+    }
+}
+
+impl Debug for Prompt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Prompt")
+    }
+}
+
+// how the question is asked doesn't change what a run does
+impl PartialEq for Prompt {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,180 @@
+use crate::ExecutionSummary;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Turns the summary of a run into the text printed at its end.
+pub trait SummaryRenderer {
+    /// The whole text, nothing gets printed when empty.
+    fn render(&self, summary: &ExecutionSummary) -> crate::Result<String>;
+}
+
+/// Shape of the summary printed at the end of a run.
+#[derive(Deserialize, Default, Debug, PartialEq, Clone, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryFormat {
+    /// a few colored lines for a terminal.
+    #[default]
+    Human,
+    /// the whole summary as a JSON document.
+    Json,
+    /// a single line of `key=value` pairs.
+    Logfmt,
+    /// totals per destination, with a header row.
+    Csv,
+    /// nothing at all.
+    Silent,
+}
+
+impl SummaryFormat {
+    pub fn renderer(&self) -> Box<dyn SummaryRenderer> {
+        match self {
+            Self::Human => Box::new(Human),
+            Self::Json => Box::new(Json),
+            Self::Logfmt => Box::new(Logfmt),
+            Self::Csv => Box::new(Csv),
+            Self::Silent => Box::new(Silent),
+        }
+    }
+}
+
+pub struct Human;
+
+impl SummaryRenderer for Human {
+    fn render(&self, summary: &ExecutionSummary) -> crate::Result<String> {
+        Ok(format!("\n{}", summary))
+    }
+}
+
+pub struct Json;
+
+impl SummaryRenderer for Json {
+    fn render(&self, summary: &ExecutionSummary) -> crate::Result<String> {
+        Ok(serde_json::to_string_pretty(summary)?)
+    }
+}
+
+pub struct Logfmt;
+
+impl SummaryRenderer for Logfmt {
+    fn render(&self, summary: &ExecutionSummary) -> crate::Result<String> {
+        let mut line = format!(
+            "dryrun={} removed={} bytes={} failed={} cancelled={} max_depth_reached={}",
+            summary.dryrun,
+            summary.removed,
+            summary.bytes,
+            summary.errors.len(),
+            summary.cancelled,
+            summary.max_depth_reached
+        );
+        if !summary.failed_steps.is_empty() {
+            line.push_str(&format!(" failed_steps={}", summary.failed_steps.len()));
+        }
+        if let Some((path, size)) = &summary.largest_item {
+            line.push_str(&format!(
+                " largest_item={} largest_bytes={}",
+                self::logfmt_value(path),
+                size
+            ));
+        }
+        Ok(line)
+    }
+}
+
+/// A path as a logfmt value, quoted when it has to be.
+fn logfmt_value(path: &Path) -> String {
+    let value = path.to_string_lossy();
+    if value.is_empty() || value.contains([' ', '=', '"']) {
+        format!("{:?}", value)
+    } else {
+        value.into_owned()
+    }
+}
+
+pub struct Csv;
+
+impl SummaryRenderer for Csv {
+    fn render(&self, summary: &ExecutionSummary) -> crate::Result<String> {
+        let mut csv = String::from("destination,removed,bytes");
+        for d in &summary.destinations {
+            let destination = d.destination.to_string_lossy();
+            csv.push_str(&format!(
+                "\n{},{},{}",
+                self::csv_field(&destination),
+                d.removed,
+                d.bytes
+            ));
+        }
+        csv.push_str(&format!("\n(total),{},{}", summary.removed, summary.bytes));
+        Ok(csv)
+    }
+}
+
+/// `field` quoted (and its quotes doubled) when it has to be.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub struct Silent;
+
+impl SummaryRenderer for Silent {
+    fn render(&self, _: &ExecutionSummary) -> crate::Result<String> {
+        Ok(String::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> ExecutionSummary {
+        let mut summary = ExecutionSummary::new(false);
+        summary.start("/pool/node");
+        summary.record("/pool/node/dist", 10);
+        summary.start("/pool/my, project");
+        summary.record("/pool/my, project/build out", 32);
+        summary.fail("/pool/my, project/locked", "locked");
+        summary.reach(2);
+        summary
+    }
+
+    #[test]
+    fn logfmt_and_csv() {
+        let summary = summary();
+
+        assert_eq!(
+            SummaryFormat::Logfmt.renderer().render(&summary).unwrap(),
+            "dryrun=false removed=2 bytes=42 failed=1 cancelled=false max_depth_reached=2 \
+             largest_item=\"/pool/my, project/build out\" largest_bytes=32"
+        );
+        assert_eq!(
+            SummaryFormat::Csv.renderer().render(&summary).unwrap(),
+            "destination,removed,bytes\n\
+             /pool/node,1,10\n\
+             \"/pool/my, project\",1,32\n\
+             (total),2,42"
+        );
+    }
+
+    #[test]
+    fn human_json_and_silent() {
+        let summary = summary();
+
+        let human = SummaryFormat::Human.renderer().render(&summary).unwrap();
+        assert_eq!(human, format!("\n{}", summary));
+        let json = SummaryFormat::Json.renderer().render(&summary).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["bytes"], 42);
+        assert!(
+            SummaryFormat::Silent
+                .renderer()
+                .render(&summary)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
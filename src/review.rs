@@ -0,0 +1,234 @@
+use crate::PlannedAction;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState},
+};
+
+/// A key the checklist reacts to, whatever the terminal sent.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Key {
+    Up,
+    Down,
+    Toggle,
+    All,
+    Invert,
+    Confirm,
+    Quit,
+}
+
+/// Where the checklist ends up after a key.
+#[derive(Debug, PartialEq)]
+pub enum Step {
+    Continue,
+    Confirm,
+    Quit,
+}
+
+/// Which planned actions are checked, and which one the cursor is on.
+/// Everything starts checked.
+#[derive(Debug, PartialEq)]
+pub struct Selection {
+    checked: Vec<bool>,
+    cursor: usize,
+}
+
+impl Selection {
+    pub fn new(len: usize) -> Self {
+        Self {
+            checked: vec![true; len],
+            cursor: 0,
+        }
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_checked(&self, index: usize) -> bool {
+        self.checked.get(index).copied().unwrap_or(false)
+    }
+
+    pub fn count(&self) -> usize {
+        self.checked.iter().filter(|checked| **checked).count()
+    }
+
+    /// Check every item, or uncheck them all when they already are.
+    pub fn all(&mut self) {
+        let all = self.count() < self.checked.len();
+        self.checked.fill(all);
+    }
+
+    pub fn invert(&mut self) {
+        self.checked
+            .iter_mut()
+            .for_each(|checked| *checked = !*checked);
+    }
+
+    pub fn toggle(&mut self) {
+        if let Some(checked) = self.checked.get_mut(self.cursor) {
+            *checked = !*checked;
+        }
+    }
+
+    pub fn apply(&mut self, key: Key) -> Step {
+        match key {
+            Key::Up => self.cursor = self.cursor.saturating_sub(1),
+            Key::Down => self.cursor = (self.cursor + 1).min(self.checked.len().saturating_sub(1)),
+            Key::Toggle => self.toggle(),
+            Key::All => self.all(),
+            Key::Invert => self.invert(),
+            Key::Confirm => return Step::Confirm,
+            Key::Quit => return Step::Quit,
+        }
+        Step::Continue
+    }
+
+    /// The checked items of `items`, in order.
+    pub fn chosen<T: Clone>(&self, items: &[T]) -> Vec<T> {
+        items
+            .iter()
+            .zip(&self.checked)
+            .filter(|(_, checked)| **checked)
+            .map(|(item, _)| item.clone())
+            .collect()
+    }
+}
+
+/// Show `actions` as a checklist on the terminal and return the ones left
+/// checked, or none when the user quits.
+pub fn pick(actions: &[PlannedAction]) -> crate::Result<Vec<PlannedAction>> {
+    let mut terminal = ratatui::try_init()?;
+    let result = self::run(&mut terminal, actions);
+    ratatui::try_restore()?;
+    result
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    actions: &[PlannedAction],
+) -> crate::Result<Vec<PlannedAction>> {
+    let mut selection = Selection::new(actions.len());
+    loop {
+        terminal.draw(|frame| self::draw(frame, actions, &selection))?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Up | KeyCode::Char('k') => Key::Up,
+                KeyCode::Down | KeyCode::Char('j') => Key::Down,
+                KeyCode::Char(' ') => Key::Toggle,
+                KeyCode::Char('a') => Key::All,
+                KeyCode::Char('i') => Key::Invert,
+                KeyCode::Enter => Key::Confirm,
+                KeyCode::Esc | KeyCode::Char('q') => Key::Quit,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        match selection.apply(key) {
+            Step::Continue => {}
+            Step::Confirm => return Ok(selection.chosen(actions)),
+            Step::Quit => return Ok(Vec::new()),
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, actions: &[PlannedAction], selection: &Selection) {
+    let [list, help] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+
+    let items: Vec<ListItem> = actions
+        .iter()
+        .enumerate()
+        .map(|(index, action)| {
+            let mark = if selection.is_checked(index) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            ListItem::new(format!(
+                "{} {} ({} bytes)",
+                mark,
+                action.path.display(),
+                action.size
+            ))
+        })
+        .collect();
+    let title = format!(
+        " {} of {} item(s) to remove ",
+        selection.count(),
+        actions.len()
+    );
+    let widget = List::new(items)
+        .block(Block::bordered().title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(selection.cursor()));
+    frame.render_stateful_widget(widget, list, &mut state);
+
+    let keys = "space toggle  a all  i invert  enter remove checked  q quit";
+    frame.render_widget(Line::from(keys), help);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_all_checked() {
+        let selection = Selection::new(3);
+        assert_eq!(selection.count(), 3);
+        assert_eq!(selection.cursor(), 0);
+        assert_eq!(selection.chosen(&["a", "b", "c"]), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn move_and_toggle() {
+        let mut selection = Selection::new(3);
+        assert_eq!(selection.apply(Key::Up), Step::Continue);
+        assert_eq!(selection.cursor(), 0);
+
+        selection.apply(Key::Down);
+        selection.apply(Key::Toggle);
+        assert!(!selection.is_checked(1));
+        // stops at the last item
+        for _ in 0..5 {
+            selection.apply(Key::Down);
+        }
+        assert_eq!(selection.cursor(), 2);
+        selection.apply(Key::Toggle);
+        assert_eq!(selection.chosen(&["a", "b", "c"]), vec!["a"]);
+
+        selection.apply(Key::Up);
+        selection.apply(Key::Toggle);
+        assert_eq!(selection.chosen(&["a", "b", "c"]), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn all_and_invert() {
+        let mut selection = Selection::new(3);
+        selection.apply(Key::All);
+        assert_eq!(selection.count(), 0);
+        selection.apply(Key::All);
+        assert_eq!(selection.count(), 3);
+
+        selection.apply(Key::Toggle);
+        selection.apply(Key::Invert);
+        assert_eq!(selection.chosen(&["a", "b", "c"]), vec!["a"]);
+        // some checked, so all checks the rest
+        selection.apply(Key::All);
+        assert_eq!(selection.count(), 3);
+    }
+
+    #[test]
+    fn confirm_or_quit() {
+        let mut selection = Selection::new(0);
+        assert_eq!(selection.apply(Key::Down), Step::Continue);
+        assert_eq!(selection.apply(Key::Toggle), Step::Continue);
+        assert_eq!(selection.apply(Key::Confirm), Step::Confirm);
+        assert_eq!(selection.apply(Key::Quit), Step::Quit);
+        assert!(selection.chosen::<&str>(&[]).is_empty());
+    }
+}
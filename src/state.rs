@@ -0,0 +1,155 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Directory kept between runs, `NEATEN_STATE_DIR` when set, otherwise the
+/// platform's usual place for application state.
+pub fn dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("NEATEN_STATE_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+
+    #[cfg(windows)]
+    let base = env::var_os("LOCALAPPDATA").map(PathBuf::from);
+    #[cfg(target_os = "macos")]
+    let base =
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"));
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let base = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")));
+    #[cfg(not(any(unix, windows)))]
+    let base: Option<PathBuf> = None;
+
+    base.map(|base| base.join(env!("CARGO_PKG_NAME")))
+}
+
+/// Fingerprints of the configs the user already confirmed once.
+#[derive(Debug, Default)]
+pub struct Trusted {
+    path: PathBuf,
+    fingerprints: BTreeSet<String>,
+}
+
+impl Trusted {
+    const FILE: &'static str = "trusted.json";
+
+    pub fn load<P: AsRef<Path>>(dir: P) -> crate::Result<Self> {
+        let path = dir.as_ref().join(Self::FILE);
+        let fingerprints = match fs::read_to_string(&path) {
+            Ok(json_data) => serde_json::from_str(&json_data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeSet::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path, fingerprints })
+    }
+
+    pub fn contains(&self, fingerprint: &str) -> bool {
+        self.fingerprints.contains(fingerprint)
+    }
+
+    pub fn insert(&mut self, fingerprint: String) -> crate::Result<()> {
+        self.fingerprints.insert(fingerprint);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(
+            &self.path,
+            serde_json::to_string_pretty(&self.fingerprints)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// When each config (by fingerprint) last ran to completion, in seconds
+/// since the Unix epoch.
+#[derive(Debug, Default)]
+pub struct LastRuns {
+    path: PathBuf,
+    runs: BTreeMap<String, u64>,
+}
+
+impl LastRuns {
+    const FILE: &'static str = "last_runs.json";
+
+    pub fn load<P: AsRef<Path>>(dir: P) -> crate::Result<Self> {
+        let path = dir.as_ref().join(Self::FILE);
+        let runs = match fs::read_to_string(&path) {
+            Ok(json_data) => serde_json::from_str(&json_data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path, runs })
+    }
+
+    pub fn get(&self, fingerprint: &str) -> Option<SystemTime> {
+        let seconds = self.runs.get(fingerprint)?;
+        Some(UNIX_EPOCH + Duration::from_secs(*seconds))
+    }
+
+    pub fn set(&mut self, fingerprint: String, time: SystemTime) -> crate::Result<()> {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.runs.insert(fingerprint, seconds);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.runs)?)?;
+        Ok(())
+    }
+}
+
+/// Modification times of the folders a walk of one config found nothing to
+/// remove in, in nanoseconds since the Unix epoch. Kept by the config's
+/// fingerprint rather than its destination alone: the fingerprint covers
+/// the destination too, and a folder clean for the patterns of one config
+/// may well hold matches of another on the same destination.
+#[derive(Debug, Default)]
+pub struct Index {
+    path: PathBuf,
+    folders: BTreeMap<PathBuf, u128>,
+}
+
+impl Index {
+    pub fn load<P: AsRef<Path>>(dir: P, fingerprint: &str) -> crate::Result<Self> {
+        let path = dir.as_ref().join(format!("index-{}.json", fingerprint));
+        let folders = match fs::read_to_string(&path) {
+            Ok(json_data) => serde_json::from_str(&json_data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path, folders })
+    }
+
+    /// Whether `folder` is in the index, modified at `modified` still.
+    pub fn unchanged(&self, folder: &Path, modified: SystemTime) -> bool {
+        self.folders.get(folder) == Some(&Self::nanos(modified))
+    }
+
+    pub fn insert(&mut self, folder: PathBuf, modified: SystemTime) {
+        self.folders.insert(folder, Self::nanos(modified));
+    }
+
+    pub fn remove(&mut self, folder: &Path) {
+        self.folders.remove(folder);
+    }
+
+    pub fn save(&self) -> crate::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.folders)?)?;
+        Ok(())
+    }
+
+    fn nanos(time: SystemTime) -> u128 {
+        time.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    }
+}
@@ -0,0 +1,355 @@
+use crate::PlannedAction;
+use serde::{Serialize, Serializer};
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Outcome of a `Manager::execute` run.
+#[derive(Serialize, Debug, Default, PartialEq, Clone)]
+pub struct ExecutionSummary {
+    /// whether the run was a dry-run (nothing actually removed).
+    pub dryrun: bool,
+    /// number of items removed (or would be removed in dry-run).
+    pub removed: usize,
+    /// total size of the removed items in bytes.
+    pub bytes: u64,
+    /// the single largest removed item and its size in bytes.
+    pub largest_item: Option<(PathBuf, u64)>,
+    /// deepest level visited below a destination (its children are level 1).
+    pub max_depth_reached: usize,
+    /// every removed item with its size, in removal order.
+    pub items: Vec<(PathBuf, u64)>,
+    /// items moved into quarantine, with where each went.
+    pub quarantined: Vec<(PathBuf, PathBuf)>,
+    /// items that couldn't be removed, with the reason.
+    pub errors: Vec<(PathBuf, String)>,
+    /// what else failed for a destination, none of it an item: its `after`
+    /// command, too few matches, its sentinel left unwritten.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed_steps: Vec<(PathBuf, String)>,
+    /// destinations left alone for holding the sentinel file.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<PathBuf>,
+    /// free space of the volumes a dry-run would free space on, once the
+    /// run went through.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<VolumeSummary>,
+    /// with `--sample`, how many of the matches it picked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample: Option<SampleSummary>,
+    /// whether the run was interrupted before visiting everything.
+    pub cancelled: bool,
+    /// the same totals broken down per destination, in run order.
+    pub destinations: Vec<DestinationSummary>,
+    /// SHA-256 of the resolved configs of the run, when it ran any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// wall-clock time the run took, in seconds once serialized.
+    #[serde(serialize_with = "seconds")]
+    pub duration: Duration,
+    /// items removed (or found, in dry-run) per second.
+    pub items_per_sec: f64,
+    /// bytes removed (or found, in dry-run) per second.
+    pub bytes_per_sec: f64,
+    /// what a dry-run found, kept for its output to be collapsed at the end.
+    #[serde(skip)]
+    pub(crate) planned: Vec<PlannedAction>,
+    /// index in `destinations` new items are accounted to.
+    #[serde(skip)]
+    current: Option<usize>,
+}
+
+/// Totals of a single destination.
+#[derive(Serialize, Debug, Default, PartialEq, Clone)]
+pub struct DestinationSummary {
+    pub destination: PathBuf,
+    pub removed: usize,
+    pub bytes: u64,
+}
+
+/// Free space of one volume, now and projected after the run.
+#[derive(Serialize, Debug, Default, PartialEq, Clone)]
+pub struct VolumeSummary {
+    /// destinations on the volume, in run order.
+    pub destinations: Vec<PathBuf>,
+    /// bytes available on it now.
+    pub free: u64,
+    /// bytes the run frees on it.
+    pub freed: u64,
+    /// `free` plus `freed`.
+    pub projected_free: u64,
+}
+
+/// Matches of a `--sample` run, and how many of them it picked.
+#[derive(Serialize, Debug, Default, PartialEq, Clone)]
+pub struct SampleSummary {
+    /// seed the picks came from, to pick the same ones again.
+    pub seed: u64,
+    pub sampled: usize,
+    pub matched: usize,
+}
+
+impl ExecutionSummary {
+    pub fn new(dryrun: bool) -> Self {
+        Self {
+            dryrun,
+            ..Default::default()
+        }
+    }
+
+    /// Account the following items to `destination`, which shows up once
+    /// however many configs share it.
+    pub(crate) fn start<P: AsRef<Path>>(&mut self, destination: P) {
+        let destination = destination.as_ref();
+        let index = match self
+            .destinations
+            .iter()
+            .position(|d| d.destination == destination)
+        {
+            Some(index) => index,
+            None => {
+                self.destinations.push(DestinationSummary {
+                    destination: destination.to_path_buf(),
+                    ..Default::default()
+                });
+                self.destinations.len() - 1
+            }
+        };
+        self.current = Some(index);
+    }
+
+    pub(crate) fn record<P: AsRef<Path>>(&mut self, path: P, size: u64) {
+        let path = path.as_ref().to_path_buf();
+        self.removed += 1;
+        self.bytes += size;
+        if let Some(current) = self.current.and_then(|i| self.destinations.get_mut(i)) {
+            current.removed += 1;
+            current.bytes += size;
+        }
+
+        let larger = match &self.largest_item {
+            Some((_, largest)) => size > *largest,
+            None => true,
+        };
+        if larger {
+            self.largest_item = Some((path.clone(), size));
+        }
+        self.items.push((path, size));
+    }
+
+    /// Take back the last `record` of `path`, an item which is in place
+    /// again.
+    pub(crate) fn unrecord(&mut self, path: &Path) {
+        let Some(index) = self.items.iter().rposition(|(item, _)| item == path) else {
+            return;
+        };
+        let (_, size) = self.items.remove(index);
+        self.removed -= 1;
+        self.bytes -= size;
+        if let Some(destination) = self
+            .destinations
+            .iter_mut()
+            .filter(|d| path.starts_with(&d.destination))
+            .max_by_key(|d| d.destination.components().count())
+        {
+            destination.removed = destination.removed.saturating_sub(1);
+            destination.bytes = destination.bytes.saturating_sub(size);
+        }
+        if self
+            .largest_item
+            .as_ref()
+            .is_some_and(|(largest, _)| largest == path)
+        {
+            // the first of the largest left, as `record` keeps it
+            self.largest_item = self
+                .items
+                .iter()
+                .rev()
+                .max_by_key(|(_, size)| *size)
+                .cloned();
+        }
+    }
+
+    /// Add the totals of `part`, another piece of the same run.
+    pub(crate) fn merge(&mut self, part: ExecutionSummary) {
+        for destination in &part.destinations {
+            self.start(&destination.destination);
+            if let Some(current) = self.current.and_then(|i| self.destinations.get_mut(i)) {
+                current.removed += destination.removed;
+                current.bytes += destination.bytes;
+            }
+        }
+        self.removed += part.removed;
+        self.bytes += part.bytes;
+        if let Some((path, size)) = part.largest_item
+            && self
+                .largest_item
+                .as_ref()
+                .is_none_or(|(_, largest)| size > *largest)
+        {
+            self.largest_item = Some((path, size));
+        }
+        self.max_depth_reached = self.max_depth_reached.max(part.max_depth_reached);
+        self.items.extend(part.items);
+        self.planned.extend(part.planned);
+        self.quarantined.extend(part.quarantined);
+        self.errors.extend(part.errors);
+        self.failed_steps.extend(part.failed_steps);
+        self.cancelled |= part.cancelled;
+        if let Some(sample) = part.sample {
+            let total = self.sample.get_or_insert(SampleSummary {
+                seed: sample.seed,
+                ..Default::default()
+            });
+            total.sampled += sample.sampled;
+            total.matched += sample.matched;
+        }
+        for destination in part.skipped {
+            if !self.skipped.contains(&destination) {
+                self.skipped.push(destination);
+            }
+        }
+    }
+
+    pub(crate) fn fail<P: AsRef<Path>>(&mut self, path: P, error: impl Into<String>) {
+        self.errors
+            .push((path.as_ref().to_path_buf(), error.into()));
+    }
+
+    pub(crate) fn fail_step<P: AsRef<Path>>(&mut self, path: P, error: impl Into<String>) {
+        self.failed_steps
+            .push((path.as_ref().to_path_buf(), error.into()));
+    }
+
+    /// Failed items and failed steps alike.
+    pub(crate) fn failures(&self) -> usize {
+        self.errors.len() + self.failed_steps.len()
+    }
+
+    pub(crate) fn reach(&mut self, depth: usize) {
+        self.max_depth_reached = self.max_depth_reached.max(depth);
+    }
+
+    /// The one line printed when the full summary goes to a report file:
+    /// the totals, and how many items and steps failed if any.
+    pub fn brief(&self) -> String {
+        let action = if self.dryrun {
+            "Would remove"
+        } else {
+            "Removed"
+        };
+        let mut line = format!(
+            "\u{1b}[1m{}\u{1b}[0m {} item(s), {} byte(s)",
+            action, self.removed, self.bytes
+        );
+        if !self.errors.is_empty() {
+            line.push_str(&format!(", {} failed", self.errors.len()));
+        }
+        if !self.failed_steps.is_empty() {
+            line.push_str(&format!(", {} step(s) failed", self.failed_steps.len()));
+        }
+        if self.cancelled {
+            line.push_str(", interrupted");
+        }
+        line
+    }
+
+    /// Record how long the run took and the throughput that makes.
+    pub(crate) fn time(&mut self, duration: Duration) {
+        self.duration = duration;
+        let seconds = duration.as_secs_f64();
+        if seconds > 0.0 {
+            self.items_per_sec = self.removed as f64 / seconds;
+            self.bytes_per_sec = self.bytes as f64 / seconds;
+        }
+    }
+}
+
+fn seconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+impl Display for ExecutionSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let action = if self.dryrun {
+            "Would remove"
+        } else {
+            "Removed"
+        };
+        writeln!(
+            f,
+            "\u{1b}[1m{}\u{1b}[0m {} item(s), {} byte(s)",
+            action, self.removed, self.bytes
+        )?;
+        if self.destinations.len() > 1 {
+            for d in &self.destinations {
+                writeln!(
+                    f,
+                    "  {:?}: {} item(s), {} byte(s)",
+                    d.destination, d.removed, d.bytes
+                )?;
+            }
+        }
+        if let Some(sample) = &self.sample {
+            writeln!(
+                f,
+                "\u{1b}[1mSampled\u{1b}[0m {} of {} match(es) (seed {})",
+                sample.sampled, sample.matched, sample.seed
+            )?;
+        }
+        if self.cancelled {
+            writeln!(f, "\u{1b}[1m\u{1b}[33mInterrupted\u{1b}[0m before the end")?;
+        }
+        if !self.errors.is_empty() {
+            writeln!(
+                f,
+                "\u{1b}[1m\u{1b}[31mFailed\u{1b}[0m {} item(s)",
+                self.errors.len()
+            )?;
+            for (path, error) in &self.errors {
+                writeln!(f, "  {:?}: {}", path, error)?;
+            }
+        }
+        if !self.failed_steps.is_empty() {
+            writeln!(
+                f,
+                "\u{1b}[1m\u{1b}[31mFailed\u{1b}[0m {} step(s)",
+                self.failed_steps.len()
+            )?;
+            for (path, error) in &self.failed_steps {
+                writeln!(f, "  {:?}: {}", path, error)?;
+            }
+        }
+        for destination in &self.skipped {
+            writeln!(
+                f,
+                "\u{1b}[1m\u{1b}[33mSkipped\u{1b}[0m {:?}, paused by its sentinel file",
+                destination
+            )?;
+        }
+        for volume in &self.volumes {
+            writeln!(
+                f,
+                "\u{1b}[1mProjected free\u{1b}[0m {} byte(s) on the volume of {:?} (now {})",
+                volume.projected_free, volume.destinations[0], volume.free
+            )?;
+        }
+        if let Some((path, size)) = &self.largest_item {
+            writeln!(f, "\u{1b}[1mLargest\u{1b}[0m {:?} ({} bytes)", path, size)?;
+        }
+        writeln!(
+            f,
+            "\u{1b}[1mDeepest level\u{1b}[0m {}",
+            self.max_depth_reached
+        )?;
+        write!(
+            f,
+            "\u{1b}[1mTook\u{1b}[0m {:.2}s ({:.1} item(s)/s, {:.2} MB/s)",
+            self.duration.as_secs_f64(),
+            self.items_per_sec,
+            self.bytes_per_sec / 1_000_000.0
+        )
+    }
+}
@@ -0,0 +1,38 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// `text` composed (NFC), to match a name typed one way against the same
+/// name stored another, e.g. `café` as macOS keeps it, decomposed.
+pub fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nfd(text: &str) -> String {
+        text.nfd().collect()
+    }
+
+    #[test]
+    fn normal_forms() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+        assert_eq!(nfc(decomposed), composed);
+        assert_eq!(nfc(composed), composed);
+        assert_eq!(nfd(composed), decomposed);
+
+        // two marks, the inner letter itself composed
+        assert_eq!(nfd("\u{1ec7}"), "e\u{323}\u{302}");
+        assert_eq!(nfc("e\u{323}\u{302}"), "\u{1ec7}");
+
+        // beyond Latin too: Greek, and Hangul syllables
+        assert_eq!(nfc("\u{3b1}\u{301}"), "\u{3ac}");
+        assert_eq!(nfc("\u{1112}\u{1161}\u{11ab}"), "\u{d55c}");
+        assert_eq!(nfd("\u{d55c}"), "\u{1112}\u{1161}\u{11ab}");
+
+        // anything else is left alone
+        assert_eq!(nfc("build-01_x.log"), "build-01_x.log");
+        assert_eq!(nfc("\u{301}a"), "\u{301}a");
+    }
+}